@@ -0,0 +1,252 @@
+//! Session server authentication for online mode servers.
+//!
+//! When a server sends an Encryption Request, the client has to tell the
+//! session server that it is joining before answering, and the server then
+//! asks the session server whether that happened. Both halves go through a
+//! [`SessionService`] so they can be swapped out for [`MockSessionService`]
+//! in tests.
+//!
+//! See <https://wiki.vg/Protocol_Encryption#Authentication>.
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::client::GameProfileProperty;
+
+pub const MOJANG_SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("Session server request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Session server rejected join ({status}): {message}")]
+    Rejected { status: u16, message: String },
+    #[error("Mock session state poisoned")]
+    Poisoned,
+}
+
+/// A player's identity as known to the session server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameProfile {
+    pub id: Uuid,
+    pub name: String,
+    pub properties: Vec<GameProfileProperty>,
+}
+
+#[async_trait]
+pub trait SessionService: Debug + Send + Sync {
+    /// Called by the client: announce that it is joining the server
+    /// identified by `server_hash`.
+    async fn join_server(&self, server_hash: &str) -> Result<(), AuthError>;
+
+    /// Called by the server: check whether `username` announced a join with
+    /// `server_hash`, returning their profile if they did.
+    async fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+    ) -> Result<Option<GameProfile>, AuthError>;
+}
+
+/// Compute the "server hash" sent to the session server. It is a SHA-1 digest
+/// of the server id, the shared secret and the server's public key, printed
+/// as a signed (two's complement) hex number.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::server_hash;
+///
+/// assert_eq!(server_hash("Notch", &[], &[]), "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48");
+/// assert_eq!(server_hash("jeb_", &[], &[]), "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1");
+/// assert_eq!(server_hash("simon", &[], &[]), "88e16a1019277b15d58faf0541e11910eb756f6");
+/// ```
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(server_id.as_bytes());
+    hasher.update(shared_secret);
+    hasher.update(public_key);
+    let mut digest: [u8; 20] = hasher.finalize().into();
+
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        // two's complement
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            *byte = !*byte;
+            if carry {
+                (*byte, carry) = byte.overflowing_add(1);
+            }
+        }
+    }
+
+    let hex: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+    let hex = hex.trim_start_matches('0');
+    if negative {
+        format!("-{hex}")
+    } else {
+        hex.to_string()
+    }
+}
+
+#[derive(Deserialize)]
+struct ProfileResponse {
+    id: Uuid,
+    name: String,
+    #[serde(default)]
+    properties: Vec<PropertyResponse>,
+}
+
+#[derive(Deserialize)]
+struct PropertyResponse {
+    name: String,
+    value: String,
+    signature: Option<String>,
+}
+
+/// Talks to the real session server (or anything with the same API).
+#[derive(Debug, Clone)]
+pub struct MojangSessionService {
+    access_token: String,
+    profile_id: Uuid,
+    endpoint: String,
+    http: reqwest::Client,
+}
+
+impl MojangSessionService {
+    /// `access_token` is the Minecraft access token of the account whose
+    /// profile has the id `profile_id`. Getting one (through the Microsoft
+    /// OAuth flow) is up to the caller.
+    pub fn new(access_token: &str, profile_id: Uuid) -> Self {
+        Self::with_endpoint(access_token, profile_id, MOJANG_SESSION_SERVER)
+    }
+
+    pub fn with_endpoint(access_token: &str, profile_id: Uuid, endpoint: &str) -> Self {
+        MojangSessionService {
+            access_token: access_token.to_string(),
+            profile_id,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionService for MojangSessionService {
+    async fn join_server(&self, server_hash: &str) -> Result<(), AuthError> {
+        let res = self
+            .http
+            .post(format!("{}/session/minecraft/join", self.endpoint))
+            .json(&json!({
+                "accessToken": self.access_token,
+                "selectedProfile": self.profile_id.simple().to_string(),
+                "serverId": server_hash,
+            }))
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            return Err(AuthError::Rejected {
+                status: res.status().as_u16(),
+                message: res.text().await.unwrap_or_default(),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+    ) -> Result<Option<GameProfile>, AuthError> {
+        let res = self
+            .http
+            .get(format!("{}/session/minecraft/hasJoined", self.endpoint))
+            .query(&[("username", username), ("serverId", server_hash)])
+            .send()
+            .await?;
+
+        // the session server answers 204 No Content when the player didn't join
+        if res.status() == reqwest::StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+        let profile: ProfileResponse = res.error_for_status()?.json().await?;
+
+        Ok(Some(GameProfile {
+            id: profile.id,
+            name: profile.name,
+            properties: profile
+                .properties
+                .into_iter()
+                .map(|property| GameProfileProperty {
+                    name: property.name,
+                    value: property.value,
+                    signature: property.signature,
+                })
+                .collect(),
+        }))
+    }
+}
+
+/// An in-process stand-in for the session server. Clones share their state,
+/// so one clone can be handed to a [`Client`](crate::Client) and another to a
+/// fake server, which then sees the client's joins.
+#[derive(Debug, Clone)]
+pub struct MockSessionService {
+    profile: GameProfile,
+    joins: Arc<Mutex<HashMap<String, GameProfile>>>,
+}
+
+impl MockSessionService {
+    /// `profile` is who [`SessionService::join_server`] joins as.
+    pub fn new(profile: GameProfile) -> Self {
+        MockSessionService {
+            profile,
+            joins: Arc::default(),
+        }
+    }
+
+    /// Every server hash that has been joined so far.
+    pub fn joined_hashes(&self) -> Vec<String> {
+        self.joins
+            .lock()
+            .map(|joins| joins.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl SessionService for MockSessionService {
+    async fn join_server(&self, server_hash: &str) -> Result<(), AuthError> {
+        self.joins
+            .lock()
+            .map_err(|_| AuthError::Poisoned)?
+            .insert(server_hash.to_string(), self.profile.clone());
+        Ok(())
+    }
+
+    async fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+    ) -> Result<Option<GameProfile>, AuthError> {
+        Ok(self
+            .joins
+            .lock()
+            .map_err(|_| AuthError::Poisoned)?
+            .get(server_hash)
+            .filter(|profile| profile.name == username)
+            .cloned())
+    }
+}
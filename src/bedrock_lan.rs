@@ -0,0 +1,293 @@
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use futures::stream::{self, BoxStream, StreamExt};
+use tokio::{
+    net::{ToSocketAddrs, UdpSocket},
+    time::{self, Interval, MissedTickBehavior},
+};
+
+use crate::{
+    error::{Error, IoResultExt, Phase},
+    ping_bedrock::PingBedrock,
+    raknet::{parse_ping, parse_pong, ping_packet, pong_packet},
+};
+
+/// The port Bedrock clients look for LAN games on.
+pub const BEDROCK_LAN_PORT: u16 = 19132;
+
+/// How often [`BedrockLan`] pings its targets unless told otherwise.
+pub const DEFAULT_BEDROCK_LAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where a [`BedrockLanAdvertiser`] gets the advertisement it answers pings
+/// with, in the `MCPE;motd;protocol;version;...` form [`PingBedrock::parse`]
+/// reads. Implemented for [`String`], for fixed answers, and for closures
+/// returning one, for answers that change (like a proxy passing on its
+/// backend's).
+pub trait BedrockAdvertisement: Send + Sync {
+    fn advertisement(&self) -> String;
+}
+
+impl BedrockAdvertisement for String {
+    fn advertisement(&self) -> String {
+        self.clone()
+    }
+}
+
+impl<F> BedrockAdvertisement for F
+where
+    F: Fn() -> String + Send + Sync,
+{
+    fn advertisement(&self) -> String {
+        self()
+    }
+}
+
+/// A server that answered a [`BedrockLan`] ping.
+#[derive(Debug)]
+pub struct BedrockLanServer {
+    /// Where the answer came from.
+    pub addr: SocketAddr,
+    pub server_guid: i64,
+    pub ping: PingBedrock,
+}
+
+/// Finds Bedrock games on the local network the way clients do: by pinging
+/// the broadcast address on [`BEDROCK_LAN_PORT`] over and over and listening
+/// for whoever answers.
+///
+/// Bind to an interface's address to search on that interface, and merge
+/// the streams of several to search on more than one.
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use minecraft_utilities::BedrockLan;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), minecraft_utilities::Error> {
+/// let mut servers = BedrockLan::bind("192.168.1.10:0")
+///     .await?
+///     .with_targets(["192.168.1.255:19132".parse().unwrap()])
+///     .discover();
+/// while let Some(server) = servers.next().await {
+///     let server = server?;
+///     println!("{}: {}", server.addr, server.ping.motd);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BedrockLan {
+    socket: UdpSocket,
+    targets: Vec<SocketAddr>,
+    interval: Duration,
+    guid: i64,
+}
+
+impl BedrockLan {
+    /// Bind the socket pings are sent from, with broadcasting allowed. The
+    /// only target is `255.255.255.255:19132` until set.
+    pub async fn bind(addr: impl ToSocketAddrs) -> Result<Self, Error> {
+        let socket = UdpSocket::bind(addr).await.during(Phase::BedrockPing)?;
+        socket.set_broadcast(true).during(Phase::BedrockPing)?;
+        Ok(BedrockLan {
+            socket,
+            targets: vec![(Ipv4Addr::BROADCAST, BEDROCK_LAN_PORT).into()],
+            interval: DEFAULT_BEDROCK_LAN_INTERVAL,
+            guid: rand::random(),
+        })
+    }
+
+    /// Where pings are sent, broadcast addresses or single servers.
+    pub fn with_targets(mut self, targets: impl IntoIterator<Item = SocketAddr>) -> Self {
+        self.targets = targets.into_iter().collect();
+        self
+    }
+
+    /// How long to wait between rounds of pings. Defaults to
+    /// [`DEFAULT_BEDROCK_LAN_INTERVAL`].
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The client GUID sent with pings. Random unless set.
+    pub fn with_guid(mut self, guid: i64) -> Self {
+        self.guid = guid;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.socket.local_addr().during(Phase::BedrockPing)
+    }
+
+    /// Ping the targets every interval, starting now, and yield every answer
+    /// as it arrives. Servers show up again each round they answer.
+    ///
+    /// Failing to send a round of pings yields an error but the search goes
+    /// on, failing to receive ends it. Answers that can't be parsed are
+    /// skipped.
+    pub fn discover(self) -> BoxStream<'static, Result<BedrockLanServer, Error>> {
+        let mut ticker = time::interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let discovery = Discovery {
+            lan: self,
+            ticker,
+            started: Instant::now(),
+            buf: vec![0; 2048],
+            stopped: false,
+        };
+        stream::unfold(discovery, |mut discovery| async move {
+            if discovery.stopped {
+                return None;
+            }
+            let res = discovery.next().await;
+            Some((res, discovery))
+        })
+        .boxed()
+    }
+}
+
+struct Discovery {
+    lan: BedrockLan,
+    ticker: Interval,
+    /// Pings carry the milliseconds since this, so answers tell how long
+    /// they took.
+    started: Instant,
+    buf: Vec<u8>,
+    /// Set when receiving fails, which ends the stream after the error.
+    stopped: bool,
+}
+
+impl Discovery {
+    async fn next(&mut self) -> Result<BedrockLanServer, Error> {
+        loop {
+            tokio::select! {
+                _ = self.ticker.tick() => self.ping_targets().await?,
+                received = self.lan.socket.recv_from(&mut self.buf) => {
+                    let (len, from) = match received {
+                        Ok(received) => received,
+                        // ICMP port unreachable for an earlier ping, on Windows
+                        Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+                        Err(err) => {
+                            self.stopped = true;
+                            return Err(Error::io(Phase::BedrockPing, err));
+                        }
+                    };
+                    let Some(pong) = parse_pong(&self.buf[..len]) else {
+                        continue;
+                    };
+                    let response_time = self.elapsed().saturating_sub(pong.time).max(0);
+                    let Ok(ping) = PingBedrock::parse(&pong.advertisement, response_time) else {
+                        continue;
+                    };
+                    return Ok(BedrockLanServer {
+                        addr: from,
+                        server_guid: pong.server_guid,
+                        ping,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Ping every target even if some fail, then report the first failure.
+    async fn ping_targets(&self) -> Result<(), Error> {
+        let packet = ping_packet(self.elapsed(), self.lan.guid);
+        let mut res = Ok(());
+        for &target in &self.lan.targets {
+            if let Err(err) = self.lan.socket.send_to(&packet, target).await {
+                let err = io::Error::new(err.kind(), format!("Couldn't ping {target}: {err}"));
+                res = res.and(Err(Error::io(Phase::BedrockPing, err)));
+            }
+        }
+        res
+    }
+
+    fn elapsed(&self) -> i64 {
+        self.started.elapsed().as_millis() as i64
+    }
+}
+
+/// Answers Bedrock pings with an advertisement, so clients list it as a LAN
+/// game (when bound to [`BEDROCK_LAN_PORT`]) or a server.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::{BedrockLanAdvertiser, PingBedrock, ServerAddress};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), minecraft_utilities::Error> {
+/// let advertisement =
+///     "MCPE;My Proxy;594;1.20.12;0;10;1;Lobby;Survival;1;19132;19133;".to_string();
+/// let advertiser = BedrockLanAdvertiser::bind("127.0.0.1:0", advertisement).await?;
+/// let addr = ServerAddress::from(advertiser.local_addr()?);
+/// tokio::spawn(advertiser.run());
+///
+/// assert_eq!(PingBedrock::ping(&addr).await?.motd, "My Proxy\nLobby");
+/// # Ok(())
+/// # }
+/// ```
+pub struct BedrockLanAdvertiser {
+    socket: UdpSocket,
+    advertisement: Box<dyn BedrockAdvertisement>,
+    guid: i64,
+}
+
+impl std::fmt::Debug for BedrockLanAdvertiser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BedrockLanAdvertiser")
+            .field("socket", &self.socket)
+            .field("guid", &self.guid)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BedrockLanAdvertiser {
+    /// Bind `0.0.0.0:19132` to be found by clients on the local network.
+    pub async fn bind(
+        addr: impl ToSocketAddrs,
+        advertisement: impl BedrockAdvertisement + 'static,
+    ) -> Result<Self, Error> {
+        Ok(BedrockLanAdvertiser {
+            socket: UdpSocket::bind(addr).await.during(Phase::BedrockPing)?,
+            advertisement: Box::new(advertisement),
+            guid: rand::random(),
+        })
+    }
+
+    /// The server GUID sent with pongs. Random unless set.
+    pub fn with_guid(mut self, guid: i64) -> Self {
+        self.guid = guid;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.socket.local_addr().during(Phase::BedrockPing)
+    }
+
+    /// Answer pings until receiving fails.
+    pub async fn run(self) -> Result<(), Error> {
+        let mut buf = vec![0; 1500];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                // ICMP port unreachable for an earlier pong, on Windows
+                Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(err) => return Err(Error::io(Phase::BedrockPing, err)),
+            };
+            let Some((time, _client_guid)) = parse_ping(&buf[..len]) else {
+                continue;
+            };
+            let pong = pong_packet(time, self.guid, &self.advertisement.advertisement());
+            // the sender being gone is their problem
+            let _ = self.socket.send_to(&pong, from).await;
+        }
+    }
+}
@@ -0,0 +1,962 @@
+//! Chat components, the rich text format used for MOTDs, kick reasons and
+//! pretty much every other piece of text the server sends.
+//!
+//! Both the JSON form (<https://wiki.vg/Text_formatting>) and the legacy
+//! `§`-coded form are parsed into the same [`ChatComponent`] tree, which can
+//! then be rendered as plain text, ANSI escape codes, HTML, or back into
+//! `§` codes.
+
+use std::fmt::{Display, Write};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::nbt::Tag;
+
+/// The character that starts a legacy formatting code.
+pub const SECTION_SIGN: char = '§';
+
+/// A node in a chat component tree.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::ChatComponent;
+///
+/// let json = ChatComponent::parse(r#"{"text":"Hello ","extra":[{"text":"world","color":"red","bold":true}]}"#);
+/// assert_eq!(json.to_plain(), "Hello world");
+/// assert_eq!(json.to_legacy(), "Hello §c§lworld");
+///
+/// let legacy = ChatComponent::parse("§aA §lMinecraft§r Server");
+/// assert_eq!(legacy.to_plain(), "A Minecraft Server");
+/// assert_eq!(legacy.to_legacy(), "§aA §lMinecraft§r Server");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatComponent {
+    pub content: ChatContent,
+    pub style: ChatStyle,
+    pub extra: Vec<ChatComponent>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatContent {
+    Text(String),
+    Translate {
+        key: String,
+        fallback: Option<String>,
+        with: Vec<ChatComponent>,
+    },
+    Keybind(String),
+    Score {
+        name: String,
+        objective: String,
+        value: Option<String>,
+    },
+    Selector {
+        selector: String,
+        separator: Option<Box<ChatComponent>>,
+    },
+}
+
+impl Default for ChatContent {
+    fn default() -> Self {
+        ChatContent::Text(String::new())
+    }
+}
+
+/// Formatting applied to a component. `None` means "inherit from the parent".
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatStyle {
+    pub color: Option<ChatColor>,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub underlined: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub obfuscated: Option<bool>,
+    pub font: Option<String>,
+    pub insertion: Option<String>,
+    pub click_event: Option<ClickEvent>,
+    pub hover_event: Option<HoverEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatColor {
+    Named(NamedColor),
+    Hex(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedColor {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClickEvent {
+    pub action: ClickAction,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClickAction {
+    OpenUrl,
+    OpenFile,
+    RunCommand,
+    SuggestCommand,
+    ChangePage,
+    CopyToClipboard,
+    Unknown(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HoverEvent {
+    ShowText(Box<ChatComponent>),
+    ShowItem(Value),
+    ShowEntity(Value),
+    Unknown { action: String, contents: Value },
+}
+
+const NAMED_COLORS: [(NamedColor, char, &str, u32); 16] = [
+    (NamedColor::Black, '0', "black", 0x000000),
+    (NamedColor::DarkBlue, '1', "dark_blue", 0x0000AA),
+    (NamedColor::DarkGreen, '2', "dark_green", 0x00AA00),
+    (NamedColor::DarkAqua, '3', "dark_aqua", 0x00AAAA),
+    (NamedColor::DarkRed, '4', "dark_red", 0xAA0000),
+    (NamedColor::DarkPurple, '5', "dark_purple", 0xAA00AA),
+    (NamedColor::Gold, '6', "gold", 0xFFAA00),
+    (NamedColor::Gray, '7', "gray", 0xAAAAAA),
+    (NamedColor::DarkGray, '8', "dark_gray", 0x555555),
+    (NamedColor::Blue, '9', "blue", 0x5555FF),
+    (NamedColor::Green, 'a', "green", 0x55FF55),
+    (NamedColor::Aqua, 'b', "aqua", 0x55FFFF),
+    (NamedColor::Red, 'c', "red", 0xFF5555),
+    (NamedColor::LightPurple, 'd', "light_purple", 0xFF55FF),
+    (NamedColor::Yellow, 'e', "yellow", 0xFFFF55),
+    (NamedColor::White, 'f', "white", 0xFFFFFF),
+];
+
+impl NamedColor {
+    fn entry(&self) -> &'static (NamedColor, char, &'static str, u32) {
+        NAMED_COLORS
+            .iter()
+            .find(|(color, ..)| color == self)
+            .expect("every named color is in the table")
+    }
+
+    pub fn from_code(code: char) -> Option<Self> {
+        let code = code.to_ascii_lowercase();
+        NAMED_COLORS
+            .iter()
+            .find(|(_, c, ..)| *c == code)
+            .map(|(color, ..)| *color)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        NAMED_COLORS
+            .iter()
+            .find(|(_, _, n, _)| *n == name)
+            .map(|(color, ..)| *color)
+    }
+
+    pub fn code(&self) -> char {
+        self.entry().1
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.entry().2
+    }
+
+    pub fn rgb(&self) -> u32 {
+        self.entry().3
+    }
+
+    /// The 16-color ANSI foreground code closest to this color.
+    pub fn ansi_code(&self) -> u8 {
+        match self {
+            NamedColor::Black => 30,
+            NamedColor::DarkBlue => 34,
+            NamedColor::DarkGreen => 32,
+            NamedColor::DarkAqua => 36,
+            NamedColor::DarkRed => 31,
+            NamedColor::DarkPurple => 35,
+            NamedColor::Gold => 33,
+            NamedColor::Gray => 37,
+            NamedColor::DarkGray => 90,
+            NamedColor::Blue => 94,
+            NamedColor::Green => 92,
+            NamedColor::Aqua => 96,
+            NamedColor::Red => 91,
+            NamedColor::LightPurple => 95,
+            NamedColor::Yellow => 93,
+            NamedColor::White => 97,
+        }
+    }
+
+    /// The named color closest to an arbitrary RGB value.
+    pub fn nearest(rgb: u32) -> Self {
+        let distance = |other: u32| {
+            let channel = |shift: u32| {
+                let a = ((rgb >> shift) & 0xFF) as i32;
+                let b = ((other >> shift) & 0xFF) as i32;
+                (a - b) * (a - b)
+            };
+            channel(16) + channel(8) + channel(0)
+        };
+        NAMED_COLORS
+            .iter()
+            .min_by_key(|(.., rgb)| distance(*rgb))
+            .map(|(color, ..)| *color)
+            .unwrap_or(NamedColor::White)
+    }
+}
+
+impl ChatColor {
+    /// Parse a color as found in the `color` field of a JSON component,
+    /// either a name like `dark_red` or a hex value like `#ff0000`.
+    pub fn parse(color: &str) -> Option<Self> {
+        match color.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 => u32::from_str_radix(hex, 16).ok().map(ChatColor::Hex),
+            Some(_) => None,
+            None => NamedColor::from_name(color).map(ChatColor::Named),
+        }
+    }
+
+    pub fn rgb(&self) -> u32 {
+        match self {
+            ChatColor::Named(color) => color.rgb(),
+            ChatColor::Hex(rgb) => *rgb,
+        }
+    }
+
+    pub fn to_named(&self) -> NamedColor {
+        match self {
+            ChatColor::Named(color) => *color,
+            ChatColor::Hex(rgb) => NamedColor::nearest(*rgb),
+        }
+    }
+}
+
+impl Display for ChatColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChatColor::Named(color) => write!(f, "{}", color.name()),
+            ChatColor::Hex(rgb) => write!(f, "#{rgb:06x}"),
+        }
+    }
+}
+
+impl ClickAction {
+    fn parse(action: &str) -> Self {
+        match action {
+            "open_url" => ClickAction::OpenUrl,
+            "open_file" => ClickAction::OpenFile,
+            "run_command" => ClickAction::RunCommand,
+            "suggest_command" => ClickAction::SuggestCommand,
+            "change_page" => ClickAction::ChangePage,
+            "copy_to_clipboard" => ClickAction::CopyToClipboard,
+            other => ClickAction::Unknown(other.to_string()),
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            ClickAction::OpenUrl => "open_url",
+            ClickAction::OpenFile => "open_file",
+            ClickAction::RunCommand => "run_command",
+            ClickAction::SuggestCommand => "suggest_command",
+            ClickAction::ChangePage => "change_page",
+            ClickAction::CopyToClipboard => "copy_to_clipboard",
+            ClickAction::Unknown(other) => other,
+        }
+    }
+}
+
+impl ChatStyle {
+    /// Fill in anything this style leaves unset from `parent`. Events are not
+    /// inherited by the renderers, but they are by the game, so they are
+    /// merged too.
+    pub fn inherit(&self, parent: &ChatStyle) -> ChatStyle {
+        ChatStyle {
+            color: self.color.or(parent.color),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underlined: self.underlined.or(parent.underlined),
+            strikethrough: self.strikethrough.or(parent.strikethrough),
+            obfuscated: self.obfuscated.or(parent.obfuscated),
+            font: self.font.clone().or_else(|| parent.font.clone()),
+            insertion: self.insertion.clone().or_else(|| parent.insertion.clone()),
+            click_event: self
+                .click_event
+                .clone()
+                .or_else(|| parent.click_event.clone()),
+            hover_event: self
+                .hover_event
+                .clone()
+                .or_else(|| parent.hover_event.clone()),
+        }
+    }
+
+    fn formats(&self) -> [(bool, char); 5] {
+        [
+            (self.obfuscated == Some(true), 'k'),
+            (self.bold == Some(true), 'l'),
+            (self.strikethrough == Some(true), 'm'),
+            (self.underlined == Some(true), 'n'),
+            (self.italic == Some(true), 'o'),
+        ]
+    }
+}
+
+impl ChatComponent {
+    pub fn text(text: &str) -> Self {
+        ChatComponent {
+            content: ChatContent::Text(text.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Parse a string that is either a JSON chat component or legacy
+    /// `§`-coded text, as is the case for kick reasons and MOTDs.
+    pub fn parse(source: &str) -> Self {
+        let trimmed = source.trim_start();
+        if trimmed.starts_with('{') || trimmed.starts_with('[') || trimmed.starts_with('"') {
+            if let Ok(json) = serde_json::from_str::<Value>(source) {
+                return ChatComponent::from_json(&json);
+            }
+        }
+        ChatComponent::from_legacy(source)
+    }
+
+    /// Build a component tree from a JSON chat component. This never fails,
+    /// anything that isn't understood is rendered as text.
+    pub fn from_json(json: &Value) -> Self {
+        match json {
+            Value::String(text) => ChatComponent::from_legacy(text),
+            Value::Array(parts) => {
+                let mut parts = parts.iter().map(ChatComponent::from_json);
+                let mut res = parts.next().unwrap_or_default();
+                res.extra.extend(parts);
+                res
+            }
+            Value::Object(object) => ChatComponent::from_json_object(object),
+            Value::Null => ChatComponent::default(),
+            other => ChatComponent::text(&other.to_string()),
+        }
+    }
+
+    fn from_json_object(object: &Map<String, Value>) -> Self {
+        let string = |key: &str| object.get(key).and_then(Value::as_str).map(str::to_string);
+        let boolean = |key: &str| {
+            object.get(key).and_then(|val| match val {
+                Value::Bool(val) => Some(*val),
+                Value::Number(val) => val.as_i64().map(|val| val != 0),
+                Value::String(val) => Some(val == "true"),
+                _ => None,
+            })
+        };
+        let components = |key: &str| match object.get(key) {
+            Some(Value::Array(parts)) => parts.iter().map(ChatComponent::from_json).collect(),
+            Some(part) => vec![ChatComponent::from_json(part)],
+            None => vec![],
+        };
+
+        let content = if let Some(text) = object.get("text") {
+            ChatContent::Text(match text {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            })
+        } else if let Some(key) = string("translate") {
+            ChatContent::Translate {
+                key,
+                fallback: string("fallback"),
+                with: components("with"),
+            }
+        } else if let Some(keybind) = string("keybind") {
+            ChatContent::Keybind(keybind)
+        } else if let Some(Value::Object(score)) = object.get("score") {
+            let field = |key: &str| match score.get(key) {
+                Some(Value::String(val)) => Some(val.clone()),
+                Some(Value::Null) | None => None,
+                Some(other) => Some(other.to_string()),
+            };
+            ChatContent::Score {
+                name: field("name").unwrap_or_default(),
+                objective: field("objective").unwrap_or_default(),
+                value: field("value"),
+            }
+        } else if let Some(selector) = string("selector") {
+            ChatContent::Selector {
+                selector,
+                separator: object
+                    .get("separator")
+                    .map(|separator| Box::new(ChatComponent::from_json(separator))),
+            }
+        } else {
+            ChatContent::default()
+        };
+
+        let click_event = match object.get("clickEvent") {
+            Some(Value::Object(event)) => Some(ClickEvent {
+                action: ClickAction::parse(
+                    event.get("action").and_then(Value::as_str).unwrap_or(""),
+                ),
+                value: match event.get("value") {
+                    Some(Value::String(val)) => val.clone(),
+                    Some(other) => other.to_string(),
+                    None => String::new(),
+                },
+            }),
+            _ => None,
+        };
+
+        let hover_event = match object.get("hoverEvent") {
+            Some(Value::Object(event)) => {
+                let action = event.get("action").and_then(Value::as_str).unwrap_or("");
+                // `value` is the pre-1.16 name of `contents`
+                let contents = event
+                    .get("contents")
+                    .or_else(|| event.get("value"))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                Some(match action {
+                    "show_text" => {
+                        HoverEvent::ShowText(Box::new(ChatComponent::from_json(&contents)))
+                    }
+                    "show_item" => HoverEvent::ShowItem(contents),
+                    "show_entity" => HoverEvent::ShowEntity(contents),
+                    other => HoverEvent::Unknown {
+                        action: other.to_string(),
+                        contents,
+                    },
+                })
+            }
+            _ => None,
+        };
+
+        ChatComponent {
+            content,
+            style: ChatStyle {
+                color: string("color").as_deref().and_then(ChatColor::parse),
+                bold: boolean("bold"),
+                italic: boolean("italic"),
+                underlined: boolean("underlined"),
+                strikethrough: boolean("strikethrough"),
+                obfuscated: boolean("obfuscated"),
+                font: string("font"),
+                insertion: string("insertion"),
+                click_event,
+                hover_event,
+            },
+            extra: components("extra"),
+        }
+    }
+
+    /// Build a component tree from an NBT text component, the form packets
+    /// use since 1.20.3.
+    pub fn from_nbt(tag: &Tag) -> Self {
+        ChatComponent::from_json(&nbt_to_json(tag))
+    }
+
+    /// Build a component tree from `§`-coded text. The result is an unstyled
+    /// root with one child per run of identically formatted text.
+    ///
+    /// The BungeeCord `§x§r§r§g§g§b§b` hex color form is understood too.
+    pub fn from_legacy(source: &str) -> Self {
+        let mut root = ChatComponent::default();
+        let mut style = ChatStyle::default();
+        let mut current = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            if ch != SECTION_SIGN {
+                current.push(ch);
+                continue;
+            }
+            let Some(&code) = chars.peek() else {
+                current.push(ch);
+                break;
+            };
+            let code = code.to_ascii_lowercase();
+
+            if code == 'x' {
+                let rest: String = chars.clone().skip(1).take(12).collect();
+                let digits: Option<String> = rest
+                    .chars()
+                    .collect::<Vec<_>>()
+                    .chunks(2)
+                    .map(|pair| match pair {
+                        [SECTION_SIGN, digit] if digit.is_ascii_hexdigit() => Some(*digit),
+                        _ => None,
+                    })
+                    .collect();
+                if let Some(digits) = digits.filter(|digits| digits.len() == 6) {
+                    flush_legacy_run(&mut current, &style, &mut root);
+                    style = ChatStyle {
+                        color: u32::from_str_radix(&digits, 16).ok().map(ChatColor::Hex),
+                        ..Default::default()
+                    };
+                    for _ in 0..13 {
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+
+            let mut next = style.clone();
+            if let Some(color) = NamedColor::from_code(code) {
+                next = ChatStyle {
+                    color: Some(ChatColor::Named(color)),
+                    ..Default::default()
+                };
+            } else {
+                match code {
+                    'k' => next.obfuscated = Some(true),
+                    'l' => next.bold = Some(true),
+                    'm' => next.strikethrough = Some(true),
+                    'n' => next.underlined = Some(true),
+                    'o' => next.italic = Some(true),
+                    'r' => next = ChatStyle::default(),
+                    _ => {
+                        // not a formatting code, keep it as text
+                        current.push(ch);
+                        continue;
+                    }
+                }
+            }
+            chars.next();
+            if next != style {
+                flush_legacy_run(&mut current, &style, &mut root);
+                style = next;
+            }
+        }
+        flush_legacy_run(&mut current, &style, &mut root);
+
+        if root.extra.len() == 1 && root.extra[0].extra.is_empty() {
+            return root.extra.remove(0);
+        }
+        root
+    }
+
+    /// Serialize this component back into its JSON form.
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        match &self.content {
+            ChatContent::Text(text) => {
+                object.insert("text".into(), text.clone().into());
+            }
+            ChatContent::Translate {
+                key,
+                fallback,
+                with,
+            } => {
+                object.insert("translate".into(), key.clone().into());
+                if let Some(fallback) = fallback {
+                    object.insert("fallback".into(), fallback.clone().into());
+                }
+                if !with.is_empty() {
+                    object.insert(
+                        "with".into(),
+                        with.iter().map(ChatComponent::to_json).collect(),
+                    );
+                }
+            }
+            ChatContent::Keybind(keybind) => {
+                object.insert("keybind".into(), keybind.clone().into());
+            }
+            ChatContent::Score {
+                name,
+                objective,
+                value,
+            } => {
+                let mut score = Map::new();
+                score.insert("name".into(), name.clone().into());
+                score.insert("objective".into(), objective.clone().into());
+                if let Some(value) = value {
+                    score.insert("value".into(), value.clone().into());
+                }
+                object.insert("score".into(), score.into());
+            }
+            ChatContent::Selector {
+                selector,
+                separator,
+            } => {
+                object.insert("selector".into(), selector.clone().into());
+                if let Some(separator) = separator {
+                    object.insert("separator".into(), separator.to_json());
+                }
+            }
+        }
+
+        let style = &self.style;
+        if let Some(color) = style.color {
+            object.insert("color".into(), color.to_string().into());
+        }
+        for (key, val) in [
+            ("bold", style.bold),
+            ("italic", style.italic),
+            ("underlined", style.underlined),
+            ("strikethrough", style.strikethrough),
+            ("obfuscated", style.obfuscated),
+        ] {
+            if let Some(val) = val {
+                object.insert(key.into(), val.into());
+            }
+        }
+        if let Some(font) = &style.font {
+            object.insert("font".into(), font.clone().into());
+        }
+        if let Some(insertion) = &style.insertion {
+            object.insert("insertion".into(), insertion.clone().into());
+        }
+        if let Some(event) = &style.click_event {
+            let mut click = Map::new();
+            click.insert("action".into(), event.action.as_str().into());
+            click.insert("value".into(), event.value.clone().into());
+            object.insert("clickEvent".into(), click.into());
+        }
+        if let Some(event) = &style.hover_event {
+            let (action, contents) = match event {
+                HoverEvent::ShowText(text) => ("show_text", text.to_json()),
+                HoverEvent::ShowItem(item) => ("show_item", item.clone()),
+                HoverEvent::ShowEntity(entity) => ("show_entity", entity.clone()),
+                HoverEvent::Unknown { action, contents } => (action.as_str(), contents.clone()),
+            };
+            let mut hover = Map::new();
+            hover.insert("action".into(), action.into());
+            hover.insert("contents".into(), contents);
+            object.insert("hoverEvent".into(), hover.into());
+        }
+
+        if !self.extra.is_empty() {
+            object.insert(
+                "extra".into(),
+                self.extra.iter().map(ChatComponent::to_json).collect(),
+            );
+        }
+        Value::Object(object)
+    }
+
+    /// Walk the tree in display order, calling `visit` with every piece of
+    /// text and the style it is displayed with.
+    ///
+    /// Translations are rendered from their fallback (or key) since the crate
+    /// has no language files. Their arguments are walked in place, inheriting
+    /// the translation's style like children do.
+    fn walk(&self, parent: &ChatStyle, visit: &mut dyn FnMut(&str, &ChatStyle)) {
+        let style = self.style.inherit(parent);
+        let visit_text = |text: &str, visit: &mut dyn FnMut(&str, &ChatStyle)| {
+            if !text.is_empty() {
+                visit(text, &style);
+            }
+        };
+        match &self.content {
+            ChatContent::Text(text) => visit_text(text, visit),
+            ChatContent::Translate {
+                key,
+                fallback,
+                with,
+            } => {
+                for part in translation_parts(fallback.as_deref().unwrap_or(key)) {
+                    match part {
+                        TranslationPart::Text(text) => visit_text(&text, visit),
+                        TranslationPart::Arg(index) => {
+                            if let Some(arg) = with.get(index) {
+                                arg.walk(&style, visit);
+                            }
+                        }
+                    }
+                }
+            }
+            ChatContent::Keybind(keybind) => visit_text(keybind, visit),
+            ChatContent::Score { value, .. } => {
+                visit_text(value.as_deref().unwrap_or_default(), visit)
+            }
+            ChatContent::Selector { selector, .. } => visit_text(selector, visit),
+        }
+        for child in &self.extra {
+            child.walk(&style, visit);
+        }
+    }
+
+    /// Render the component as unformatted text.
+    pub fn to_plain(&self) -> String {
+        let mut res = String::new();
+        self.walk(&ChatStyle::default(), &mut |text, _| res.push_str(text));
+        res
+    }
+
+    /// Render the component for a terminal using ANSI escape codes. Hex colors
+    /// are emitted as 24-bit color sequences.
+    pub fn to_ansi(&self) -> String {
+        let mut res = String::new();
+        let mut any = false;
+        self.walk(&ChatStyle::default(), &mut |text, style| {
+            any = true;
+            res.push_str("\x1b[0m");
+            match style.color {
+                Some(ChatColor::Named(color)) => {
+                    let _ = write!(res, "\x1b[{}m", color.ansi_code());
+                }
+                Some(ChatColor::Hex(rgb)) => {
+                    let _ = write!(
+                        res,
+                        "\x1b[38;2;{};{};{}m",
+                        (rgb >> 16) & 0xFF,
+                        (rgb >> 8) & 0xFF,
+                        rgb & 0xFF
+                    );
+                }
+                None => {}
+            }
+            for (enabled, code) in [
+                (style.bold, 1),
+                (style.italic, 3),
+                (style.underlined, 4),
+                (style.obfuscated, 5),
+                (style.strikethrough, 9),
+            ] {
+                if enabled == Some(true) {
+                    let _ = write!(res, "\x1b[{code}m");
+                }
+            }
+            res.push_str(text);
+        });
+        if any {
+            res.push_str("\x1b[0m");
+        }
+        res
+    }
+
+    /// Render the component as HTML, one `<span>` per run of text. Text is
+    /// escaped and newlines become `<br>`.
+    pub fn to_html(&self) -> String {
+        let mut res = String::new();
+        self.walk(&ChatStyle::default(), &mut |text, style| {
+            let mut css = String::new();
+            if let Some(color) = style.color {
+                let _ = write!(css, "color:#{:06x};", color.rgb());
+            }
+            if style.bold == Some(true) {
+                css.push_str("font-weight:bold;");
+            }
+            if style.italic == Some(true) {
+                css.push_str("font-style:italic;");
+            }
+            match (
+                style.underlined == Some(true),
+                style.strikethrough == Some(true),
+            ) {
+                (true, true) => css.push_str("text-decoration:underline line-through;"),
+                (true, false) => css.push_str("text-decoration:underline;"),
+                (false, true) => css.push_str("text-decoration:line-through;"),
+                (false, false) => {}
+            }
+            let escaped = escape_html(text);
+            if css.is_empty() {
+                res.push_str(&escaped);
+            } else {
+                let _ = write!(res, "<span style=\"{css}\">{escaped}</span>");
+            }
+        });
+        res
+    }
+
+    /// Render the component as `§`-coded text. Hex colors are approximated by
+    /// the nearest named color.
+    pub fn to_legacy(&self) -> String {
+        let mut res = String::new();
+        let mut current = ChatStyle::default();
+        self.walk(&ChatStyle::default(), &mut |text, style| {
+            let color = style.color.map(|color| color.to_named());
+            let formats = style.formats();
+            let current_formats = current.formats();
+            let dropped_format = formats
+                .iter()
+                .zip(current_formats.iter())
+                .any(|((on, _), (was_on, _))| *was_on && !on);
+            let current_color = current.color.map(|color| color.to_named());
+
+            if color != current_color || dropped_format {
+                // a color code clears the formats, so they have to be redone
+                match color {
+                    Some(color) => {
+                        res.push(SECTION_SIGN);
+                        res.push(color.code());
+                    }
+                    None => {
+                        res.push(SECTION_SIGN);
+                        res.push('r');
+                    }
+                }
+                for (on, code) in formats {
+                    if on {
+                        res.push(SECTION_SIGN);
+                        res.push(code);
+                    }
+                }
+            } else {
+                for ((on, code), (was_on, _)) in formats.iter().zip(current_formats.iter()) {
+                    if *on && !was_on {
+                        res.push(SECTION_SIGN);
+                        res.push(*code);
+                    }
+                }
+            }
+            current = ChatStyle {
+                color: color.map(ChatColor::Named),
+                ..style.clone()
+            };
+            res.push_str(text);
+        });
+        res
+    }
+}
+
+impl Display for ChatComponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_plain())
+    }
+}
+
+impl From<&str> for ChatComponent {
+    fn from(text: &str) -> Self {
+        ChatComponent::parse(text)
+    }
+}
+
+impl Serialize for ChatComponent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChatComponent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(ChatComponent::from_json(&Value::deserialize(deserializer)?))
+    }
+}
+
+/// Remove every `§` code from a string, leaving only the text. Anything
+/// [`ChatComponent::from_legacy`] doesn't take as a code, like `§` followed by
+/// a letter with no meaning, is left in.
+///
+/// ```
+/// use minecraft_utilities::strip_legacy_codes;
+///
+/// assert_eq!(strip_legacy_codes("§6Gold §land bold"), "Gold and bold");
+/// assert_eq!(strip_legacy_codes("§x§f§f§0§0§0§0Red §zcode"), "Red §zcode");
+/// ```
+pub fn strip_legacy_codes(source: &str) -> String {
+    ChatComponent::from_legacy(source).to_plain()
+}
+
+fn flush_legacy_run(current: &mut String, style: &ChatStyle, root: &mut ChatComponent) {
+    if !current.is_empty() {
+        root.extra.push(ChatComponent {
+            content: ChatContent::Text(std::mem::take(current)),
+            style: style.clone(),
+            extra: vec![],
+        });
+    }
+}
+
+enum TranslationPart {
+    Text(String),
+    /// The index into the translation's arguments.
+    Arg(usize),
+}
+
+/// Split a translation string into text and its `%s` and `%1$s` style
+/// arguments.
+fn translation_parts(format: &str) -> Vec<TranslationPart> {
+    let mut res = vec![];
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+    let mut next_arg = 0;
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            text.push(ch);
+            continue;
+        }
+        let arg = match chars.peek() {
+            Some('%') => {
+                chars.next();
+                text.push('%');
+                continue;
+            }
+            Some('s') | Some('d') => {
+                chars.next();
+                next_arg += 1;
+                next_arg - 1
+            }
+            Some(digit) if digit.is_ascii_digit() => {
+                let mut index = String::new();
+                while let Some(digit) = chars.peek().filter(|digit| digit.is_ascii_digit()) {
+                    index.push(*digit);
+                    chars.next();
+                }
+                if chars.peek() == Some(&'$') {
+                    chars.next();
+                    chars.next();
+                }
+                index.parse::<usize>().unwrap_or(0).saturating_sub(1)
+            }
+            _ => {
+                text.push(ch);
+                continue;
+            }
+        };
+        res.push(TranslationPart::Text(std::mem::take(&mut text)));
+        res.push(TranslationPart::Arg(arg));
+    }
+    res.push(TranslationPart::Text(text));
+    res
+}
+
+/// NBT lists can't mix types, so lists of components that do wrap each
+/// element in a compound with an empty key, which gets unwrapped here.
+fn nbt_to_json(tag: &Tag) -> Value {
+    match tag {
+        Tag::Compound(compound) => match compound.get("") {
+            Some(inner) if compound.len() == 1 => nbt_to_json(inner),
+            _ => Value::Object(
+                compound
+                    .iter()
+                    .map(|(key, val)| (key.clone(), nbt_to_json(val)))
+                    .collect(),
+            ),
+        },
+        Tag::List(vals) => Value::Array(vals.iter().map(nbt_to_json).collect()),
+        other => serde_json::to_value(other).unwrap_or(Value::Null),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            '\n' => res.push_str("<br>"),
+            other => res.push(other),
+        }
+    }
+    res
+}
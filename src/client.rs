@@ -1,12 +1,25 @@
-use std::{error::Error, str::from_utf8};
-use tokio::{io::AsyncWriteExt, net::TcpStream};
+use futures::{SinkExt, StreamExt};
+use std::{
+    io::{self, Read, Write},
+    sync::Arc,
+};
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
 use uuid::Uuid;
 
 use crate::{
-    packetutil::{
-        get_packet, read_varint_buf, send_prefixed_packet, write_varint, MinecraftPacket,
-    },
+    auth::{server_hash, SessionService},
+    chat::ChatComponent,
+    codec::{MinecraftCodec, ZlibCompression},
+    data_types::{DataTypeError, McRead, McWrite, RemainingBytes, VarInt},
+    encryption::{encrypt_with_public_key, generate_shared_secret, Aes128Cfb8},
+    error::{Error, IoResultExt, Phase},
+    packets::{handshake::Handshake, login, Packet},
+    packetutil::MinecraftPacket,
     server_address::ServerAddress,
+    session::{start_session, PacketSender, PacketStream},
+    versions::{PROTOCOL_1_16, PROTOCOL_1_19_4, PROTOCOL_1_20_5},
 };
 
 #[derive(Debug)]
@@ -17,25 +30,157 @@ pub enum OnlineModeResults {
     UnknownProtocol,
 }
 
+#[derive(Error, Debug, PartialEq)]
+pub enum LoginError {
+    #[error("No connection, cannot join")]
+    NoConnection,
+    #[error("Disconnected during login: {0}")]
+    Disconnected(Box<ChatComponent>),
+    #[error("Recieved unexpected packet {0:#04x} during login")]
+    UnexpectedPacket(i32),
+}
+
+/// A property of the player's game profile, such as their skin (`textures`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameProfileProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>,
+}
+
+impl McRead for GameProfileProperty {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        Ok(GameProfileProperty {
+            name: String::read_from(buf)?,
+            value: String::read_from(buf)?,
+            signature: Option::read_from(buf)?,
+        })
+    }
+}
+
+impl McWrite for GameProfileProperty {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        self.name.write_to(buf)?;
+        self.value.write_to(buf)?;
+        self.signature.write_to(buf)
+    }
+}
+
+/// The contents of the Login Success packet that ends the login sequence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginSuccess {
+    pub uuid: Uuid,
+    pub username: String,
+    pub properties: Vec<GameProfileProperty>,
+}
+
 #[derive(Debug)]
 pub struct Client {
     address: ServerAddress,
-    connection: Option<TcpStream>,
+    connection: Option<Framed<TcpStream, MinecraftCodec>>,
+    protocol_version: i32,
+    session_service: Option<Arc<dyn SessionService>>,
 }
 
 impl Client {
-    pub async fn connect(addr: &ServerAddress) -> Result<Self, Box<dyn Error>> {
+    pub async fn connect(addr: &ServerAddress) -> Result<Self, Error> {
         let mut res = Client {
             address: addr.clone(),
             connection: None,
+            protocol_version: PROTOCOL_1_19_4,
+            session_service: None,
         };
 
-        res.connection =
-            Some(TcpStream::connect(format!("{}:{}", res.address.host, res.address.port)).await?);
+        let stream = res.address.connect().await?;
+        res.connection = Some(Framed::new(stream, MinecraftCodec::new()));
 
         Ok(res)
     }
 
+    /// Authenticate through `service` when the server asks for encryption.
+    /// Without one, online mode servers will kick the client after the
+    /// Encryption Response.
+    pub fn with_session_service(mut self, service: impl SessionService + 'static) -> Self {
+        self.session_service = Some(Arc::new(service));
+        self
+    }
+
+    /// Send a packet, applying whatever compression and encryption has been
+    /// negotiated so far.
+    pub async fn send_packet(&mut self, packet_id: i32, data: &[u8]) -> Result<(), Error> {
+        match &mut self.connection {
+            Some(stream) => stream
+                .send(MinecraftPacket {
+                    packet_id,
+                    buffer: data.to_vec(),
+                })
+                .await
+                .during(Phase::Login),
+            None => Err(LoginError::NoConnection.into()),
+        }
+    }
+
+    /// Read a packet, applying whatever compression and encryption has been
+    /// negotiated so far.
+    pub async fn read_packet(&mut self) -> Result<MinecraftPacket, Error> {
+        match &mut self.connection {
+            Some(stream) => match stream.next().await {
+                Some(packet) => packet.during(Phase::Login),
+                None => Err(Error::io(
+                    Phase::Login,
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "Connection closed by server"),
+                )),
+            },
+            None => Err(LoginError::NoConnection.into()),
+        }
+    }
+
+    /// Send a declared packet, encoded for the protocol version being used.
+    pub async fn send<P: Packet>(&mut self, packet: &P) -> Result<(), Error> {
+        let packet = packet.to_packet(self.protocol_version)?;
+        self.send_packet(packet.packet_id, &packet.buffer).await
+    }
+
+    /// Send the Handshake and Login Start packets and return the first packet
+    /// the server answers with.
+    pub async fn start_login(
+        &mut self,
+        protocol_version: Option<i32>,
+        hostname: Option<&str>,
+        port: Option<u16>,
+        playername: Option<&str>,
+        player_uuid: Option<Uuid>,
+    ) -> Result<MinecraftPacket, Error> {
+        let resolved_protocol_version: i32 = protocol_version.unwrap_or(PROTOCOL_1_19_4);
+        let resolved_hostname = hostname.unwrap_or("shrecked.dev");
+        let resolved_port = port.unwrap_or(25565);
+        let resolved_playername = playername.unwrap_or("Shrecknt");
+        self.protocol_version = resolved_protocol_version;
+
+        self.send(&Handshake {
+            protocol_version: VarInt(resolved_protocol_version),
+            server_address: resolved_hostname.to_string(),
+            server_port: resolved_port,
+            next_state: VarInt(0x02), // login
+        })
+        .await?;
+
+        self.send(&login::LoginStart {
+            name: resolved_playername.to_string(),
+            has_sig_data: false,
+            player_uuid,
+            uuid: player_uuid.unwrap_or_else(Uuid::new_v4),
+        })
+        .await?;
+
+        self.read_packet().await
+    }
+
+    /// Log in to the server, handling encryption, compression and login plugin
+    /// requests along the way, and return once Login Success arrives.
+    ///
+    /// Servers running 1.20.2 or newer are sent Login Acknowledged, which
+    /// moves the connection into the configuration state.
     pub async fn join(
         &mut self,
         protocol_version: Option<i32>,
@@ -43,62 +188,109 @@ impl Client {
         port: Option<u16>,
         playername: Option<&str>,
         player_uuid: Option<Uuid>,
-    ) -> Result<MinecraftPacket, Box<dyn Error>> {
-        match &mut self.connection {
-            Some(stream) => {
-                let resolved_protocol_version: i32 = protocol_version.unwrap_or(762);
-                let resolved_hostname = hostname.unwrap_or("shrecked.dev");
-                let resolved_port = port.unwrap_or(25565);
-                let resolved_playername = playername.unwrap_or("Shrecknt");
-
-                let mut connect_packet: Vec<u8> = vec![];
-                connect_packet.write_u8(0x00).await?;
-                write_varint(&mut connect_packet, resolved_protocol_version).await?; // protocol version - 762 (1.19.4)
-                write_varint(
-                    &mut connect_packet,
-                    i32::try_from(resolved_hostname.as_bytes().len())?,
-                )
-                .await?; // host length - 12
-                connect_packet
-                    .write_all(resolved_hostname.as_bytes())
-                    .await?; // host name - shrecked.dev
-                connect_packet.write_u16(resolved_port).await?; // port number - 42069
-                connect_packet.write_u8(0x02).await?; // next state - 2 (login)
-
-                send_prefixed_packet(stream, &connect_packet).await?;
-
-                let mut login_start_packet: Vec<u8> = vec![];
-                login_start_packet.write_u8(0x00).await?;
-                write_varint(
-                    &mut login_start_packet,
-                    i32::try_from(resolved_playername.as_bytes().len())?,
-                )
-                .await?;
-                login_start_packet
-                    .write_all(resolved_playername.as_bytes())
-                    .await?;
-                if resolved_protocol_version == 759 || resolved_protocol_version == 760 {
-                    login_start_packet.write_u8(0x00).await?;
+    ) -> Result<LoginSuccess, Error> {
+        let mut packet = self
+            .start_login(protocol_version, hostname, port, playername, player_uuid)
+            .await?;
+
+        loop {
+            let protocol_version = self.protocol_version;
+            if login::LoginDisconnect::matches(&packet, protocol_version) {
+                let disconnect = login::LoginDisconnect::from_packet(&packet, protocol_version)?;
+                return Err(LoginError::Disconnected(Box::new(ChatComponent::parse(
+                    &disconnect.reason,
+                )))
+                .into());
+            } else if login::EncryptionRequest::matches(&packet, protocol_version) {
+                let request = login::EncryptionRequest::from_packet(&packet, protocol_version)?;
+                self.handle_encryption_request(request).await?;
+            } else if login::LoginSuccess::matches(&packet, protocol_version) {
+                let login_success = login::LoginSuccess::from_packet(&packet, protocol_version)?;
+                if login::LoginAcknowledged::id(protocol_version).is_some() {
+                    self.send(&login::LoginAcknowledged {}).await?;
                 }
-                if resolved_protocol_version >= 759 {
-                    match player_uuid {
-                        Some(uuid) => {
-                            login_start_packet.write_u8(0x01).await?;
-                            login_start_packet.write_all(uuid.as_bytes()).await?;
-                        }
-                        None => {
-                            login_start_packet.write_u8(0x00).await?;
-                        }
-                    }
+                return Ok(LoginSuccess {
+                    uuid: if protocol_version >= PROTOCOL_1_16 {
+                        login_success.uuid
+                    } else {
+                        Uuid::parse_str(&login_success.uuid_string).map_err(|err| {
+                            Error::invalid_response(Phase::Login, format!("Bad uuid: {err}"))
+                        })?
+                    },
+                    username: login_success.username,
+                    properties: login_success.properties,
+                });
+            } else if login::SetCompression::matches(&packet, protocol_version) {
+                let VarInt(threshold) =
+                    login::SetCompression::from_packet(&packet, protocol_version)?.threshold;
+                if let Some(stream) = &mut self.connection {
+                    // a negative threshold turns compression off
+                    let compression = usize::try_from(threshold)
+                        .ok()
+                        .map(|threshold| Box::new(ZlibCompression::new(threshold)) as _);
+                    stream.codec_mut().set_compression(compression);
                 }
+            } else if login::LoginPluginRequest::matches(&packet, protocol_version) {
+                // we don't understand any login plugin channels
+                let request = login::LoginPluginRequest::from_packet(&packet, protocol_version)?;
+                self.send(&login::LoginPluginResponse {
+                    message_id: request.message_id,
+                    successful: false,
+                    data: RemainingBytes::default(),
+                })
+                .await?;
+            } else {
+                return Err(LoginError::UnexpectedPacket(packet.packet_id).into());
+            }
+            packet = self.read_packet().await?;
+        }
+    }
 
-                send_prefixed_packet(stream, &login_start_packet).await?;
+    async fn handle_encryption_request(
+        &mut self,
+        request: login::EncryptionRequest,
+    ) -> Result<(), Error> {
+        // added in 1.20.5, before that encryption always meant online mode
+        let should_authenticate =
+            self.protocol_version < PROTOCOL_1_20_5 || request.should_authenticate;
 
-                let result = get_packet(stream).await?;
-                Ok(result)
+        let shared_secret = generate_shared_secret();
+
+        if let Some(session_service) = &self.session_service {
+            if should_authenticate {
+                session_service
+                    .join_server(&server_hash(
+                        &request.server_id,
+                        &shared_secret,
+                        &request.public_key,
+                    ))
+                    .await?;
             }
-            None => Err("No connection, cannot join".into()),
         }
+
+        self.send(&login::EncryptionResponse {
+            shared_secret: encrypt_with_public_key(&request.public_key, &shared_secret)?,
+            has_verify_token: true,
+            verify_token: encrypt_with_public_key(&request.public_key, &request.verify_token)?,
+        })
+        .await?;
+
+        if let Some(stream) = &mut self.connection {
+            stream
+                .codec_mut()
+                .set_encryption(Some(Box::new(Aes128Cfb8::new(&shared_secret))));
+        }
+
+        Ok(())
+    }
+
+    /// Turn a client that has finished [`join`](Client::join)ing into a
+    /// session that stays connected. Keep Alive, Ping and the configuration
+    /// state are dealt with in the background, every other packet comes out
+    /// of the [`PacketStream`].
+    pub fn into_session(self) -> Result<(PacketSender, PacketStream), Error> {
+        let connection = self.connection.ok_or(LoginError::NoConnection)?;
+        Ok(start_session(connection, self.protocol_version)?)
     }
 
     pub async fn check_online_mode(
@@ -108,21 +300,19 @@ impl Client {
         port: Option<u16>,
         playername: Option<&str>,
         player_uuid: Option<Uuid>,
-    ) -> Result<(OnlineModeResults, Option<String>), Box<dyn Error>> {
+    ) -> Result<(OnlineModeResults, Option<String>), Error> {
         let res = self
-            .join(protocol_version, hostname, port, playername, player_uuid)
+            .start_login(protocol_version, hostname, port, playername, player_uuid)
             .await?;
-        if res.packet_id == 0x00 {
-            let (len, val) = read_varint_buf(&res.buffer).await?;
-            if val > 16777216 {
-                return Err("Exceeded varint size sanity limit".into());
-            }
-            let slice = &res.buffer[len.try_into()?..(u32::try_from(val)? + len).try_into()?];
-            let reason = from_utf8(slice)?;
-            Ok((OnlineModeResults::Kicked, Some(reason.to_string())))
-        } else if res.packet_id == 0x01 {
+        let protocol_version = self.protocol_version;
+        if login::LoginDisconnect::matches(&res, protocol_version) {
+            let disconnect = login::LoginDisconnect::from_packet(&res, protocol_version)?;
+            Ok((OnlineModeResults::Kicked, Some(disconnect.reason)))
+        } else if login::EncryptionRequest::matches(&res, protocol_version) {
             Ok((OnlineModeResults::OnlineMode, None))
-        } else if res.packet_id == 0x03 {
+        } else if login::SetCompression::matches(&res, protocol_version)
+            || login::LoginSuccess::matches(&res, protocol_version)
+        {
             Ok((OnlineModeResults::OfflineMode, None))
         } else {
             Ok((OnlineModeResults::UnknownProtocol, None))
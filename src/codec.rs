@@ -0,0 +1,292 @@
+//! A [`tokio_util::codec`] for Minecraft's packet framing, usable on any
+//! `AsyncRead + AsyncWrite` through [`Framed`](tokio_util::codec::Framed).
+//!
+//! Compression and encryption are layers that can be switched on at any point,
+//! which is what Set Compression and Encryption Response do during login.
+//!
+//! # Examples
+//!
+//! ```
+//! use futures::{SinkExt, StreamExt};
+//! use minecraft_utilities::{MinecraftCodec, MinecraftPacket, ZlibCompression};
+//! use tokio_util::codec::Framed;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let (client, server) = tokio::io::duplex(1024);
+//! let mut client = Framed::new(client, MinecraftCodec::new());
+//! let mut server = Framed::new(server, MinecraftCodec::new());
+//! client.codec_mut().set_compression(Some(Box::new(ZlibCompression::new(64))));
+//! server.codec_mut().set_compression(Some(Box::new(ZlibCompression::new(64))));
+//!
+//! client
+//!     .send(MinecraftPacket { packet_id: 0x42, buffer: vec![7; 500] })
+//!     .await
+//!     .unwrap();
+//! let packet = server.next().await.unwrap().unwrap();
+//! assert_eq!(packet.packet_id, 0x42);
+//! assert_eq!(packet.buffer, vec![7; 500]);
+//! # }
+//! ```
+
+use std::{
+    fmt::Debug,
+    io::{self, Read},
+};
+
+use bytes::{Buf, BufMut, BytesMut};
+use flate2::{
+    read::{ZlibDecoder, ZlibEncoder},
+    Compression,
+};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{error::Error, packetutil::MinecraftPacket};
+
+/// The largest packet (and decompressed packet) the codec accepts unless told
+/// otherwise.
+pub const DEFAULT_SANITY_LIMIT: usize = 16777216;
+
+/// Compresses packet bodies once they reach a size threshold.
+pub trait PacketCompression: Debug + Send + Sync {
+    /// Packets this size or bigger get compressed.
+    fn threshold(&self) -> usize;
+    fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>>;
+    /// `uncompressed_len` is the size the sender claims the data inflates to.
+    fn decompress(&self, data: &[u8], uncompressed_len: usize) -> io::Result<Vec<u8>>;
+}
+
+/// Turns the bytes going over the wire into ciphertext and back. Both
+/// directions are expected to keep their own state.
+pub trait PacketEncryption: Debug + Send + Sync {
+    fn encrypt(&mut self, data: &mut [u8]);
+    fn decrypt(&mut self, data: &mut [u8]);
+}
+
+/// The compression vanilla uses.
+#[derive(Debug, Clone)]
+pub struct ZlibCompression {
+    pub threshold: usize,
+    pub level: Compression,
+}
+
+impl ZlibCompression {
+    pub fn new(threshold: usize) -> Self {
+        ZlibCompression {
+            threshold,
+            level: Compression::default(),
+        }
+    }
+}
+
+impl PacketCompression for ZlibCompression {
+    fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut res = vec![];
+        ZlibEncoder::new(data, self.level).read_to_end(&mut res)?;
+        Ok(res)
+    }
+
+    fn decompress(&self, data: &[u8], uncompressed_len: usize) -> io::Result<Vec<u8>> {
+        let mut res = Vec::with_capacity(uncompressed_len);
+        // one byte past the claimed size is enough to tell it was a lie
+        ZlibDecoder::new(data)
+            .take(uncompressed_len as u64 + 1)
+            .read_to_end(&mut res)?;
+        if res.len() != uncompressed_len {
+            return Err(invalid_data("Decompressed packet has the wrong size"));
+        }
+        Ok(res)
+    }
+}
+
+#[derive(Debug)]
+pub struct MinecraftCodec {
+    sanity_limit: usize,
+    compression: Option<Box<dyn PacketCompression>>,
+    encryption: Option<Box<dyn PacketEncryption>>,
+    /// How much of the read buffer has already been through `decrypt`.
+    decrypted_len: usize,
+}
+
+impl Default for MinecraftCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MinecraftCodec {
+    pub fn new() -> Self {
+        Self::with_sanity_limit(DEFAULT_SANITY_LIMIT)
+    }
+
+    /// Refuse packets that claim to be bigger than `sanity_limit` bytes,
+    /// before or after decompression.
+    pub fn with_sanity_limit(sanity_limit: usize) -> Self {
+        MinecraftCodec {
+            sanity_limit,
+            compression: None,
+            encryption: None,
+            decrypted_len: 0,
+        }
+    }
+
+    pub fn set_compression(&mut self, compression: Option<Box<dyn PacketCompression>>) {
+        self.compression = compression;
+    }
+
+    /// Encrypt everything written and decrypt everything read from now on.
+    /// Bytes that were already buffered are left alone.
+    pub fn set_encryption(&mut self, encryption: Option<Box<dyn PacketEncryption>>) {
+        self.encryption = encryption;
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compression.is_some()
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+}
+
+impl Decoder for MinecraftCodec {
+    type Item = MinecraftPacket;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(encryption) = &mut self.encryption {
+            encryption.decrypt(&mut src[self.decrypted_len..]);
+        }
+        self.decrypted_len = src.len();
+
+        let Some((len_len, len)) = peek_varint(src)? else {
+            return Ok(None);
+        };
+        let len = usize::try_from(len).map_err(|_| invalid_data("Negative packet length"))?;
+        if len > self.sanity_limit {
+            return Err(too_large(len, self.sanity_limit));
+        }
+        if src.len() < len_len + len {
+            src.reserve(len_len + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(len_len);
+        let mut body = src.split_to(len);
+        self.decrypted_len -= len_len + len;
+
+        if let Some(compression) = &self.compression {
+            let (data_len_len, data_len) =
+                peek_varint(&body)?.ok_or_else(|| invalid_data("Unexpected end of packet"))?;
+            body.advance(data_len_len);
+            let data_len = usize::try_from(data_len)
+                .map_err(|_| invalid_data("Negative decompressed packet length"))?;
+            if data_len > self.sanity_limit {
+                return Err(too_large(data_len, self.sanity_limit));
+            }
+            if data_len != 0 {
+                body = BytesMut::from(compression.decompress(&body, data_len)?.as_slice());
+            }
+        }
+
+        let (packet_id_len, packet_id) =
+            peek_varint(&body)?.ok_or_else(|| invalid_data("Unexpected end of packet"))?;
+        body.advance(packet_id_len);
+
+        Ok(Some(MinecraftPacket {
+            packet_id,
+            buffer: body.to_vec(),
+        }))
+    }
+}
+
+impl Encoder<MinecraftPacket> for MinecraftCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: MinecraftPacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut uncompressed = Vec::with_capacity(item.buffer.len() + 5);
+        put_varint(&mut uncompressed, item.packet_id);
+        uncompressed.extend_from_slice(&item.buffer);
+
+        let body = match &self.compression {
+            Some(compression) if uncompressed.len() >= compression.threshold() => {
+                let mut body = vec![];
+                put_varint(&mut body, varint_len(uncompressed.len())?);
+                body.extend(compression.compress(&uncompressed)?);
+                body
+            }
+            Some(_) => {
+                let mut body = Vec::with_capacity(uncompressed.len() + 1);
+                put_varint(&mut body, 0);
+                body.extend(uncompressed);
+                body
+            }
+            None => uncompressed,
+        };
+
+        let start = dst.len();
+        let mut len = vec![];
+        put_varint(&mut len, varint_len(body.len())?);
+        dst.reserve(len.len() + body.len());
+        dst.put_slice(&len);
+        dst.put_slice(&body);
+
+        if let Some(encryption) = &mut self.encryption {
+            encryption.encrypt(&mut dst[start..]);
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Someone is trying to DDOS you or something :eyes:. The io error carries an
+/// [`Error::PacketTooLarge`] so it can be told apart from other bad data.
+fn too_large(length: usize, limit: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        Error::PacketTooLarge { length, limit },
+    )
+}
+
+fn varint_len(len: usize) -> io::Result<i32> {
+    i32::try_from(len).map_err(|_| invalid_data("Packet too large"))
+}
+
+fn put_varint(buf: &mut Vec<u8>, val: i32) {
+    let mut value = val as u32;
+    loop {
+        let mut byte = (value & 0b0111_1111) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0b1000_0000;
+        }
+        buf.push(byte);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+/// Read a varint from the start of `buf` without consuming it, returning
+/// `None` if more bytes are needed.
+fn peek_varint(buf: &[u8]) -> io::Result<Option<(usize, i32)>> {
+    let mut res = 0i32;
+    for (count, byte) in buf.iter().enumerate().take(5) {
+        res |= ((byte & 0b0111_1111) as i32) << (7 * count);
+        if byte & 0b1000_0000 == 0 {
+            return Ok(Some((count + 1, res)));
+        }
+    }
+    if buf.len() >= 5 {
+        return Err(invalid_data("Unsupported protocol"));
+    }
+    Ok(None)
+}
@@ -0,0 +1,188 @@
+//! `rcon` subcommand: an mcrcon-style console for a server's RCON port.
+//!
+//! ```text
+//! minecraft_utilities rcon -H localhost -p hunter2            # interactive
+//! minecraft_utilities rcon -H localhost -p hunter2 list "say hi"
+//! echo list | MCRCON_PASS=hunter2 minecraft_utilities rcon
+//! ```
+
+mod terminal;
+
+use std::io::{self, IsTerminal, Write};
+
+use minecraft_utilities::{
+    strip_legacy_codes, ChatComponent, Error, Phase, RconClient, RconError, ServerAddress,
+};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use terminal::LineEditor;
+
+const DEFAULT_PORT: u16 = 25575;
+
+const USAGE: &str = "\
+Usage: minecraft_utilities rcon [OPTIONS] [COMMAND]...
+
+Runs each COMMAND and exits. Without commands, reads them from stdin, or
+opens an interactive console when stdin is a terminal.
+
+Options:
+  -H, --host <HOST>          Server address [env: MCRCON_HOST] [default: localhost]
+  -P, --port <PORT>          RCON port [env: MCRCON_PORT] [default: 25575]
+  -p, --password <PASSWORD>  RCON password [env: MCRCON_PASS], prompted for if missing
+  -c, --no-color             Print output without colors
+  -h, --help                 Print this help";
+
+#[derive(Debug)]
+struct Options {
+    host: String,
+    port: u16,
+    password: Option<String>,
+    color: bool,
+    commands: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Option<Self>, String> {
+        let mut options = Options {
+            host: std::env::var("MCRCON_HOST").unwrap_or_else(|_| "localhost".to_string()),
+            port: match std::env::var("MCRCON_PORT") {
+                Ok(port) => parse_port(&port)?,
+                Err(_) => DEFAULT_PORT,
+            },
+            password: std::env::var("MCRCON_PASS").ok(),
+            color: true,
+            commands: vec![],
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{name} needs a value"))
+            };
+            match arg.as_str() {
+                "-H" | "--host" => options.host = value(arg)?,
+                "-P" | "--port" => options.port = parse_port(&value(arg)?)?,
+                "-p" | "--password" => options.password = Some(value(arg)?),
+                "-c" | "--no-color" => options.color = false,
+                "-h" | "--help" => return Ok(None),
+                "--" => {
+                    options.commands.extend(args.cloned());
+                    break;
+                }
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(format!("unknown option {flag}"))
+                }
+                command => options.commands.push(command.to_string()),
+            }
+        }
+
+        Ok(Some(options))
+    }
+}
+
+fn parse_port(port: &str) -> Result<u16, String> {
+    port.parse().map_err(|_| format!("invalid port {port:?}"))
+}
+
+/// Run the console and return the process exit code: 0 on success, 1 if
+/// connecting, logging in or a command failed, and 2 for bad arguments.
+pub async fn run(args: &[String]) -> i32 {
+    let options = match Options::parse(args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return 0;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return 2;
+        }
+    };
+
+    match console(options).await {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: {err}");
+            if matches!(err, Error::Rcon(RconError::AuthenticationFailure)) {
+                eprintln!("check the password and rcon.password in server.properties");
+            }
+            1
+        }
+    }
+}
+
+async fn console(options: Options) -> Result<(), Error> {
+    let password = match options.password {
+        Some(password) => password,
+        None => terminal::read_password("Password: ")
+            .await
+            .map_err(terminal_error)?
+            .ok_or_else(|| Error::InvalidArgument("no password given".to_string()))?,
+    };
+
+    let addr = ServerAddress::new(&options.host, options.port);
+    let mut client = RconClient::connect(&addr, Some(&password)).await?;
+    let color = options.color && io::stdout().is_terminal();
+
+    if !options.commands.is_empty() {
+        for command in &options.commands {
+            run_command(&mut client, command, color).await?;
+        }
+    } else if !io::stdin().is_terminal() {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Some(line) = lines.next_line().await.map_err(terminal_error)? {
+            if !line.trim().is_empty() {
+                run_command(&mut client, &line, color).await?;
+            }
+        }
+    } else {
+        println!("Logged in to {addr}. Type Q or press Ctrl-D to quit.");
+        let mut editor = LineEditor::new().map_err(terminal_error)?;
+        while let Some(line) = editor.read_line("> ").await.map_err(terminal_error)? {
+            let command = line.trim();
+            match command {
+                "" => continue,
+                "Q" | "quit" | "exit" => break,
+                // keep going after failed commands, the client reconnects
+                _ => {
+                    if let Err(err) = run_command(&mut client, command, color).await {
+                        eprintln!("error: {err}");
+                    }
+                }
+            }
+        }
+    }
+
+    client.close().await
+}
+
+fn terminal_error(source: io::Error) -> Error {
+    Error::Io {
+        phase: Phase::Rcon,
+        source,
+    }
+}
+
+async fn run_command(client: &mut RconClient, command: &str, color: bool) -> Result<(), Error> {
+    let output = client.command(command).await?;
+    if output.is_empty() {
+        return Ok(());
+    }
+
+    let mut output = if color {
+        ChatComponent::from_legacy(&output).to_ansi()
+    } else {
+        strip_legacy_codes(&output)
+    };
+    if !output.ends_with('\n') {
+        output.push('\n');
+    }
+
+    let mut stdout = io::stdout().lock();
+    // a closed pipe (`| head`) isn't worth failing over
+    let _ = stdout.write_all(output.as_bytes());
+    let _ = stdout.flush();
+    Ok(())
+}
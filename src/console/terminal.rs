@@ -0,0 +1,89 @@
+//! Line editing for the interactive console, with history on the arrow
+//! keys, and a password prompt that doesn't echo. Both block, so they run
+//! on blocking threads rather than the async runtime.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+/// Reads lines from a terminal, remembering them for the arrow keys.
+pub struct LineEditor {
+    // only missing while a read is in progress
+    editor: Option<DefaultEditor>,
+}
+
+impl LineEditor {
+    pub fn new() -> io::Result<Self> {
+        let editor = DefaultEditor::new().map_err(readline_error)?;
+        Ok(LineEditor {
+            editor: Some(editor),
+        })
+    }
+
+    /// Read a line after printing `prompt`, or `None` at the end of input.
+    /// Ctrl-C throws the line away and starts a new one, like a shell.
+    pub async fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let mut editor = self
+            .editor
+            .take()
+            .ok_or_else(|| io::Error::other("a previous read didn't finish"))?;
+        let prompt = prompt.to_string();
+        let (editor, line) = tokio::task::spawn_blocking(move || {
+            let line = loop {
+                match editor.readline(&prompt) {
+                    Ok(line) => {
+                        if !line.trim().is_empty() {
+                            editor.add_history_entry(&line).map_err(readline_error)?;
+                        }
+                        break Ok(Some(line));
+                    }
+                    Err(ReadlineError::Interrupted) => continue,
+                    Err(ReadlineError::Eof) => break Ok(None),
+                    Err(err) => break Err(readline_error(err)),
+                }
+            };
+            Ok::<_, io::Error>((editor, line))
+        })
+        .await
+        .map_err(io::Error::other)??;
+        self.editor = Some(editor);
+        line
+    }
+}
+
+fn readline_error(err: ReadlineError) -> io::Error {
+    match err {
+        ReadlineError::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
+/// Ask for a password without showing what is typed, or `None` at the end
+/// of input.
+pub async fn read_password(prompt: &str) -> io::Result<Option<String>> {
+    let prompt = prompt.to_string();
+    tokio::task::spawn_blocking(move || {
+        if !io::stdin().is_terminal() {
+            eprint!("{prompt}");
+            io::stderr().flush()?;
+            return read_plain_line();
+        }
+        match rpassword::prompt_password(prompt) {
+            Ok(password) => Ok(Some(password)),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(err) => Err(err),
+        }
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+fn read_plain_line() -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let len = line.trim_end_matches(['\r', '\n']).len();
+    line.truncate(len);
+    Ok(Some(line))
+}
@@ -0,0 +1,636 @@
+//! The data types packets are built out of, and the [`McRead`]/[`McWrite`]
+//! traits that turn them into bytes and back.
+//!
+//! Reading works on anything that implements [`std::io::Read`], including a
+//! `&[u8]` which gets advanced past whatever was read. Malformed input is
+//! reported as a [`DataTypeError`], never a panic.
+//!
+//! See <https://wiki.vg/Protocol#Data_types>.
+//!
+//! # Examples
+//!
+//! ```
+//! use minecraft_utilities::{McRead, McWrite, Position, VarInt};
+//!
+//! let mut packet = vec![];
+//! VarInt(300).write_to(&mut packet).unwrap();
+//! "shrecked.dev".write_to(&mut packet).unwrap();
+//! Position::new(-1, 64, 1).write_to(&mut packet).unwrap();
+//!
+//! let mut buf = packet.as_slice();
+//! assert_eq!(VarInt::read_from(&mut buf).unwrap(), VarInt(300));
+//! assert_eq!(String::read_from(&mut buf).unwrap(), "shrecked.dev");
+//! assert_eq!(Position::read_from(&mut buf).unwrap(), Position::new(-1, 64, 1));
+//! assert!(buf.is_empty());
+//! ```
+
+use std::{
+    fmt::{self, Display},
+    io::{self, Read, Write},
+    str::FromStr,
+    string::FromUtf8Error,
+};
+
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::nbt::NbtError;
+
+/// The longest string vanilla accepts, in UTF-16 code units.
+pub const MAX_STRING_LENGTH: usize = 32767;
+
+/// Arrays read through [`McRead`] are refused past this many elements.
+pub const MAX_ARRAY_LENGTH: usize = 1 << 21;
+
+#[derive(Error, Debug)]
+pub enum DataTypeError {
+    #[error("Unexpected end of data")]
+    UnexpectedEof,
+    #[error("VarInt is longer than 5 bytes")]
+    VarIntTooLong,
+    #[error("VarLong is longer than 10 bytes")]
+    VarLongTooLong,
+    #[error("Negative length {0}")]
+    NegativeLength(i64),
+    #[error("String is {length} long, the limit is {max}")]
+    StringTooLong { length: usize, max: usize },
+    #[error("Array has {length} elements, the limit is {max}")]
+    ArrayTooLong { length: usize, max: usize },
+    #[error("Invalid UTF-8 in string: {0}")]
+    InvalidUtf8(#[from] FromUtf8Error),
+    #[error("Invalid boolean {0:#04x}")]
+    InvalidBool(u8),
+    #[error("Invalid identifier {0:?}")]
+    InvalidIdentifier(String),
+    #[error("Packet does not exist in protocol version {0}")]
+    UnsupportedPacket(i32),
+    #[error("Expected packet {expected:#04x}, found {found:#04x}")]
+    WrongPacketId { expected: i32, found: i32 },
+    #[error("Invalid NBT: {0}")]
+    Nbt(#[from] NbtError),
+    #[error("IO error: {0}")]
+    Io(io::Error),
+}
+
+impl From<io::Error> for DataTypeError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => DataTypeError::UnexpectedEof,
+            _ => DataTypeError::Io(err),
+        }
+    }
+}
+
+pub trait McRead: Sized {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError>;
+}
+
+pub trait McWrite {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError>;
+}
+
+macro_rules! impl_number {
+    ($($ty:ty),*) => {
+        $(
+            impl McRead for $ty {
+                fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+                    let mut bytes = [0; std::mem::size_of::<$ty>()];
+                    buf.read_exact(&mut bytes)?;
+                    Ok(<$ty>::from_be_bytes(bytes))
+                }
+            }
+
+            impl McWrite for $ty {
+                fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+                    buf.write_all(&self.to_be_bytes())?;
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_number!(u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, f32, f64);
+
+impl McRead for bool {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        match u8::read_from(buf)? {
+            0x00 => Ok(false),
+            0x01 => Ok(true),
+            other => Err(DataTypeError::InvalidBool(other)),
+        }
+    }
+}
+
+impl McWrite for bool {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        u8::from(*self).write_to(buf)
+    }
+}
+
+/// A variable length `i32`, 1 to 5 bytes on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct VarInt(pub i32);
+
+impl VarInt {
+    /// How many bytes `value` takes up once encoded.
+    pub fn len(value: i32) -> usize {
+        match value as u32 {
+            0..=0x7F => 1,
+            0x80..=0x3FFF => 2,
+            0x4000..=0x1F_FFFF => 3,
+            0x20_0000..=0xFFF_FFFF => 4,
+            _ => 5,
+        }
+    }
+}
+
+impl From<i32> for VarInt {
+    fn from(value: i32) -> Self {
+        VarInt(value)
+    }
+}
+
+impl From<VarInt> for i32 {
+    fn from(value: VarInt) -> Self {
+        value.0
+    }
+}
+
+impl McRead for VarInt {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        let mut res = 0u32;
+        for count in 0..5 {
+            let byte = u8::read_from(buf)?;
+            res |= u32::from(byte & 0b0111_1111) << (7 * count);
+            if byte & 0b1000_0000 == 0 {
+                return Ok(VarInt(res as i32));
+            }
+        }
+        Err(DataTypeError::VarIntTooLong)
+    }
+}
+
+impl McWrite for VarInt {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        let mut value = self.0 as u32;
+        loop {
+            let mut byte = (value & 0b0111_1111) as u8;
+            value >>= 7;
+            if value != 0 {
+                byte |= 0b1000_0000;
+            }
+            buf.write_all(&[byte])?;
+            if value == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// A variable length `i64`, 1 to 10 bytes on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct VarLong(pub i64);
+
+impl From<i64> for VarLong {
+    fn from(value: i64) -> Self {
+        VarLong(value)
+    }
+}
+
+impl From<VarLong> for i64 {
+    fn from(value: VarLong) -> Self {
+        value.0
+    }
+}
+
+impl McRead for VarLong {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        let mut res = 0u64;
+        for count in 0..10 {
+            let byte = u8::read_from(buf)?;
+            res |= u64::from(byte & 0b0111_1111) << (7 * count);
+            if byte & 0b1000_0000 == 0 {
+                return Ok(VarLong(res as i64));
+            }
+        }
+        Err(DataTypeError::VarLongTooLong)
+    }
+}
+
+impl McWrite for VarLong {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        let mut value = self.0 as u64;
+        loop {
+            let mut byte = (value & 0b0111_1111) as u8;
+            value >>= 7;
+            if value != 0 {
+                byte |= 0b1000_0000;
+            }
+            buf.write_all(&[byte])?;
+            if value == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Read a VarInt length prefix, refusing negative lengths and ones over `max`.
+fn read_length(buf: &mut impl Read, max: usize) -> Result<usize, DataTypeError> {
+    let VarInt(len) = VarInt::read_from(buf)?;
+    let len = usize::try_from(len).map_err(|_| DataTypeError::NegativeLength(len.into()))?;
+    if len > max {
+        return Err(DataTypeError::ArrayTooLong { length: len, max });
+    }
+    Ok(len)
+}
+
+fn write_length(buf: &mut impl Write, len: usize) -> Result<(), DataTypeError> {
+    let len = i32::try_from(len).map_err(|_| DataTypeError::ArrayTooLong {
+        length: len,
+        max: i32::MAX as usize,
+    })?;
+    VarInt(len).write_to(buf)
+}
+
+/// Read a string that may be at most `max_length` UTF-16 code units long,
+/// which is how vanilla measures them.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::{read_bounded_string, DataTypeError, McWrite};
+///
+/// let mut packet = vec![];
+/// "Shrecknt".write_to(&mut packet).unwrap();
+///
+/// assert_eq!(read_bounded_string(&mut packet.as_slice(), 16).unwrap(), "Shrecknt");
+/// assert!(matches!(
+///     read_bounded_string(&mut packet.as_slice(), 4),
+///     Err(DataTypeError::StringTooLong { length: 8, max: 4 })
+/// ));
+/// ```
+pub fn read_bounded_string(
+    buf: &mut impl Read,
+    max_length: usize,
+) -> Result<String, DataTypeError> {
+    let VarInt(len) = VarInt::read_from(buf)?;
+    let len = usize::try_from(len).map_err(|_| DataTypeError::NegativeLength(len.into()))?;
+    // every code unit takes at most 3 bytes of UTF-8
+    if len > max_length * 3 {
+        return Err(DataTypeError::StringTooLong {
+            length: len,
+            max: max_length * 3,
+        });
+    }
+
+    let mut bytes = vec![0; len];
+    buf.read_exact(&mut bytes)?;
+    let res = String::from_utf8(bytes)?;
+
+    let length = res.encode_utf16().count();
+    if length > max_length {
+        return Err(DataTypeError::StringTooLong {
+            length,
+            max: max_length,
+        });
+    }
+    Ok(res)
+}
+
+impl McRead for String {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        read_bounded_string(buf, MAX_STRING_LENGTH)
+    }
+}
+
+impl McWrite for str {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        let length = self.encode_utf16().count();
+        if length > MAX_STRING_LENGTH {
+            return Err(DataTypeError::StringTooLong {
+                length,
+                max: MAX_STRING_LENGTH,
+            });
+        }
+        write_length(buf, self.len())?;
+        buf.write_all(self.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl McWrite for String {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        self.as_str().write_to(buf)
+    }
+}
+
+impl McRead for Uuid {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        Ok(Uuid::from_u128(u128::read_from(buf)?))
+    }
+}
+
+impl McWrite for Uuid {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        self.as_u128().write_to(buf)
+    }
+}
+
+/// Present/absent flag followed by the value if it is present.
+impl<T: McRead> McRead for Option<T> {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        if bool::read_from(buf)? {
+            Ok(Some(T::read_from(buf)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T: McWrite> McWrite for Option<T> {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        match self {
+            Some(value) => {
+                true.write_to(buf)?;
+                value.write_to(buf)
+            }
+            None => false.write_to(buf),
+        }
+    }
+}
+
+/// VarInt length followed by that many elements.
+impl<T: McRead> McRead for Vec<T> {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        let len = read_length(buf, MAX_ARRAY_LENGTH)?;
+        // don't trust the length with the allocation, the data might not be there
+        let mut res = Vec::with_capacity(len.min(1024));
+        for _ in 0..len {
+            res.push(T::read_from(buf)?);
+        }
+        Ok(res)
+    }
+}
+
+impl<T: McWrite> McWrite for [T] {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        write_length(buf, self.len())?;
+        for value in self {
+            value.write_to(buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: McWrite> McWrite for Vec<T> {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        self.as_slice().write_to(buf)
+    }
+}
+
+impl<T: McWrite + ?Sized> McWrite for &T {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        (**self).write_to(buf)
+    }
+}
+
+/// Whatever is left of the packet, with no length prefix. Only makes sense
+/// as the last field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct RemainingBytes(pub Vec<u8>);
+
+impl McRead for RemainingBytes {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        let mut res = vec![];
+        buf.read_to_end(&mut res)?;
+        Ok(RemainingBytes(res))
+    }
+}
+
+impl McWrite for RemainingBytes {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        buf.write_all(&self.0)?;
+        Ok(())
+    }
+}
+
+/// A block position, packed into a single `i64` as 26 bits of x, 26 bits of z
+/// and 12 bits of y (the layout used since 1.14).
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::Position;
+///
+/// let pos = Position::new(18357644, 831, -20882616);
+/// assert_eq!(pos.to_packed(), 0x4607632c15b4833f);
+/// assert_eq!(Position::from_packed(0x4607632c15b4833f), pos);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Position { x, y, z }
+    }
+
+    pub fn to_packed(&self) -> i64 {
+        ((i64::from(self.x) & 0x3FF_FFFF) << 38)
+            | ((i64::from(self.z) & 0x3FF_FFFF) << 12)
+            | (i64::from(self.y) & 0xFFF)
+    }
+
+    pub fn from_packed(packed: i64) -> Self {
+        // shifting left then right sign extends each field
+        Position {
+            x: (packed >> 38) as i32,
+            y: (packed << 52 >> 52) as i32,
+            z: (packed << 26 >> 38) as i32,
+        }
+    }
+}
+
+impl McRead for Position {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        Ok(Position::from_packed(i64::read_from(buf)?))
+    }
+}
+
+impl McWrite for Position {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        self.to_packed().write_to(buf)
+    }
+}
+
+/// A rotation in steps of 1/256 of a full turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    pub fn from_degrees(degrees: f32) -> Self {
+        // just under a full turn rounds up to 256, which is 0 again
+        Angle(((degrees.rem_euclid(360.0) * 256.0 / 360.0).round() as u32 % 256) as u8)
+    }
+
+    pub fn to_degrees(self) -> f32 {
+        f32::from(self.0) * 360.0 / 256.0
+    }
+}
+
+impl McRead for Angle {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        Ok(Angle(u8::read_from(buf)?))
+    }
+}
+
+impl McWrite for Angle {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        self.0.write_to(buf)
+    }
+}
+
+/// A growable set of bits, sent as a VarInt count of longs followed by the
+/// longs. Bit `n` is bit `n % 64` of long `n / 64`.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::BitSet;
+///
+/// let mut bits = BitSet::new();
+/// bits.set(3, true);
+/// bits.set(70, true);
+/// assert!(bits.get(3) && bits.get(70));
+/// assert!(!bits.get(4) && !bits.get(1000));
+/// assert_eq!(bits.words(), &[0b1000, 0b1000000]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_words(words: Vec<u64>) -> Self {
+        BitSet { words }
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        self.words
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        let word = index / 64;
+        if word >= self.words.len() {
+            if !value {
+                return;
+            }
+            self.words.resize(word + 1, 0);
+        }
+        if value {
+            self.words[word] |= 1 << (index % 64);
+        } else {
+            self.words[word] &= !(1 << (index % 64));
+        }
+    }
+}
+
+impl McRead for BitSet {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        let words = Vec::<i64>::read_from(buf)?;
+        Ok(BitSet {
+            words: words.into_iter().map(|word| word as u64).collect(),
+        })
+    }
+}
+
+impl McWrite for BitSet {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        self.words.write_to(buf)
+    }
+}
+
+/// A namespaced location such as `minecraft:stone`. The namespace defaults to
+/// `minecraft` when it is left out.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::Identifier;
+///
+/// let id: Identifier = "stone".parse().unwrap();
+/// assert_eq!(id.namespace, "minecraft");
+/// assert_eq!(id.to_string(), "minecraft:stone");
+///
+/// assert!("Minecraft:Stone".parse::<Identifier>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Identifier {
+    pub namespace: String,
+    pub path: String,
+}
+
+impl Identifier {
+    pub const DEFAULT_NAMESPACE: &'static str = "minecraft";
+
+    pub fn new(namespace: &str, path: &str) -> Result<Self, DataTypeError> {
+        let valid_namespace = namespace
+            .chars()
+            .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '.' | '_' | '-'));
+        let valid_path = path
+            .chars()
+            .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '.' | '_' | '-' | '/'));
+        if !valid_namespace || !valid_path || namespace.is_empty() {
+            return Err(DataTypeError::InvalidIdentifier(format!(
+                "{namespace}:{path}"
+            )));
+        }
+        Ok(Identifier {
+            namespace: namespace.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+impl FromStr for Identifier {
+    type Err = DataTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((namespace, path)) => Identifier::new(namespace, path),
+            None => Identifier::new(Self::DEFAULT_NAMESPACE, s),
+        }
+    }
+}
+
+impl Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
+    }
+}
+
+impl McRead for Identifier {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        String::read_from(buf)?.parse()
+    }
+}
+
+impl McWrite for Identifier {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        self.to_string().write_to(buf)
+    }
+}
@@ -0,0 +1,67 @@
+//! Protocol encryption: the RSA key exchange during login and the AES/CFB8
+//! stream cipher used for everything after it.
+//!
+//! See <https://wiki.vg/Protocol_Encryption>.
+
+use aes::cipher::{inout::InOutBuf, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use rand::{rngs::OsRng, RngCore};
+use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Encrypt, RsaPublicKey};
+
+use crate::{
+    codec::PacketEncryption,
+    error::{Error, Phase},
+};
+
+pub type Aes128Cfb8Enc = cfb8::Encryptor<aes::Aes128>;
+pub type Aes128Cfb8Dec = cfb8::Decryptor<aes::Aes128>;
+
+/// Generate the random 16 byte secret both sides use as the AES key and IV.
+pub fn generate_shared_secret() -> [u8; 16] {
+    let mut secret = [0u8; 16];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// Encrypt `data` with the DER encoded public key the server sent in its
+/// Encryption Request.
+pub fn encrypt_with_public_key(public_key: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+    let key = RsaPublicKey::from_public_key_der(public_key)
+        .map_err(|err| Error::invalid_response(Phase::Login, format!("Bad public key: {err}")))?;
+    Ok(key.encrypt(&mut OsRng, Pkcs1v15Encrypt, data)?)
+}
+
+/// The AES/CFB8 cipher vanilla uses, keyed with the shared secret.
+#[derive(Debug)]
+pub struct Aes128Cfb8 {
+    encryptor: Aes128Cfb8Enc,
+    decryptor: Aes128Cfb8Dec,
+}
+
+impl Aes128Cfb8 {
+    pub fn new(shared_secret: &[u8; 16]) -> Self {
+        Aes128Cfb8 {
+            encryptor: Aes128Cfb8Enc::new(shared_secret.into(), shared_secret.into()),
+            decryptor: Aes128Cfb8Dec::new(shared_secret.into(), shared_secret.into()),
+        }
+    }
+}
+
+impl PacketEncryption for Aes128Cfb8 {
+    fn encrypt(&mut self, data: &mut [u8]) {
+        encrypt_packet(&mut self.encryptor, data);
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) {
+        decrypt_packet(&mut self.decryptor, data);
+    }
+}
+
+pub fn encrypt_packet(cipher: &mut Aes128Cfb8Enc, data: &mut [u8]) {
+    let (chunks, _rest) = InOutBuf::from(data).into_chunks();
+    cipher.encrypt_blocks_inout_mut(chunks);
+}
+
+pub fn decrypt_packet(cipher: &mut Aes128Cfb8Dec, data: &mut [u8]) {
+    let (chunks, _rest) = InOutBuf::from(data).into_chunks();
+    cipher.decrypt_blocks_inout_mut(chunks);
+}
@@ -0,0 +1,195 @@
+//! The error type shared by everything that talks to a server.
+//!
+//! Every failure is one [`Error`], so a scanner can tell a refused connection
+//! from a timeout, a server that answered with garbage, or a packet that blew
+//! through the size limit. I/O errors keep the [`Phase`] they happened in and
+//! the underlying [`io::Error`].
+//!
+//! # Examples
+//!
+//! ```
+//! use minecraft_utilities::{parse_version, Error, ServerAddress};
+//!
+//! assert!(matches!(
+//!     parse_version("1.99"),
+//!     Err(Error::UnknownVersion(version)) if version == "1.99"
+//! ));
+//! assert!(matches!(
+//!     ServerAddress::try_from("localhost:nope"),
+//!     Err(Error::InvalidAddress { .. })
+//! ));
+//!
+//! // errors can cross threads, so pings can run on a multi-threaded runtime
+//! fn assert_send_sync<T: Send + Sync + 'static>() {}
+//! assert_send_sync::<Error>();
+//! ```
+
+use std::{fmt, io};
+
+use thiserror::Error;
+
+use crate::{
+    auth::AuthError, client::LoginError, data_types::DataTypeError, nbt::NbtError, rcon::RconError,
+    resolve_address::ResolverError, server_address::ServerAddress, session::SessionError,
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// What the crate was doing when an error happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Connect,
+    Handshake,
+    Status,
+    Login,
+    Play,
+    Rcon,
+    LegacyPing,
+    BedrockPing,
+    Query,
+    LanDiscovery,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Phase::Connect => "connect",
+            Phase::Handshake => "handshake",
+            Phase::Status => "status",
+            Phase::Login => "login",
+            Phase::Play => "play",
+            Phase::Rcon => "rcon",
+            Phase::LegacyPing => "legacy ping",
+            Phase::BedrockPing => "bedrock ping",
+            Phase::Query => "query",
+            Phase::LanDiscovery => "LAN discovery",
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Could not connect to {address}: {source}")]
+    Connect {
+        address: ServerAddress,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Timed out during {0}")]
+    Timeout(Phase),
+    #[error("I/O error during {phase}: {source}")]
+    Io {
+        phase: Phase,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Packet of {length} bytes exceeds the limit of {limit} bytes")]
+    PacketTooLarge { length: usize, limit: usize },
+    #[error("Invalid response during {phase}: {message}")]
+    InvalidResponse { phase: Phase, message: String },
+    #[error("Invalid server address '{address}': {reason}")]
+    InvalidAddress {
+        address: String,
+        reason: &'static str,
+    },
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("Unable to find version '{0}' in version table")]
+    UnknownVersion(String),
+    #[error("Invalid status response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Encryption failed: {0}")]
+    Encryption(#[from] rsa::Error),
+    #[error(transparent)]
+    DataType(#[from] DataTypeError),
+    #[error(transparent)]
+    Nbt(#[from] NbtError),
+    #[error(transparent)]
+    Login(#[from] LoginError),
+    #[error(transparent)]
+    Session(SessionError),
+    #[error(transparent)]
+    Rcon(#[from] RconError),
+    #[error(transparent)]
+    Resolver(#[from] ResolverError),
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+}
+
+impl Error {
+    /// Wrap an I/O error that happened during `phase`. Errors that carry one
+    /// of ours (like the codec's size limit) are unwrapped, and
+    /// [`InvalidData`](io::ErrorKind::InvalidData) becomes
+    /// [`Error::InvalidResponse`].
+    pub(crate) fn io(phase: Phase, source: io::Error) -> Self {
+        if source.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            let inner = source.into_inner().expect("checked above");
+            return *inner.downcast::<Error>().expect("checked above");
+        }
+        if source.kind() == io::ErrorKind::InvalidData {
+            return Error::InvalidResponse {
+                phase,
+                message: source.to_string(),
+            };
+        }
+        Error::Io { phase, source }
+    }
+
+    pub(crate) fn invalid_response(phase: Phase, message: impl Into<String>) -> Self {
+        Error::InvalidResponse {
+            phase,
+            message: message.into(),
+        }
+    }
+
+    /// The phase the error happened in, if it is tied to one.
+    pub fn phase(&self) -> Option<Phase> {
+        match self {
+            Error::Connect { .. } => Some(Phase::Connect),
+            Error::Timeout(phase)
+            | Error::Io { phase, .. }
+            | Error::InvalidResponse { phase, .. } => Some(*phase),
+            Error::Login(_) | Error::Auth(_) => Some(Phase::Login),
+            Error::Session(_) => Some(Phase::Play),
+            Error::Rcon(_) => Some(Phase::Rcon),
+            _ => None,
+        }
+    }
+
+    /// The server actively refused the connection.
+    pub fn is_refused(&self) -> bool {
+        matches!(self, Error::Connect { source, .. }
+            if source.kind() == io::ErrorKind::ConnectionRefused)
+    }
+
+    /// The server (or the network) didn't answer in time.
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            Error::Timeout(_) => true,
+            Error::Connect { source, .. } | Error::Io { source, .. } => {
+                source.kind() == io::ErrorKind::TimedOut
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<SessionError> for Error {
+    fn from(err: SessionError) -> Self {
+        match err {
+            SessionError::ConnectionLost(source) => Error::io(Phase::Play, source),
+            err => Error::Session(err),
+        }
+    }
+}
+
+/// Attach a [`Phase`] to I/O results.
+pub(crate) trait IoResultExt<T> {
+    fn during(self, phase: Phase) -> Result<T>;
+}
+
+impl<T> IoResultExt<T> for io::Result<T> {
+    fn during(self, phase: Phase) -> Result<T> {
+        self.map_err(|err| Error::io(phase, err))
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+
+use futures::stream::{self, BoxStream, StreamExt};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use tokio::{
+    net::{ToSocketAddrs, UdpSocket},
+    time::{self, MissedTickBehavior},
+};
+
+use crate::{
+    chat::ChatComponent,
+    error::{Error, IoResultExt, Phase},
+    server_address::ServerAddress,
+};
+
+/// Where Java clients listen for "Open to LAN" announcements.
+pub const JAVA_LAN_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(224, 0, 2, 60), 4445);
+
+/// How often [`JavaLanBroadcaster`] announces unless told otherwise, the
+/// same as vanilla.
+pub const DEFAULT_JAVA_LAN_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// A world announced on the local network.
+#[derive(Debug, Clone)]
+pub struct JavaLanGame {
+    /// Where to join, the announcer's IP with the announced port.
+    pub address: ServerAddress,
+    pub motd: String,
+}
+
+impl JavaLanGame {
+    pub fn motd_component(&self) -> ChatComponent {
+        ChatComponent::from_legacy(&self.motd)
+    }
+
+    /// Read an announcement sent by `from`. A missing MOTD is empty, but
+    /// there has to be a port.
+    ///
+    /// Vanilla only ever announces a port, the host is whoever sent it. A
+    /// full `host:port` is accepted too, since some tools send one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minecraft_utilities::JavaLanGame;
+    ///
+    /// let game = JavaLanGame::parse(
+    ///     "[MOTD]Steve - New World[/MOTD][AD]41234[/AD]",
+    ///     "192.168.1.20:53000".parse().unwrap(),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(game.motd, "Steve - New World");
+    /// assert_eq!(game.address.to_string(), "192.168.1.20:41234");
+    /// ```
+    pub fn parse(announcement: &str, from: SocketAddr) -> Result<Self, Error> {
+        let motd = between(announcement, "[MOTD]", "[/MOTD]").unwrap_or_default();
+        // the ad comes after the MOTD, which could contain "[AD]" itself
+        let rest = announcement
+            .find("[/MOTD]")
+            .map_or(announcement, |end| &announcement[end..]);
+        let ad = between(rest, "[AD]", "[/AD]")
+            .ok_or_else(|| {
+                Error::invalid_response(
+                    Phase::LanDiscovery,
+                    format!("No port in announcement '{announcement}'"),
+                )
+            })?
+            .trim();
+
+        let address = match ad.parse::<u16>() {
+            Ok(port) => ServerAddress::from(SocketAddr::new(from.ip().to_canonical(), port)),
+            Err(_) if ad.contains(':') => ServerAddress::try_from(ad)?,
+            Err(_) => {
+                return Err(Error::invalid_response(
+                    Phase::LanDiscovery,
+                    format!("Bad port '{ad}' in announcement"),
+                ))
+            }
+        };
+        Ok(JavaLanGame {
+            address,
+            motd: motd.to_string(),
+        })
+    }
+}
+
+fn between<'a>(string: &'a str, start: &str, end: &str) -> Option<&'a str> {
+    let after_start = &string[string.find(start)? + start.len()..];
+    Some(&after_start[..after_start.find(end)?])
+}
+
+fn announcement(motd: &str, port: u16) -> String {
+    format!("[MOTD]{motd}[/MOTD][AD]{port}[/AD]")
+}
+
+/// Listens for worlds opened to LAN, like the multiplayer screen does.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::Ipv4Addr;
+///
+/// use futures::StreamExt;
+/// use minecraft_utilities::JavaLanListener;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), minecraft_utilities::Error> {
+/// let mut games = JavaLanListener::join(Ipv4Addr::UNSPECIFIED).await?.discover();
+/// while let Some(game) = games.next().await {
+///     let game = game?;
+///     println!("{}: {}", game.address, game.motd);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct JavaLanListener {
+    socket: UdpSocket,
+}
+
+impl JavaLanListener {
+    /// Listen on port 4445 and join [`JAVA_LAN_GROUP`] on the interface
+    /// with address `interface`, or one the OS picks for
+    /// [`Ipv4Addr::UNSPECIFIED`]. The port is shared, so clients on the same
+    /// machine still hear announcements.
+    pub async fn join(interface: Ipv4Addr) -> Result<Self, Error> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+            .during(Phase::LanDiscovery)?;
+        socket.set_reuse_address(true).during(Phase::LanDiscovery)?;
+        socket
+            .bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, JAVA_LAN_GROUP.port())).into())
+            .during(Phase::LanDiscovery)?;
+        socket
+            .join_multicast_v4(JAVA_LAN_GROUP.ip(), &interface)
+            .during(Phase::LanDiscovery)?;
+        socket.set_nonblocking(true).during(Phase::LanDiscovery)?;
+        Ok(JavaLanListener {
+            socket: UdpSocket::from_std(socket.into()).during(Phase::LanDiscovery)?,
+        })
+    }
+
+    /// Listen for announcements sent straight to `addr`, without joining
+    /// any group.
+    pub async fn bind(addr: impl ToSocketAddrs) -> Result<Self, Error> {
+        Ok(JavaLanListener {
+            socket: UdpSocket::bind(addr).await.during(Phase::LanDiscovery)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.socket.local_addr().during(Phase::LanDiscovery)
+    }
+
+    /// Wait for the next announcement, skipping anything that isn't one.
+    pub async fn recv(&self) -> Result<JavaLanGame, Error> {
+        let mut buf = vec![0; 1024];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                // ICMP port unreachable, on Windows
+                Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(err) => return Err(Error::io(Phase::LanDiscovery, err)),
+            };
+            let announcement = String::from_utf8_lossy(&buf[..len]);
+            if let Ok(game) = JavaLanGame::parse(&announcement, from) {
+                return Ok(game);
+            }
+        }
+    }
+
+    /// Yield every announcement as it arrives. Games announce themselves
+    /// over and over, so each shows up many times. Failing to receive ends
+    /// the stream.
+    pub fn discover(self) -> BoxStream<'static, Result<JavaLanGame, Error>> {
+        stream::unfold(Some(self), |listener| async move {
+            let listener = listener?;
+            match listener.recv().await {
+                Ok(game) => Some((Ok(game), Some(listener))),
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Announces a server as a world opened to LAN, so it shows up in the
+/// multiplayer screen of players on the local network.
+///
+/// Clients join the announced port on whatever IP the announcement came
+/// from, so a server on another machine has to be reached through a proxy
+/// running here.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::{JavaLanBroadcaster, JavaLanListener};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), minecraft_utilities::Error> {
+/// let listener = JavaLanListener::bind("127.0.0.1:0").await?;
+/// let broadcaster = JavaLanBroadcaster::bind("127.0.0.1:0".parse().unwrap(), "My Proxy", 25565)
+///     .await?
+///     .with_target(listener.local_addr()?);
+/// tokio::spawn(broadcaster.run());
+///
+/// let game = listener.recv().await?;
+/// assert_eq!(game.motd, "My Proxy");
+/// assert_eq!(game.address.to_string(), "127.0.0.1:25565");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct JavaLanBroadcaster {
+    socket: UdpSocket,
+    announcement: String,
+    target: SocketAddr,
+    interval: Duration,
+}
+
+impl JavaLanBroadcaster {
+    /// Announce `motd` for the server on `port` of this machine from `addr`,
+    /// `0.0.0.0:0` to let the OS pick the interface. Announcements go to
+    /// [`JAVA_LAN_GROUP`] until told otherwise.
+    pub async fn bind(addr: SocketAddrV4, motd: &str, port: u16) -> Result<Self, Error> {
+        let socket = UdpSocket::bind(addr).await.during(Phase::LanDiscovery)?;
+        if !addr.ip().is_unspecified() {
+            SockRef::from(&socket)
+                .set_multicast_if_v4(addr.ip())
+                .during(Phase::LanDiscovery)?;
+        }
+        Ok(JavaLanBroadcaster {
+            socket,
+            announcement: announcement(motd, port),
+            target: JAVA_LAN_GROUP.into(),
+            interval: DEFAULT_JAVA_LAN_INTERVAL,
+        })
+    }
+
+    /// Where announcements are sent, a multicast group or a single
+    /// listener.
+    pub fn with_target(mut self, target: SocketAddr) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// How long to wait between announcements. Defaults to
+    /// [`DEFAULT_JAVA_LAN_INTERVAL`].
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.socket.local_addr().during(Phase::LanDiscovery)
+    }
+
+    /// Announce every interval, starting now, until sending fails.
+    pub async fn run(self) -> Result<(), Error> {
+        let mut ticker = time::interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            self.socket
+                .send_to(self.announcement.as_bytes(), self.target)
+                .await
+                .during(Phase::LanDiscovery)?;
+        }
+    }
+}
@@ -1,24 +1,97 @@
 #![doc = include_str!("../README.md")]
 
+mod error;
+pub use error::{Error, Phase, Result};
+
 mod packetutil;
+pub use packetutil::MinecraftPacket;
+
+mod data_types;
+pub use data_types::{
+    read_bounded_string, Angle, BitSet, DataTypeError, Identifier, McRead, McWrite, Position,
+    RemainingBytes, VarInt, VarLong, MAX_ARRAY_LENGTH, MAX_STRING_LENGTH,
+};
+
+pub mod nbt;
+
+pub mod packets;
 
 mod rcon;
-pub use rcon::{RconClient, RconError};
+pub use rcon::{
+    RconClient, RconCodec, RconError, RconHandle, RconHandler, RconPacket, RconServer,
+    DEFAULT_RCON_MAX_PAYLOAD, DEFAULT_RCON_TIMEOUT, MAX_COMMAND_PAYLOAD, MAX_RESPONSE_PAYLOAD,
+};
 
 mod ping;
-pub use ping::Ping;
+pub use ping::{AnyPing, LegacyPingResult, LegacyPingVersion, Ping, PingTimings, StatusPing};
+
+mod chat;
+pub use chat::{
+    strip_legacy_codes, ChatColor, ChatComponent, ChatContent, ChatStyle, ClickAction, ClickEvent,
+    HoverEvent, NamedColor, SECTION_SIGN,
+};
+
+mod status_response;
+pub use status_response::{
+    ForgeChannel, ForgeData, ForgeMod, LegacyForgeMod, LegacyForgeModInfo, StatusPlayerSample,
+    StatusPlayers, StatusResponse, StatusVersion,
+};
+
+mod query;
+pub use query::{
+    Query, QueryBasicStat, QueryFullStat, QueryServer, QueryStats, DEFAULT_CHALLENGE_LIFETIME,
+    DEFAULT_QUERY_TIMEOUT,
+};
 
 mod ping_bedrock;
-pub use ping_bedrock::{BedrockServerEdition, BedrockServerGamemode, PingBedrock};
+pub use ping_bedrock::{
+    BedrockPinger, BedrockServerEdition, BedrockServerGamemode, PingBedrock,
+    DEFAULT_BEDROCK_ATTEMPTS, DEFAULT_BEDROCK_TIMEOUT,
+};
+
+mod bedrock_lan;
+pub use bedrock_lan::{
+    BedrockAdvertisement, BedrockLan, BedrockLanAdvertiser, BedrockLanServer, BEDROCK_LAN_PORT,
+    DEFAULT_BEDROCK_LAN_INTERVAL,
+};
+
+mod raknet;
+
+mod java_lan;
+pub use java_lan::{
+    JavaLanBroadcaster, JavaLanGame, JavaLanListener, DEFAULT_JAVA_LAN_INTERVAL, JAVA_LAN_GROUP,
+};
+
+mod codec;
+pub use codec::{
+    MinecraftCodec, PacketCompression, PacketEncryption, ZlibCompression, DEFAULT_SANITY_LIMIT,
+};
+
+mod encryption;
+pub use encryption::Aes128Cfb8;
+
+mod auth;
+pub use auth::{
+    server_hash, AuthError, GameProfile, MockSessionService, MojangSessionService, SessionService,
+    MOJANG_SESSION_SERVER,
+};
 
 mod client;
-pub use client::{Client, OnlineModeResults};
+pub use client::{Client, GameProfileProperty, LoginError, LoginSuccess, OnlineModeResults};
+
+mod session;
+pub use session::{PacketSender, PacketStream, ProtocolState, SessionError};
 
 mod server_address;
 pub use server_address::ServerAddress;
 
 mod resolve_address;
-pub use resolve_address::resolve_address;
+pub use resolve_address::{resolve_address, ResolverError};
 
 mod versions;
-pub use versions::parse_version;
+pub use versions::{
+    bedrock_version_consistent, bedrock_version_names, parse_bedrock_version, parse_version,
+    PROTOCOL_1_16, PROTOCOL_1_19, PROTOCOL_1_19_1, PROTOCOL_1_19_3, PROTOCOL_1_19_4, PROTOCOL_1_20,
+    PROTOCOL_1_20_2, PROTOCOL_1_20_3, PROTOCOL_1_20_5, PROTOCOL_1_21, PROTOCOL_1_21_2,
+    PROTOCOL_1_21_4,
+};
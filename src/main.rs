@@ -1,33 +1,23 @@
 // Crimsongale is mid
 
-use std::error::Error;
-use std::time::Duration;
-
-mod ping;
-use ping::Ping;
-
-mod client;
-use client::Client;
-
-mod server_address;
-use server_address::ServerAddress;
+mod console;
 
-mod resolve_address;
-use resolve_address::resolve_address;
-
-mod packetutil;
-
-mod versions;
-use versions::parse_version;
-
-mod ping_bedrock;
-use ping_bedrock::{BedrockServerEdition, PingBedrock};
+use std::time::Duration;
 
+use minecraft_utilities::{
+    parse_version, resolve_address, BedrockServerEdition, Client, Error, Phase, Ping, PingBedrock,
+    ServerAddress,
+};
 use tokio::time::timeout;
 use uuid::uuid;
 
 #[tokio::main]
-pub async fn main() -> Result<(), Box<dyn Error>> {
+pub async fn main() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("rcon") {
+        std::process::exit(console::run(&args[2..]).await);
+    }
+
     let bedrock_addr = ServerAddress::try_from("127.0.0.1:19132")?;
     let bedrock_ping = PingBedrock::ping(&bedrock_addr);
     let bedrock_ping_timeout = timeout(Duration::from_millis(2500), bedrock_ping).await;
@@ -55,8 +45,6 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
         parse_version(check_version_number)?
     );
 
-    let args: Vec<String> = std::env::args().collect();
-
     let mut lookup_ip = "localhost";
     if args.len() == 2 {
         lookup_ip = args[1].as_str();
@@ -106,7 +94,7 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
 
     let mut protocol_ver: i32 = 0;
 
-    let test_ping = Ping::ping(
+    let test_ping = Ping::ping_with_latency(
         &address.host,
         Some(address.port),
         None,
@@ -116,7 +104,10 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
     let ping_result = timeout(Duration::from_millis(1000), test_ping).await;
     match ping_result {
         Ok(ping_result) => match ping_result {
-            Ok(res) => protocol_ver = Ping::get_protocol_version(&res)?,
+            Ok(res) => {
+                println!("latency: {:?}", res.latency);
+                protocol_ver = Ping::get_protocol_version(&res.status)?
+            }
             Err(err) => {
                 println!("An error occured (4): {}", err);
             }
@@ -153,8 +144,9 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
         Some("Gamer"),
         Some(uuid!("b64dfb9c-82ec-426d-918c-73f62afc4e01")),
     );
-    let (is_online_mode_result, other) =
-        timeout(Duration::from_millis(1000), test_is_online_mode).await??;
+    let (is_online_mode_result, other) = timeout(Duration::from_millis(1000), test_is_online_mode)
+        .await
+        .map_err(|_| Error::Timeout(Phase::Login))??;
     println!(
         "Online mode results: {:?}, other: {}",
         is_online_mode_result,
@@ -0,0 +1,318 @@
+use std::fmt;
+
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+
+use super::{ByteArray, Compound, IntArray, LongArray, NbtError, Tag};
+
+/// Convert a tag into anything deserializable, the reverse of
+/// [`to_tag`](super::to_tag). Bytes deserialize into `bool`s and the array
+/// tags into sequences.
+pub fn from_tag<T: DeserializeOwned>(tag: Tag) -> Result<T, NbtError> {
+    T::deserialize(tag)
+}
+
+impl de::Error for NbtError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        NbtError::Custom(msg.to_string())
+    }
+}
+
+fn visit_tags<'de, V: Visitor<'de>>(
+    tags: impl Iterator<Item = Tag>,
+    visitor: V,
+) -> Result<V::Value, NbtError> {
+    let mut seq = SeqDeserializer::new(tags);
+    let res = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(res)
+}
+
+impl<'de> Deserializer<'de> for Tag {
+    type Error = NbtError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            Tag::Byte(val) => visitor.visit_i8(val),
+            Tag::Short(val) => visitor.visit_i16(val),
+            Tag::Int(val) => visitor.visit_i32(val),
+            Tag::Long(val) => visitor.visit_i64(val),
+            Tag::Float(val) => visitor.visit_f32(val),
+            Tag::Double(val) => visitor.visit_f64(val),
+            Tag::ByteArray(vals) => visit_tags(vals.into_iter().map(Tag::Byte), visitor),
+            Tag::String(val) => visitor.visit_string(val),
+            Tag::List(vals) => visit_tags(vals.into_iter(), visitor),
+            Tag::Compound(compound) => {
+                let mut map = MapDeserializer::new(compound.into_iter());
+                let res = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(res)
+            }
+            Tag::IntArray(vals) => visit_tags(vals.into_iter().map(Tag::Int), visitor),
+            Tag::LongArray(vals) => visit_tags(vals.into_iter().map(Tag::Long), visitor),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            Tag::Byte(val) => visitor.visit_bool(val != 0),
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            Tag::ByteArray(vals) => {
+                visitor.visit_byte_buf(vals.into_iter().map(|val| val as u8).collect())
+            }
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        // missing fields are `None`, anything that is there is `Some`
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self {
+            Tag::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Tag::Compound(compound) if compound.len() == 1 => {
+                let (variant, value) = compound.into_iter().next().expect("compound has one entry");
+                visitor.visit_enum(EnumDeserializer { variant, value })
+            }
+            _ => Err(NbtError::Custom(
+                "Expected a string or a compound with one entry for an enum".to_string(),
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        seq tuple tuple_struct map struct identifier
+    }
+}
+
+impl IntoDeserializer<'_, NbtError> for Tag {
+    type Deserializer = Tag;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Tag,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = NbtError;
+    type Variant = Tag;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant: de::value::StringDeserializer<NbtError> = self.variant.into_deserializer();
+        Ok((seed.deserialize(variant)?, self.value))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Tag {
+    type Error = NbtError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+}
+
+struct TagVisitor;
+
+impl<'de> Visitor<'de> for TagVisitor {
+    type Value = Tag;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an NBT tag")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Tag, E> {
+        Ok(Tag::Byte(v.into()))
+    }
+
+    fn visit_i8<E: de::Error>(self, v: i8) -> Result<Tag, E> {
+        Ok(Tag::Byte(v))
+    }
+
+    fn visit_i16<E: de::Error>(self, v: i16) -> Result<Tag, E> {
+        Ok(Tag::Short(v))
+    }
+
+    fn visit_i32<E: de::Error>(self, v: i32) -> Result<Tag, E> {
+        Ok(Tag::Int(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Tag, E> {
+        Ok(Tag::Long(v))
+    }
+
+    fn visit_u8<E: de::Error>(self, v: u8) -> Result<Tag, E> {
+        Ok(Tag::Short(v.into()))
+    }
+
+    fn visit_u16<E: de::Error>(self, v: u16) -> Result<Tag, E> {
+        Ok(Tag::Int(v.into()))
+    }
+
+    fn visit_u32<E: de::Error>(self, v: u32) -> Result<Tag, E> {
+        Ok(Tag::Long(v.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Tag, E> {
+        i64::try_from(v)
+            .map(Tag::Long)
+            .map_err(|_| E::custom("u64 too large for NBT"))
+    }
+
+    fn visit_f32<E: de::Error>(self, v: f32) -> Result<Tag, E> {
+        Ok(Tag::Float(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Tag, E> {
+        Ok(Tag::Double(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Tag, E> {
+        Ok(Tag::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Tag, E> {
+        Ok(Tag::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Tag, E> {
+        Ok(Tag::ByteArray(v.iter().map(|val| *val as i8).collect()))
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<Tag, D::Error> {
+        Tag::deserialize(deserializer)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Tag, D::Error> {
+        Tag::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Tag, A::Error> {
+        let mut vals: Vec<Tag> = vec![];
+        while let Some(val) = seq.next_element::<Tag>()? {
+            if vals.first().is_some_and(|first| first.id() != val.id()) {
+                return Err(de::Error::custom(NbtError::MixedList));
+            }
+            vals.push(val);
+        }
+        Ok(Tag::List(vals))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Tag, A::Error> {
+        let mut res = Compound::new();
+        while let Some((key, val)) = map.next_entry::<String, Tag>()? {
+            res.insert(key, val);
+        }
+        Ok(Tag::Compound(res))
+    }
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TagVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Compound {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Tag::deserialize(deserializer)? {
+            Tag::Compound(compound) => Ok(compound),
+            _ => Err(de::Error::custom("expected a compound")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteArray {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(ByteArray(Vec::deserialize(deserializer)?))
+    }
+}
+
+impl<'de> Deserialize<'de> for IntArray {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(IntArray(Vec::deserialize(deserializer)?))
+    }
+}
+
+impl<'de> Deserialize<'de> for LongArray {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(LongArray(Vec::deserialize(deserializer)?))
+    }
+}
@@ -0,0 +1,661 @@
+//! Named Binary Tag, the format used for registries, chunks, items, text
+//! components (since 1.20.3) and files like `servers.dat` and `level.dat`.
+//!
+//! There are two binary layouts:
+//!
+//! -   The file form, where the root compound has a name, read and written by
+//!     [`read_named`]/[`write_named`] and, with compression, by
+//!     [`read_file`]/[`write_file`]. Before 1.20.2 packets used it too (with
+//!     an empty name).
+//! -   The network form used by packets since 1.20.2, where the root has no
+//!     name and can be any tag, read and written by
+//!     [`read_network`]/[`write_network`].
+//!
+//! [`Tag`] also converts to and from SNBT through [`Display`](std::fmt::Display)
+//! and [`FromStr`](std::str::FromStr), and anything implementing serde's
+//! traits converts to and from tags through [`to_tag`] and [`from_tag`].
+//!
+//! See <https://wiki.vg/NBT>.
+//!
+//! # Examples
+//!
+//! ```
+//! use minecraft_utilities::nbt::{self, Tag};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Server {
+//!     name: String,
+//!     ip: String,
+//!     #[serde(rename = "acceptTextures")]
+//!     accept_textures: Option<bool>,
+//! }
+//!
+//! let server = Server {
+//!     name: "Shrecked".to_string(),
+//!     ip: "shrecked.dev".to_string(),
+//!     accept_textures: Some(true),
+//! };
+//!
+//! let tag = nbt::to_tag(&server).unwrap();
+//! assert_eq!(
+//!     tag.to_string(),
+//!     r#"{name:"Shrecked",ip:"shrecked.dev",acceptTextures:1b}"#
+//! );
+//!
+//! let mut bytes = vec![];
+//! nbt::write_network(&mut bytes, Some(&tag)).unwrap();
+//! let tag = nbt::read_network(&mut bytes.as_slice()).unwrap().unwrap();
+//! assert_eq!(nbt::from_tag::<Server>(tag).unwrap(), server);
+//! ```
+
+mod de;
+mod ser;
+mod snbt;
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use thiserror::Error;
+
+use crate::data_types::{DataTypeError, McRead, McWrite};
+
+pub use de::from_tag;
+pub use ser::to_tag;
+
+pub const TAG_END: u8 = 0;
+pub const TAG_BYTE: u8 = 1;
+pub const TAG_SHORT: u8 = 2;
+pub const TAG_INT: u8 = 3;
+pub const TAG_LONG: u8 = 4;
+pub const TAG_FLOAT: u8 = 5;
+pub const TAG_DOUBLE: u8 = 6;
+pub const TAG_BYTE_ARRAY: u8 = 7;
+pub const TAG_STRING: u8 = 8;
+pub const TAG_LIST: u8 = 9;
+pub const TAG_COMPOUND: u8 = 10;
+pub const TAG_INT_ARRAY: u8 = 11;
+pub const TAG_LONG_ARRAY: u8 = 12;
+
+/// How deeply lists and compounds may nest, the same limit vanilla uses.
+pub const MAX_DEPTH: usize = 512;
+
+#[derive(Error, Debug)]
+pub enum NbtError {
+    #[error("Unexpected end of data")]
+    UnexpectedEof,
+    #[error("IO error: {0}")]
+    Io(io::Error),
+    #[error("Invalid tag id {0}")]
+    InvalidTagId(u8),
+    #[error("Negative length {0}")]
+    NegativeLength(i32),
+    #[error("Nested deeper than {MAX_DEPTH} levels")]
+    TooDeep,
+    #[error("Invalid modified UTF-8 in string")]
+    InvalidString,
+    #[error("String is too long ({0} bytes)")]
+    StringTooLong(usize),
+    #[error("List elements must all be the same type")]
+    MixedList,
+    #[error("Root tag must be a compound, found tag id {0}")]
+    RootNotCompound(u8),
+    #[error("Invalid SNBT at position {position}: {message}")]
+    Snbt { message: String, position: usize },
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl From<io::Error> for NbtError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => NbtError::UnexpectedEof,
+            _ => NbtError::Io(err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    /// Every element has the same type.
+    List(Vec<Tag>),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    pub fn id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => TAG_BYTE,
+            Tag::Short(_) => TAG_SHORT,
+            Tag::Int(_) => TAG_INT,
+            Tag::Long(_) => TAG_LONG,
+            Tag::Float(_) => TAG_FLOAT,
+            Tag::Double(_) => TAG_DOUBLE,
+            Tag::ByteArray(_) => TAG_BYTE_ARRAY,
+            Tag::String(_) => TAG_STRING,
+            Tag::List(_) => TAG_LIST,
+            Tag::Compound(_) => TAG_COMPOUND,
+            Tag::IntArray(_) => TAG_INT_ARRAY,
+            Tag::LongArray(_) => TAG_LONG_ARRAY,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// The value of any integer tag, widened to an `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Tag::Byte(val) => Some((*val).into()),
+            Tag::Short(val) => Some((*val).into()),
+            Tag::Int(val) => Some((*val).into()),
+            Tag::Long(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    /// The value of any number tag as an `f64`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Tag::Float(val) => Some((*val).into()),
+            Tag::Double(val) => Some(*val),
+            other => other.as_i64().map(|val| val as f64),
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&Compound> {
+        match self {
+            Tag::Compound(val) => Some(val),
+            _ => None,
+        }
+    }
+}
+
+impl From<Compound> for Tag {
+    fn from(value: Compound) -> Self {
+        Tag::Compound(value)
+    }
+}
+
+impl From<&str> for Tag {
+    fn from(value: &str) -> Self {
+        Tag::String(value.to_string())
+    }
+}
+
+impl From<String> for Tag {
+    fn from(value: String) -> Self {
+        Tag::String(value)
+    }
+}
+
+macro_rules! impl_from_number {
+    ($($ty:ty => $variant:ident),*) => {
+        $(
+            impl From<$ty> for Tag {
+                fn from(value: $ty) -> Self {
+                    Tag::$variant(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_number!(i8 => Byte, i16 => Short, i32 => Int, i64 => Long, f32 => Float, f64 => Double);
+
+impl From<bool> for Tag {
+    fn from(value: bool) -> Self {
+        Tag::Byte(value.into())
+    }
+}
+
+/// A compound tag. Entries keep the order they were read or inserted in, so
+/// reading and writing a tag gives back the same bytes.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Compound {
+    entries: Vec<(String, Tag)>,
+}
+
+impl Compound {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        self.entries
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, tag)| tag)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Tag> {
+        self.entries
+            .iter_mut()
+            .find(|(name, _)| name == key)
+            .map(|(_, tag)| tag)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Set `key`, returning the tag it replaced.
+    pub fn insert(&mut self, key: impl Into<String>, tag: impl Into<Tag>) -> Option<Tag> {
+        let key = key.into();
+        let tag = tag.into();
+        match self.get_mut(&key) {
+            Some(existing) => Some(std::mem::replace(existing, tag)),
+            None => {
+                self.entries.push((key, tag));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Tag> {
+        let idx = self.entries.iter().position(|(name, _)| name == key)?;
+        Some(self.entries.remove(idx).1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Tag)> {
+        self.entries.iter().map(|(name, tag)| (name, tag))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(name, _)| name)
+    }
+}
+
+impl<K: Into<String>, V: Into<Tag>> FromIterator<(K, V)> for Compound {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut res = Compound::new();
+        for (key, tag) in iter {
+            res.insert(key, tag);
+        }
+        res
+    }
+}
+
+impl IntoIterator for Compound {
+    type Item = (String, Tag);
+    type IntoIter = std::vec::IntoIter<(String, Tag)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+/// A `Vec<i8>` that serializes to a byte array tag rather than a list.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ByteArray(pub Vec<i8>);
+
+/// A `Vec<i32>` that serializes to an int array tag rather than a list.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IntArray(pub Vec<i32>);
+
+/// A `Vec<i64>` that serializes to a long array tag rather than a list, like
+/// heightmaps and block states in chunk data.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LongArray(pub Vec<i64>);
+
+/// Compression for NBT files. `servers.dat` is uncompressed, `level.dat`
+/// and player data are gzipped and region file chunks are usually zlib.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileCompression {
+    None,
+    Gzip,
+    Zlib,
+}
+
+/// Read a nameless network root (1.20.2+). A lone `TAG_End` means there is
+/// no tag and gives `None`.
+pub fn read_network(buf: &mut impl Read) -> Result<Option<Tag>, NbtError> {
+    match read_u8(buf)? {
+        TAG_END => Ok(None),
+        id => Ok(Some(read_payload(buf, id, 0)?)),
+    }
+}
+
+pub fn write_network(buf: &mut impl Write, tag: Option<&Tag>) -> Result<(), NbtError> {
+    match tag {
+        Some(tag) => {
+            buf.write_all(&[tag.id()])?;
+            write_payload(buf, tag)
+        }
+        None => Ok(buf.write_all(&[TAG_END])?),
+    }
+}
+
+/// A tag in the network form, for packet fields. `None` is a lone
+/// `TAG_End`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NetworkTag(pub Option<Tag>);
+
+impl McRead for NetworkTag {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        Ok(NetworkTag(read_network(buf)?))
+    }
+}
+
+impl McWrite for NetworkTag {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        Ok(write_network(buf, self.0.as_ref())?)
+    }
+}
+
+/// Read an uncompressed root compound along with its name.
+pub fn read_named(buf: &mut impl Read) -> Result<(String, Compound), NbtError> {
+    match read_u8(buf)? {
+        TAG_COMPOUND => {
+            let name = read_string(buf)?;
+            match read_payload(buf, TAG_COMPOUND, 0)? {
+                Tag::Compound(compound) => Ok((name, compound)),
+                _ => unreachable!("compound payloads are compounds"),
+            }
+        }
+        id => Err(NbtError::RootNotCompound(id)),
+    }
+}
+
+pub fn write_named(buf: &mut impl Write, name: &str, root: &Compound) -> Result<(), NbtError> {
+    buf.write_all(&[TAG_COMPOUND])?;
+    write_string(buf, name)?;
+    write_compound(buf, root)
+}
+
+/// Read an NBT file, working out from its first bytes whether it is gzipped,
+/// zlib compressed or neither.
+pub fn read_file(data: &[u8]) -> Result<(String, Compound), NbtError> {
+    match data {
+        [0x1F, 0x8B, ..] => read_named(&mut GzDecoder::new(data)),
+        [0x78, ..] => read_named(&mut ZlibDecoder::new(data)),
+        _ => read_named(&mut &data[..]),
+    }
+}
+
+pub fn write_file(
+    name: &str,
+    root: &Compound,
+    compression: FileCompression,
+) -> Result<Vec<u8>, NbtError> {
+    match compression {
+        FileCompression::None => {
+            let mut res = vec![];
+            write_named(&mut res, name, root)?;
+            Ok(res)
+        }
+        FileCompression::Gzip => {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            write_named(&mut encoder, name, root)?;
+            Ok(encoder.finish()?)
+        }
+        FileCompression::Zlib => {
+            let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+            write_named(&mut encoder, name, root)?;
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+fn read_u8(buf: &mut impl Read) -> Result<u8, NbtError> {
+    let mut byte = [0];
+    buf.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_array<const N: usize>(buf: &mut impl Read) -> Result<[u8; N], NbtError> {
+    let mut bytes = [0; N];
+    buf.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_length(buf: &mut impl Read) -> Result<usize, NbtError> {
+    let len = i32::from_be_bytes(read_array(buf)?);
+    usize::try_from(len).map_err(|_| NbtError::NegativeLength(len))
+}
+
+/// Read `len` elements without trusting `len` with the allocation up front.
+fn read_elements<T>(
+    len: usize,
+    mut read: impl FnMut() -> Result<T, NbtError>,
+) -> Result<Vec<T>, NbtError> {
+    let mut res = Vec::with_capacity(len.min(4096));
+    for _ in 0..len {
+        res.push(read()?);
+    }
+    Ok(res)
+}
+
+fn read_string(buf: &mut impl Read) -> Result<String, NbtError> {
+    let len = u16::from_be_bytes(read_array(buf)?);
+    let mut bytes = vec![0; len.into()];
+    buf.read_exact(&mut bytes)?;
+    decode_modified_utf8(&bytes)
+}
+
+fn read_payload(buf: &mut impl Read, id: u8, depth: usize) -> Result<Tag, NbtError> {
+    if depth > MAX_DEPTH {
+        return Err(NbtError::TooDeep);
+    }
+    Ok(match id {
+        TAG_BYTE => Tag::Byte(read_u8(buf)? as i8),
+        TAG_SHORT => Tag::Short(i16::from_be_bytes(read_array(buf)?)),
+        TAG_INT => Tag::Int(i32::from_be_bytes(read_array(buf)?)),
+        TAG_LONG => Tag::Long(i64::from_be_bytes(read_array(buf)?)),
+        TAG_FLOAT => Tag::Float(f32::from_be_bytes(read_array(buf)?)),
+        TAG_DOUBLE => Tag::Double(f64::from_be_bytes(read_array(buf)?)),
+        TAG_BYTE_ARRAY => {
+            let len = read_length(buf)?;
+            Tag::ByteArray(read_elements(len, || Ok(read_u8(buf)? as i8))?)
+        }
+        TAG_STRING => Tag::String(read_string(buf)?),
+        TAG_LIST => {
+            let element_id = read_u8(buf)?;
+            let len = read_length(buf)?;
+            if element_id == TAG_END && len > 0 {
+                return Err(NbtError::InvalidTagId(TAG_END));
+            }
+            Tag::List(read_elements(len, || {
+                read_payload(buf, element_id, depth + 1)
+            })?)
+        }
+        TAG_COMPOUND => {
+            let mut res = Compound::new();
+            loop {
+                let id = read_u8(buf)?;
+                if id == TAG_END {
+                    break;
+                }
+                let name = read_string(buf)?;
+                let tag = read_payload(buf, id, depth + 1)?;
+                res.entries.push((name, tag));
+            }
+            Tag::Compound(res)
+        }
+        TAG_INT_ARRAY => {
+            let len = read_length(buf)?;
+            Tag::IntArray(read_elements(len, || {
+                Ok(i32::from_be_bytes(read_array(buf)?))
+            })?)
+        }
+        TAG_LONG_ARRAY => {
+            let len = read_length(buf)?;
+            Tag::LongArray(read_elements(len, || {
+                Ok(i64::from_be_bytes(read_array(buf)?))
+            })?)
+        }
+        other => return Err(NbtError::InvalidTagId(other)),
+    })
+}
+
+fn write_length(buf: &mut impl Write, len: usize) -> Result<(), NbtError> {
+    let len = i32::try_from(len).map_err(|_| NbtError::Custom("Too many elements".into()))?;
+    Ok(buf.write_all(&len.to_be_bytes())?)
+}
+
+fn write_string(buf: &mut impl Write, val: &str) -> Result<(), NbtError> {
+    let bytes = encode_modified_utf8(val);
+    let len = u16::try_from(bytes.len()).map_err(|_| NbtError::StringTooLong(bytes.len()))?;
+    buf.write_all(&len.to_be_bytes())?;
+    Ok(buf.write_all(&bytes)?)
+}
+
+fn write_compound(buf: &mut impl Write, compound: &Compound) -> Result<(), NbtError> {
+    for (name, tag) in &compound.entries {
+        buf.write_all(&[tag.id()])?;
+        write_string(buf, name)?;
+        write_payload(buf, tag)?;
+    }
+    Ok(buf.write_all(&[TAG_END])?)
+}
+
+fn write_payload(buf: &mut impl Write, tag: &Tag) -> Result<(), NbtError> {
+    match tag {
+        Tag::Byte(val) => buf.write_all(&val.to_be_bytes())?,
+        Tag::Short(val) => buf.write_all(&val.to_be_bytes())?,
+        Tag::Int(val) => buf.write_all(&val.to_be_bytes())?,
+        Tag::Long(val) => buf.write_all(&val.to_be_bytes())?,
+        Tag::Float(val) => buf.write_all(&val.to_be_bytes())?,
+        Tag::Double(val) => buf.write_all(&val.to_be_bytes())?,
+        Tag::ByteArray(vals) => {
+            write_length(buf, vals.len())?;
+            let bytes: Vec<u8> = vals.iter().map(|val| *val as u8).collect();
+            buf.write_all(&bytes)?;
+        }
+        Tag::String(val) => write_string(buf, val)?,
+        Tag::List(vals) => {
+            let element_id = vals.first().map_or(TAG_END, Tag::id);
+            if vals.iter().any(|val| val.id() != element_id) {
+                return Err(NbtError::MixedList);
+            }
+            buf.write_all(&[element_id])?;
+            write_length(buf, vals.len())?;
+            for val in vals {
+                write_payload(buf, val)?;
+            }
+        }
+        Tag::Compound(compound) => write_compound(buf, compound)?,
+        Tag::IntArray(vals) => {
+            write_length(buf, vals.len())?;
+            for val in vals {
+                buf.write_all(&val.to_be_bytes())?;
+            }
+        }
+        Tag::LongArray(vals) => {
+            write_length(buf, vals.len())?;
+            for val in vals {
+                buf.write_all(&val.to_be_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Java's "modified UTF-8": nulls are written as `C0 80` and characters
+/// outside the BMP as two 3 byte surrogates.
+fn decode_modified_utf8(bytes: &[u8]) -> Result<String, NbtError> {
+    // the common case, where modified UTF-8 and UTF-8 agree
+    if let Ok(res) = std::str::from_utf8(bytes) {
+        return Ok(res.to_string());
+    }
+
+    let mut units: Vec<u16> = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter().copied();
+    let continuation = |byte: Option<u8>| match byte {
+        Some(byte) if byte & 0xC0 == 0x80 => Ok(u16::from(byte & 0x3F)),
+        _ => Err(NbtError::InvalidString),
+    };
+    while let Some(byte) = iter.next() {
+        let unit = match byte {
+            0x00..=0x7F => u16::from(byte),
+            0xC0..=0xDF => (u16::from(byte & 0x1F) << 6) | continuation(iter.next())?,
+            0xE0..=0xEF => {
+                (u16::from(byte & 0x0F) << 12)
+                    | (continuation(iter.next())? << 6)
+                    | continuation(iter.next())?
+            }
+            _ => return Err(NbtError::InvalidString),
+        };
+        units.push(unit);
+    }
+    String::from_utf16(&units).map_err(|_| NbtError::InvalidString)
+}
+
+fn encode_modified_utf8(val: &str) -> Vec<u8> {
+    if !val.chars().any(|c| c == '\0' || c.len_utf8() == 4) {
+        return val.as_bytes().to_vec();
+    }
+
+    let mut res = Vec::with_capacity(val.len() + 4);
+    for unit in val.encode_utf16() {
+        match unit {
+            0x0001..=0x007F => res.push(unit as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                res.push(0xC0 | (unit >> 6) as u8);
+                res.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                res.push(0xE0 | (unit >> 12) as u8);
+                res.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                res.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+    res
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        snbt::write_snbt(f, self)
+    }
+}
+
+impl fmt::Display for Compound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        snbt::write_compound_snbt(f, self)
+    }
+}
+
+impl std::str::FromStr for Tag {
+    type Err = NbtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        snbt::parse_snbt(s)
+    }
+}
@@ -0,0 +1,446 @@
+use serde::{
+    ser::{self, SerializeMap, SerializeSeq},
+    Serialize,
+};
+
+use super::{ByteArray, Compound, IntArray, LongArray, NbtError, Tag};
+
+pub(super) const BYTE_ARRAY_NAME: &str = "__nbt_byte_array";
+pub(super) const INT_ARRAY_NAME: &str = "__nbt_int_array";
+pub(super) const LONG_ARRAY_NAME: &str = "__nbt_long_array";
+
+/// Convert anything serializable into a tag.
+///
+/// `bool` becomes a byte, unsigned integers the next larger signed tag (`u8`
+/// is a short, and so on), sequences lists, and maps and structs compounds.
+/// `None` fields are left out. Use [`ByteArray`], [`IntArray`] and
+/// [`LongArray`] for the array tags.
+pub fn to_tag<T: Serialize + ?Sized>(value: &T) -> Result<Tag, NbtError> {
+    value
+        .serialize(Serializer)?
+        .ok_or_else(|| NbtError::Custom("Nothing to serialize".to_string()))
+}
+
+impl Serialize for Tag {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Tag::Byte(val) => serializer.serialize_i8(*val),
+            Tag::Short(val) => serializer.serialize_i16(*val),
+            Tag::Int(val) => serializer.serialize_i32(*val),
+            Tag::Long(val) => serializer.serialize_i64(*val),
+            Tag::Float(val) => serializer.serialize_f32(*val),
+            Tag::Double(val) => serializer.serialize_f64(*val),
+            Tag::ByteArray(vals) => serializer.serialize_newtype_struct(BYTE_ARRAY_NAME, vals),
+            Tag::String(val) => serializer.serialize_str(val),
+            Tag::List(vals) => serializer.collect_seq(vals),
+            Tag::Compound(compound) => compound.serialize(serializer),
+            Tag::IntArray(vals) => serializer.serialize_newtype_struct(INT_ARRAY_NAME, vals),
+            Tag::LongArray(vals) => serializer.serialize_newtype_struct(LONG_ARRAY_NAME, vals),
+        }
+    }
+}
+
+impl Serialize for Compound {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl Serialize for ByteArray {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(BYTE_ARRAY_NAME, &self.0)
+    }
+}
+
+impl Serialize for IntArray {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(INT_ARRAY_NAME, &self.0)
+    }
+}
+
+impl Serialize for LongArray {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(LONG_ARRAY_NAME, &self.0)
+    }
+}
+
+impl ser::Error for NbtError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        NbtError::Custom(msg.to_string())
+    }
+}
+
+/// Serializes into `None` for values that have no NBT form (`None` and `()`),
+/// which get skipped inside compounds.
+struct Serializer;
+
+fn unsupported(what: &str) -> NbtError {
+    NbtError::Custom(format!("{what} can't be represented in NBT"))
+}
+
+/// Turn a list of numbers produced for one of the array newtypes into the
+/// array tag.
+fn into_array(name: &str, tag: Option<Tag>) -> Result<Tag, NbtError> {
+    let vals = match tag {
+        Some(Tag::List(vals)) => vals,
+        _ => return Err(NbtError::Custom(format!("{name} must be a sequence"))),
+    };
+    let wrong_type = || NbtError::Custom("Wrong element type in NBT array".to_string());
+    Ok(match name {
+        BYTE_ARRAY_NAME => Tag::ByteArray(
+            vals.into_iter()
+                .map(|val| match val {
+                    Tag::Byte(val) => Ok(val),
+                    _ => Err(wrong_type()),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        INT_ARRAY_NAME => Tag::IntArray(
+            vals.into_iter()
+                .map(|val| match val {
+                    Tag::Int(val) => Ok(val),
+                    _ => Err(wrong_type()),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        _ => Tag::LongArray(
+            vals.into_iter()
+                .map(|val| match val {
+                    Tag::Long(val) => Ok(val),
+                    _ => Err(wrong_type()),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+    })
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Option<Tag>;
+    type Error = NbtError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = VariantSerializer<MapSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Byte(v.into())))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Byte(v)))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Short(v)))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Int(v)))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Long(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Short(v.into())))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Int(v.into())))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Long(v.into())))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        i64::try_from(v)
+            .map(|v| Some(Tag::Long(v)))
+            .map_err(|_| unsupported("A u64 over i64::MAX"))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Float(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Double(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::String(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::String(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::ByteArray(
+            v.iter().map(|val| *val as i8).collect(),
+        )))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Compound(Compound::new())))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::String(variant.to_string())))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        let tag = value.serialize(self)?;
+        match name {
+            BYTE_ARRAY_NAME | INT_ARRAY_NAME | LONG_ARRAY_NAME => into_array(name, tag).map(Some),
+            _ => Ok(tag),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(wrap_variant(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SeqSerializer {
+            elements: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(MapSerializer {
+            compound: Compound::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+struct SeqSerializer {
+    elements: Vec<Tag>,
+}
+
+impl SerializeSeq for SeqSerializer {
+    type Ok = Option<Tag>;
+    type Error = NbtError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let tag = value
+            .serialize(Serializer)?
+            .ok_or_else(|| unsupported("A missing list element"))?;
+        if self
+            .elements
+            .first()
+            .is_some_and(|first| first.id() != tag.id())
+        {
+            return Err(NbtError::MixedList);
+        }
+        self.elements.push(tag);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::List(self.elements)))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Option<Tag>;
+    type Error = NbtError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Option<Tag>;
+    type Error = NbtError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+struct MapSerializer {
+    compound: Compound,
+    key: Option<String>,
+}
+
+impl SerializeMap for MapSerializer {
+    type Ok = Option<Tag>;
+    type Error = NbtError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        self.key = Some(match key.serialize(Serializer)? {
+            Some(Tag::String(key)) => key,
+            Some(tag) if tag.as_i64().is_some() => tag.as_i64().unwrap_or_default().to_string(),
+            _ => return Err(unsupported("A compound key that isn't a string")),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| NbtError::Custom("Map value without a key".to_string()))?;
+        if let Some(tag) = value.serialize(Serializer)? {
+            self.compound.insert(key, tag);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Tag::Compound(self.compound)))
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Option<Tag>;
+    type Error = NbtError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        if let Some(tag) = value.serialize(Serializer)? {
+            self.compound.insert(key, tag);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeMap::end(self)
+    }
+}
+
+/// Enum variants with data become a compound holding the data under the
+/// variant's name.
+struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+fn wrap_variant(variant: &str, tag: Option<Tag>) -> Option<Tag> {
+    let mut res = Compound::new();
+    if let Some(tag) = tag {
+        res.insert(variant, tag);
+    }
+    Some(Tag::Compound(res))
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Option<Tag>;
+    type Error = NbtError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(wrap_variant(self.variant, SerializeSeq::end(self.inner)?))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<MapSerializer> {
+    type Ok = Option<Tag>;
+    type Error = NbtError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(wrap_variant(self.variant, SerializeMap::end(self.inner)?))
+    }
+}
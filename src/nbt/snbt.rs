@@ -0,0 +1,319 @@
+//! The stringified NBT used by commands, e.g. `{name:"Steve",Health:20.0f}`.
+
+use std::fmt::{self, Write};
+
+use super::{Compound, NbtError, Tag, MAX_DEPTH};
+
+/// Parse SNBT.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::nbt::Tag;
+///
+/// let tag: Tag = r#"{Count:1b,id:"minecraft:stone",tag:{Damage:0,Tags:[L;1L,2L]}}"#
+///     .parse()
+///     .unwrap();
+/// let compound = tag.as_compound().unwrap();
+/// assert_eq!(compound.get("Count"), Some(&Tag::Byte(1)));
+/// assert_eq!(compound.get("id").and_then(Tag::as_str), Some("minecraft:stone"));
+///
+/// // printing it gives the same text back
+/// assert_eq!(
+///     tag.to_string(),
+///     r#"{Count:1b,id:"minecraft:stone",tag:{Damage:0,Tags:[L;1L,2L]}}"#
+/// );
+/// ```
+pub fn parse_snbt(source: &str) -> Result<Tag, NbtError> {
+    let mut parser = Parser { source, pos: 0 };
+    let res = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.pos != source.len() {
+        return Err(parser.error("Trailing data"));
+    }
+    Ok(res)
+}
+
+pub fn write_snbt(f: &mut fmt::Formatter<'_>, tag: &Tag) -> fmt::Result {
+    match tag {
+        Tag::Byte(val) => write!(f, "{val}b"),
+        Tag::Short(val) => write!(f, "{val}s"),
+        Tag::Int(val) => write!(f, "{val}"),
+        Tag::Long(val) => write!(f, "{val}L"),
+        Tag::Float(val) => write!(f, "{val:?}f"),
+        Tag::Double(val) => write!(f, "{val:?}d"),
+        Tag::ByteArray(vals) => write_array(f, "B", vals.iter().map(|val| format!("{val}b"))),
+        Tag::String(val) => write_quoted(f, val),
+        Tag::List(vals) => {
+            f.write_char('[')?;
+            for (idx, val) in vals.iter().enumerate() {
+                if idx != 0 {
+                    f.write_char(',')?;
+                }
+                write_snbt(f, val)?;
+            }
+            f.write_char(']')
+        }
+        Tag::Compound(compound) => write_compound_snbt(f, compound),
+        Tag::IntArray(vals) => write_array(f, "I", vals.iter().map(|val| val.to_string())),
+        Tag::LongArray(vals) => write_array(f, "L", vals.iter().map(|val| format!("{val}L"))),
+    }
+}
+
+pub fn write_compound_snbt(f: &mut fmt::Formatter<'_>, compound: &Compound) -> fmt::Result {
+    f.write_char('{')?;
+    for (idx, (key, val)) in compound.iter().enumerate() {
+        if idx != 0 {
+            f.write_char(',')?;
+        }
+        if !key.is_empty() && key.chars().all(is_unquoted_char) {
+            f.write_str(key)?;
+        } else {
+            write_quoted(f, key)?;
+        }
+        f.write_char(':')?;
+        write_snbt(f, val)?;
+    }
+    f.write_char('}')
+}
+
+fn write_array(
+    f: &mut fmt::Formatter<'_>,
+    prefix: &str,
+    vals: impl Iterator<Item = String>,
+) -> fmt::Result {
+    write!(f, "[{prefix};")?;
+    for (idx, val) in vals.enumerate() {
+        if idx != 0 {
+            f.write_char(',')?;
+        }
+        f.write_str(&val)?;
+    }
+    f.write_char(']')
+}
+
+fn write_quoted(f: &mut fmt::Formatter<'_>, val: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in val.chars() {
+        if c == '"' || c == '\\' {
+            f.write_char('\\')?;
+        }
+        f.write_char(c)?;
+    }
+    f.write_char('"')
+}
+
+fn is_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> NbtError {
+        NbtError::Snbt {
+            message: message.to_string(),
+            position: self.pos,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), NbtError> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("Expected '{expected}'")));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Consume `c` if it is next, returning whether it was.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Tag, NbtError> {
+        if depth > MAX_DEPTH {
+            return Err(NbtError::TooDeep);
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.compound(depth),
+            Some('[') => self.list_or_array(depth),
+            Some('"' | '\'') => Ok(Tag::String(self.quoted()?)),
+            Some(_) => {
+                let start = self.pos;
+                let word = self.unquoted();
+                if word.is_empty() {
+                    self.pos = start;
+                    return Err(self.error("Expected a value"));
+                }
+                Ok(parse_unquoted(word))
+            }
+            None => Err(self.error("Expected a value")),
+        }
+    }
+
+    fn compound(&mut self, depth: usize) -> Result<Tag, NbtError> {
+        self.expect('{')?;
+        let mut res = Compound::new();
+        if self.eat('}') {
+            return Ok(Tag::Compound(res));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = match self.peek() {
+                Some('"' | '\'') => self.quoted()?,
+                _ => {
+                    let key = self.unquoted();
+                    if key.is_empty() {
+                        return Err(self.error("Expected a key"));
+                    }
+                    key.to_string()
+                }
+            };
+            self.expect(':')?;
+            let val = self.value(depth + 1)?;
+            res.insert(key, val);
+            if !self.eat(',') {
+                break;
+            }
+        }
+        self.expect('}')?;
+        Ok(Tag::Compound(res))
+    }
+
+    fn list_or_array(&mut self, depth: usize) -> Result<Tag, NbtError> {
+        self.expect('[')?;
+        let rest = &self.source[self.pos..];
+        let array_type = match rest.get(..2) {
+            Some("B;") => Some('B'),
+            Some("I;") => Some('I'),
+            Some("L;") => Some('L'),
+            _ => None,
+        };
+
+        if array_type.is_some() {
+            self.pos += 2;
+        }
+
+        let mut vals = vec![];
+        if !self.eat(']') {
+            loop {
+                vals.push(self.value(depth + 1)?);
+                if !self.eat(',') {
+                    break;
+                }
+            }
+            self.expect(']')?;
+        }
+
+        match array_type {
+            Some(array_type) => {
+                let mut longs = Vec::with_capacity(vals.len());
+                for val in vals {
+                    let val = match (array_type, val) {
+                        ('B', Tag::Byte(val)) => val.into(),
+                        ('I', Tag::Int(val)) => val.into(),
+                        ('L', Tag::Long(val)) => val,
+                        _ => return Err(self.error("Wrong element type in array")),
+                    };
+                    longs.push(val);
+                }
+                Ok(match array_type {
+                    'B' => Tag::ByteArray(longs.into_iter().map(|val| val as i8).collect()),
+                    'I' => Tag::IntArray(longs.into_iter().map(|val| val as i32).collect()),
+                    _ => Tag::LongArray(longs),
+                })
+            }
+            None => {
+                if vals.windows(2).any(|pair| pair[0].id() != pair[1].id()) {
+                    return Err(NbtError::MixedList);
+                }
+                Ok(Tag::List(vals))
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, NbtError> {
+        let quote = self.peek().ok_or_else(|| self.error("Expected a string"))?;
+        self.pos += 1;
+        let mut res = String::new();
+        let mut escaped = false;
+        for c in self.source[self.pos..].chars() {
+            self.pos += c.len_utf8();
+            if escaped {
+                res.push(c);
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == quote {
+                return Ok(res);
+            } else {
+                res.push(c);
+            }
+        }
+        Err(self.error("Unterminated string"))
+    }
+
+    fn unquoted(&mut self) -> &str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is_unquoted_char(c) {
+                break;
+            }
+            self.pos += 1;
+        }
+        &self.source[start..self.pos]
+    }
+}
+
+/// An unquoted word is a number if it looks like one and a string otherwise.
+fn parse_unquoted(word: &str) -> Tag {
+    let lower = word.to_ascii_lowercase();
+    match lower.as_str() {
+        "true" => return Tag::Byte(1),
+        "false" => return Tag::Byte(0),
+        _ => {}
+    }
+
+    let (number, suffix) = match lower.char_indices().last() {
+        Some((idx, c @ ('b' | 's' | 'l' | 'f' | 'd'))) => (&lower[..idx], Some(c)),
+        _ => (lower.as_str(), None),
+    };
+    let parsed = match suffix {
+        Some('b') => number.parse().ok().map(Tag::Byte),
+        Some('s') => number.parse().ok().map(Tag::Short),
+        Some('l') => number.parse().ok().map(Tag::Long),
+        Some('f') => number.parse().ok().map(Tag::Float),
+        Some('d') => number.parse().ok().map(Tag::Double),
+        _ if number.contains(['.', 'e']) => number.parse().ok().map(Tag::Double),
+        _ => number.parse().ok().map(Tag::Int),
+    };
+    // a float suffix on a word without digits (like `inf`) shouldn't count
+    match parsed {
+        Some(tag) if number.starts_with(|c: char| c.is_ascii_digit() || "-+.".contains(c)) => tag,
+        _ => Tag::String(word.to_string()),
+    }
+}
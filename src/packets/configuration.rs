@@ -0,0 +1,119 @@
+//! The configuration state added in 1.20.2, between login and play. See
+//! <https://wiki.vg/Protocol#Configuration>.
+
+use std::io::{Read, Write};
+
+use super::disconnect_reason;
+use crate::{
+    chat::ChatComponent,
+    data_types::{DataTypeError, McRead, McWrite},
+    nbt::NetworkTag,
+    versions::{PROTOCOL_1_20_2, PROTOCOL_1_20_3, PROTOCOL_1_20_5},
+};
+
+packet! {
+    /// Clientbound. Use [`Disconnect::reason`] rather than the fields.
+    pub struct Disconnect {
+        ids { PROTOCOL_1_20_5.. => 0x02, PROTOCOL_1_20_2.. => 0x01 }
+        /// A JSON text component, before 1.20.3.
+        pub reason_json: String => ..PROTOCOL_1_20_3,
+        /// An NBT text component, since 1.20.3.
+        pub reason_nbt: NetworkTag => PROTOCOL_1_20_3..,
+    }
+}
+
+impl Disconnect {
+    pub fn reason(&self) -> ChatComponent {
+        disconnect_reason(&self.reason_json, &self.reason_nbt)
+    }
+}
+
+packet! {
+    /// Clientbound. The client answers with [`AcknowledgeFinishConfiguration`]
+    /// and moves on to the play state.
+    pub struct FinishConfiguration {
+        ids { PROTOCOL_1_20_5.. => 0x03, PROTOCOL_1_20_2.. => 0x02 }
+    }
+}
+
+packet! {
+    /// Clientbound. Answered with a [`ServerboundKeepAlive`] carrying the
+    /// same id.
+    pub struct ClientboundKeepAlive {
+        ids { PROTOCOL_1_20_5.. => 0x04, PROTOCOL_1_20_2.. => 0x03 }
+        pub id: i64,
+    }
+}
+
+packet! {
+    /// Clientbound. Answered with a [`Pong`] carrying the same id.
+    pub struct Ping {
+        ids { PROTOCOL_1_20_5.. => 0x05, PROTOCOL_1_20_2.. => 0x04 }
+        pub id: i32,
+    }
+}
+
+packet! {
+    /// Clientbound. The data packs the server would rather not send in full.
+    pub struct ClientboundKnownPacks {
+        ids { PROTOCOL_1_20_5.. => 0x0E }
+        pub packs: Vec<KnownPack>,
+    }
+}
+
+packet! {
+    /// Serverbound.
+    pub struct AcknowledgeFinishConfiguration {
+        ids { PROTOCOL_1_20_5.. => 0x03, PROTOCOL_1_20_2.. => 0x02 }
+    }
+}
+
+packet! {
+    /// Serverbound.
+    pub struct ServerboundKeepAlive {
+        ids { PROTOCOL_1_20_5.. => 0x04, PROTOCOL_1_20_2.. => 0x03 }
+        pub id: i64,
+    }
+}
+
+packet! {
+    /// Serverbound.
+    pub struct Pong {
+        ids { PROTOCOL_1_20_5.. => 0x05, PROTOCOL_1_20_2.. => 0x04 }
+        pub id: i32,
+    }
+}
+
+packet! {
+    /// Serverbound. The data packs from [`ClientboundKnownPacks`] the client
+    /// has too, the server sends the registries of the rest.
+    pub struct ServerboundKnownPacks {
+        ids { PROTOCOL_1_20_5.. => 0x07 }
+        pub packs: Vec<KnownPack>,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KnownPack {
+    pub namespace: String,
+    pub id: String,
+    pub version: String,
+}
+
+impl McRead for KnownPack {
+    fn read_from(buf: &mut impl Read) -> Result<Self, DataTypeError> {
+        Ok(KnownPack {
+            namespace: String::read_from(buf)?,
+            id: String::read_from(buf)?,
+            version: String::read_from(buf)?,
+        })
+    }
+}
+
+impl McWrite for KnownPack {
+    fn write_to(&self, buf: &mut impl Write) -> Result<(), DataTypeError> {
+        self.namespace.write_to(buf)?;
+        self.id.write_to(buf)?;
+        self.version.write_to(buf)
+    }
+}
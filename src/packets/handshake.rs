@@ -0,0 +1,14 @@
+//! The first packet of every connection.
+
+use crate::data_types::VarInt;
+
+packet! {
+    /// Serverbound. `next_state` is 1 for status and 2 for login.
+    pub struct Handshake {
+        ids { 0.. => 0x00 }
+        pub protocol_version: VarInt,
+        pub server_address: String,
+        pub server_port: u16,
+        pub next_state: VarInt,
+    }
+}
@@ -0,0 +1,103 @@
+//! The login state. See <https://wiki.vg/Protocol#Login>.
+
+use uuid::Uuid;
+
+use crate::{
+    client::GameProfileProperty,
+    data_types::{RemainingBytes, VarInt},
+    versions::{
+        PROTOCOL_1_16, PROTOCOL_1_19, PROTOCOL_1_19_1, PROTOCOL_1_20, PROTOCOL_1_20_2,
+        PROTOCOL_1_20_5, PROTOCOL_1_21,
+    },
+};
+
+packet! {
+    /// Clientbound. `reason` is a JSON chat component.
+    pub struct LoginDisconnect {
+        ids { 0.. => 0x00 }
+        pub reason: String,
+    }
+}
+
+packet! {
+    /// Clientbound. Asks the client to turn on encryption.
+    pub struct EncryptionRequest {
+        ids { 0.. => 0x01 }
+        pub server_id: String,
+        pub public_key: Vec<u8>,
+        pub verify_token: Vec<u8>,
+        /// Before 1.20.5 encryption always meant authentication.
+        pub should_authenticate: bool => PROTOCOL_1_20_5..,
+    }
+}
+
+packet! {
+    /// Clientbound. Ends the login state.
+    pub struct LoginSuccess {
+        ids { 0.. => 0x02 }
+        pub uuid: Uuid => PROTOCOL_1_16..,
+        /// The hyphenated uuid servers sent before 1.16.
+        pub uuid_string: String => ..PROTOCOL_1_16,
+        pub username: String,
+        pub properties: Vec<GameProfileProperty> => PROTOCOL_1_19..,
+        pub strict_error_handling: bool => PROTOCOL_1_20_5..=PROTOCOL_1_21,
+    }
+}
+
+packet! {
+    /// Clientbound. A negative threshold turns compression off.
+    pub struct SetCompression {
+        ids { 0.. => 0x03 }
+        pub threshold: VarInt,
+    }
+}
+
+packet! {
+    /// Clientbound.
+    pub struct LoginPluginRequest {
+        ids { 0.. => 0x04 }
+        pub message_id: VarInt,
+        pub channel: String,
+        pub data: RemainingBytes,
+    }
+}
+
+packet! {
+    /// Serverbound.
+    pub struct LoginStart {
+        ids { 0.. => 0x00 }
+        pub name: String,
+        /// Signature data is never sent, so this is always false.
+        pub has_sig_data: bool => PROTOCOL_1_19..=PROTOCOL_1_19_1,
+        pub player_uuid: Option<Uuid> => PROTOCOL_1_19..=PROTOCOL_1_20,
+        pub uuid: Uuid => PROTOCOL_1_20_2..,
+    }
+}
+
+packet! {
+    /// Serverbound.
+    pub struct EncryptionResponse {
+        ids { 0.. => 0x01 }
+        pub shared_secret: Vec<u8>,
+        /// Must be true, the salt and signature alternative isn't supported.
+        pub has_verify_token: bool => PROTOCOL_1_19..=PROTOCOL_1_19_1,
+        pub verify_token: Vec<u8>,
+    }
+}
+
+packet! {
+    /// Serverbound.
+    pub struct LoginPluginResponse {
+        ids { 0.. => 0x02 }
+        pub message_id: VarInt,
+        pub successful: bool,
+        pub data: RemainingBytes,
+    }
+}
+
+packet! {
+    /// Serverbound, moving the connection into the configuration state.
+    pub struct LoginAcknowledged {
+        ids { PROTOCOL_1_20_2.. => 0x03 }
+    }
+}
@@ -0,0 +1,185 @@
+//! Packet definitions. Each packet is declared once with [`packet!`], listing
+//! its id in every protocol range it exists in and the range each field is
+//! sent in, and gets a [`Packet`] implementation that encodes and decodes it
+//! for any protocol version. Supporting a new Minecraft version means editing
+//! those ranges, not hand-written branches.
+//!
+//! # Examples
+//!
+//! ```
+//! use minecraft_utilities::packets::{login::LoginStart, Packet};
+//! use uuid::Uuid;
+//!
+//! let login_start = LoginStart {
+//!     name: "Shrecknt".to_string(),
+//!     has_sig_data: false,
+//!     player_uuid: None,
+//!     uuid: Uuid::nil(),
+//! };
+//!
+//! // 1.19 had a signature data flag, 1.20.2 sends the uuid unconditionally
+//! let packet = login_start.to_packet(759).unwrap();
+//! assert_eq!(packet.buffer, b"\x08Shrecknt\x00\x00");
+//! let packet = login_start.to_packet(764).unwrap();
+//! assert_eq!(packet.buffer.len(), 9 + 16);
+//!
+//! assert_eq!(LoginStart::from_packet(&packet, 764).unwrap(), login_start);
+//!
+//! // play packets move around between releases
+//! use minecraft_utilities::packets::play::ClientboundKeepAlive;
+//! assert_eq!(ClientboundKeepAlive::id(763), Some(0x23));
+//! assert_eq!(ClientboundKeepAlive::id(767), Some(0x26));
+//! assert_eq!(ClientboundKeepAlive::id(768), Some(0x27));
+//! assert_eq!(ClientboundKeepAlive::id(700), None);
+//! ```
+
+use std::io::{Read, Write};
+
+use crate::{
+    chat::ChatComponent, data_types::DataTypeError, nbt::NetworkTag, packetutil::MinecraftPacket,
+};
+
+pub trait Packet: Sized {
+    /// The packet's id in `protocol_version`, or `None` if it doesn't exist
+    /// there.
+    fn id(protocol_version: i32) -> Option<i32>;
+
+    /// Write the packet's fields (not its id).
+    fn encode(&self, buf: &mut impl Write, protocol_version: i32) -> Result<(), DataTypeError>;
+
+    /// Read the packet's fields (not its id).
+    fn decode(buf: &mut impl Read, protocol_version: i32) -> Result<Self, DataTypeError>;
+
+    /// Whether `packet` is this packet in `protocol_version`.
+    fn matches(packet: &MinecraftPacket, protocol_version: i32) -> bool {
+        Self::id(protocol_version) == Some(packet.packet_id)
+    }
+
+    fn to_packet(&self, protocol_version: i32) -> Result<MinecraftPacket, DataTypeError> {
+        let packet_id =
+            Self::id(protocol_version).ok_or(DataTypeError::UnsupportedPacket(protocol_version))?;
+        let mut buffer = vec![];
+        self.encode(&mut buffer, protocol_version)?;
+        Ok(MinecraftPacket { buffer, packet_id })
+    }
+
+    fn from_packet(packet: &MinecraftPacket, protocol_version: i32) -> Result<Self, DataTypeError> {
+        let expected =
+            Self::id(protocol_version).ok_or(DataTypeError::UnsupportedPacket(protocol_version))?;
+        if packet.packet_id != expected {
+            return Err(DataTypeError::WrongPacketId {
+                expected,
+                found: packet.packet_id,
+            });
+        }
+        Self::decode(&mut packet.buffer.as_slice(), protocol_version)
+    }
+}
+
+/// Declare a packet struct and its [`Packet`] implementation.
+///
+/// ```ignore
+/// packet! {
+///     /// Doc comment for the struct.
+///     pub struct Example {
+///         // protocol range => packet id, the first match wins
+///         ids { PROTOCOL_1_20_2.. => 0x03, 0.. => 0x02 }
+///         pub always_sent: VarInt,
+///         // only on the wire in this range, `Default` otherwise
+///         pub added_later: bool => PROTOCOL_1_20_5..,
+///     }
+/// }
+/// ```
+///
+/// Fields are read and written in order with [`McRead`](crate::McRead) and
+/// [`McWrite`](crate::McWrite).
+macro_rules! packet {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            ids { $($id_range:expr => $id:expr),* $(,)? }
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $ty:ty $(=> $field_range:expr)?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq)]
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $crate::packets::Packet for $name {
+            fn id(protocol_version: i32) -> Option<i32> {
+                $(
+                    if ($id_range).contains(&protocol_version) {
+                        return Some($id);
+                    }
+                )*
+                None
+            }
+
+            #[allow(unused_variables)]
+            fn encode(
+                &self,
+                buf: &mut impl std::io::Write,
+                protocol_version: i32,
+            ) -> Result<(), $crate::data_types::DataTypeError> {
+                $(
+                    if packet!(@sent protocol_version $(, $field_range)?) {
+                        $crate::data_types::McWrite::write_to(&self.$field, buf)?;
+                    }
+                )*
+                Ok(())
+            }
+
+            #[allow(unused_variables)]
+            fn decode(
+                buf: &mut impl std::io::Read,
+                protocol_version: i32,
+            ) -> Result<Self, $crate::data_types::DataTypeError> {
+                Ok($name {
+                    $(
+                        $field: packet!(@read buf, protocol_version $(, $field_range)?),
+                    )*
+                })
+            }
+        }
+    };
+    (@sent $protocol_version:ident) => {
+        true
+    };
+    (@sent $protocol_version:ident, $range:expr) => {
+        ($range).contains(&$protocol_version)
+    };
+    (@read $buf:ident, $protocol_version:ident) => {
+        $crate::data_types::McRead::read_from($buf)?
+    };
+    (@read $buf:ident, $protocol_version:ident, $range:expr) => {
+        if ($range).contains(&$protocol_version) {
+            $crate::data_types::McRead::read_from($buf)?
+        } else {
+            Default::default()
+        }
+    };
+}
+
+/// The reason of a Disconnect packet, which is JSON before 1.20.3 and NBT
+/// after.
+fn disconnect_reason(json: &str, nbt: &NetworkTag) -> ChatComponent {
+    match &nbt.0 {
+        Some(tag) => ChatComponent::from_nbt(tag),
+        None if !json.is_empty() => ChatComponent::parse(json),
+        None => ChatComponent::text("Disconnected"),
+    }
+}
+
+pub mod configuration;
+pub mod handshake;
+pub mod login;
+pub mod play;
+pub mod status;
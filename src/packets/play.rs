@@ -0,0 +1,118 @@
+//! The few play state packets a session handles itself. See
+//! <https://wiki.vg/Protocol#Play>.
+//!
+//! Play packet ids shift with nearly every release, so these only cover the
+//! versions they were checked against.
+
+use super::disconnect_reason;
+use crate::{
+    chat::ChatComponent,
+    nbt::NetworkTag,
+    versions::{
+        PROTOCOL_1_19_4, PROTOCOL_1_20, PROTOCOL_1_20_2, PROTOCOL_1_20_3, PROTOCOL_1_20_5,
+        PROTOCOL_1_21, PROTOCOL_1_21_2, PROTOCOL_1_21_4,
+    },
+};
+
+packet! {
+    /// Clientbound. Use [`Disconnect::reason`] rather than the fields.
+    pub struct Disconnect {
+        ids {
+            PROTOCOL_1_20_5..=PROTOCOL_1_21_4 => 0x1D,
+            PROTOCOL_1_20_2..=PROTOCOL_1_20_3 => 0x1B,
+            PROTOCOL_1_19_4..=PROTOCOL_1_20 => 0x1A,
+        }
+        /// A JSON text component, before 1.20.3.
+        pub reason_json: String => ..PROTOCOL_1_20_3,
+        /// An NBT text component, since 1.20.3.
+        pub reason_nbt: NetworkTag => PROTOCOL_1_20_3..,
+    }
+}
+
+impl Disconnect {
+    pub fn reason(&self) -> ChatComponent {
+        disconnect_reason(&self.reason_json, &self.reason_nbt)
+    }
+}
+
+packet! {
+    /// Clientbound. Answered with a [`ServerboundKeepAlive`] carrying the
+    /// same id, or the server times the client out.
+    pub struct ClientboundKeepAlive {
+        ids {
+            PROTOCOL_1_21_2..=PROTOCOL_1_21_4 => 0x27,
+            PROTOCOL_1_20_5..=PROTOCOL_1_21 => 0x26,
+            PROTOCOL_1_20_2..=PROTOCOL_1_20_3 => 0x24,
+            PROTOCOL_1_19_4..=PROTOCOL_1_20 => 0x23,
+        }
+        pub id: i64,
+    }
+}
+
+packet! {
+    /// Clientbound. Answered with a [`Pong`] carrying the same id.
+    pub struct Ping {
+        ids {
+            PROTOCOL_1_21_2..=PROTOCOL_1_21_4 => 0x37,
+            PROTOCOL_1_20_5..=PROTOCOL_1_21 => 0x35,
+            PROTOCOL_1_20_2..=PROTOCOL_1_20_3 => 0x33,
+            PROTOCOL_1_19_4..=PROTOCOL_1_20 => 0x32,
+        }
+        pub id: i32,
+    }
+}
+
+packet! {
+    /// Serverbound.
+    pub struct ServerboundKeepAlive {
+        ids {
+            PROTOCOL_1_21_2..=PROTOCOL_1_21_4 => 0x1A,
+            PROTOCOL_1_20_5..=PROTOCOL_1_21 => 0x18,
+            PROTOCOL_1_20_3..PROTOCOL_1_20_5 => 0x15,
+            PROTOCOL_1_20_2..PROTOCOL_1_20_3 => 0x14,
+            PROTOCOL_1_19_4..=PROTOCOL_1_20 => 0x12,
+        }
+        pub id: i64,
+    }
+}
+
+packet! {
+    /// Serverbound.
+    pub struct Pong {
+        ids {
+            PROTOCOL_1_21_4..=PROTOCOL_1_21_4 => 0x2B,
+            PROTOCOL_1_21_2..PROTOCOL_1_21_4 => 0x29,
+            PROTOCOL_1_20_5..=PROTOCOL_1_21 => 0x27,
+            PROTOCOL_1_20_3..PROTOCOL_1_20_5 => 0x24,
+            PROTOCOL_1_20_2..PROTOCOL_1_20_3 => 0x23,
+            PROTOCOL_1_19_4..=PROTOCOL_1_20 => 0x20,
+        }
+        pub id: i32,
+    }
+}
+
+packet! {
+    /// Clientbound, since 1.20.2. Sent to go back to the configuration state,
+    /// like proxies do when switching servers. Answered with
+    /// [`AcknowledgeConfiguration`].
+    pub struct StartConfiguration {
+        ids {
+            PROTOCOL_1_21_2..=PROTOCOL_1_21_4 => 0x70,
+            PROTOCOL_1_20_5..=PROTOCOL_1_21 => 0x69,
+            PROTOCOL_1_20_3..PROTOCOL_1_20_5 => 0x67,
+            PROTOCOL_1_20_2..PROTOCOL_1_20_3 => 0x65,
+        }
+    }
+}
+
+packet! {
+    /// Serverbound, since 1.20.2. Packets after it are in the configuration
+    /// state.
+    pub struct AcknowledgeConfiguration {
+        ids {
+            PROTOCOL_1_21_2..=PROTOCOL_1_21_4 => 0x0E,
+            PROTOCOL_1_20_5..=PROTOCOL_1_21 => 0x0C,
+            PROTOCOL_1_20_2..PROTOCOL_1_20_5 => 0x0B,
+        }
+    }
+}
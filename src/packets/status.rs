@@ -0,0 +1,33 @@
+//! The server list ping. See <https://wiki.vg/Server_List_Ping>.
+
+packet! {
+    /// Serverbound.
+    pub struct StatusRequest {
+        ids { 0.. => 0x00 }
+    }
+}
+
+packet! {
+    /// Clientbound. `json` parses into a
+    /// [`StatusResponse`](crate::StatusResponse).
+    pub struct StatusResponse {
+        ids { 0.. => 0x00 }
+        pub json: String,
+    }
+}
+
+packet! {
+    /// Serverbound.
+    pub struct PingRequest {
+        ids { 0.. => 0x01 }
+        pub payload: i64,
+    }
+}
+
+packet! {
+    /// Clientbound, echoing the [`PingRequest`] payload.
+    pub struct PongResponse {
+        ids { 0.. => 0x01 }
+        pub payload: i64,
+    }
+}
@@ -1,13 +1,24 @@
-use serde_json::Value;
-use std::{error::Error, vec};
+use std::{
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    vec,
+};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::TcpStream,
 };
 
 use crate::{
-    packetutil::{read_varint, send_prefixed_packet, write_varint},
+    chat::ChatComponent,
+    data_types::{McRead, McWrite, VarInt, MAX_STRING_LENGTH},
+    error::{Error, IoResultExt, Phase},
+    packets::{
+        handshake::Handshake,
+        status::{PingRequest, PongResponse},
+        Packet,
+    },
+    packetutil::{read_varint, send_prefixed_packet, MinecraftPacket},
     server_address::ServerAddress,
+    status_response::StatusResponse,
 };
 
 #[derive(Debug)]
@@ -19,8 +30,8 @@ pub struct LegacyPingResult {
     pub max_player_count: isize,
 }
 
-impl LegacyPingResult {
-    pub fn default() -> Self {
+impl Default for LegacyPingResult {
+    fn default() -> Self {
         LegacyPingResult {
             protocol_version: 0,
             server_version: String::from(""),
@@ -31,17 +42,203 @@ impl LegacyPingResult {
     }
 }
 
+impl LegacyPingResult {
+    pub fn motd_component(&self) -> ChatComponent {
+        ChatComponent::from_legacy(&self.motd)
+    }
+
+    /// Parse the reason of the kick packet servers answer legacy pings
+    /// with. 1.4 and later send `§1`, the protocol, version, MOTD and
+    /// player counts separated by nul characters. Older servers send
+    /// `motd§players§max`, and the protocol and version are left empty.
+    pub fn parse(reply: &str) -> Result<Self, Error> {
+        let bad_number =
+            |err| Error::invalid_response(Phase::LegacyPing, format!("Bad number: {err}"));
+        let mut ping_res = LegacyPingResult::default();
+
+        if let Some(reply) = reply.strip_prefix("\u{a7}1\0") {
+            let mut res_iter = reply.split('\0');
+            ping_res.protocol_version =
+                str::parse::<u8>(res_iter.next().unwrap_or("0")).map_err(bad_number)?;
+            ping_res.server_version = res_iter.next().unwrap_or("???").to_string();
+            ping_res.motd = res_iter.next().unwrap_or("???").to_string();
+            ping_res.player_count =
+                str::parse::<isize>(res_iter.next().unwrap_or("-1")).map_err(bad_number)?;
+            ping_res.max_player_count =
+                str::parse::<isize>(res_iter.next().unwrap_or("-1")).map_err(bad_number)?;
+        } else {
+            // the MOTD can't contain §, but split from the end to be safe
+            let mut res_iter = reply.rsplitn(3, '\u{a7}');
+            let (Some(max), Some(players), Some(motd)) =
+                (res_iter.next(), res_iter.next(), res_iter.next())
+            else {
+                return Err(Error::invalid_response(
+                    Phase::LegacyPing,
+                    format!("Unrecognized reply '{reply}'"),
+                ));
+            };
+            ping_res.motd = motd.to_string();
+            ping_res.player_count = str::parse::<isize>(players).map_err(bad_number)?;
+            ping_res.max_player_count = str::parse::<isize>(max).map_err(bad_number)?;
+        }
+
+        Ok(ping_res)
+    }
+}
+
+/// The legacy ping requests, named after the first version that sends
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LegacyPingVersion {
+    /// `FE`, understood by Beta 1.8 to 1.3. The reply has no protocol or
+    /// version.
+    Beta1_8,
+    /// `FE 01`, from 1.4 and 1.5.
+    V1_4,
+    /// `FE 01 FA MC|PingHost`, from 1.6.
+    V1_6,
+}
+
+/// What [`Ping::ping_any`] got back, and with which request.
+#[derive(Debug)]
+pub enum AnyPing {
+    Modern(Box<StatusResponse>),
+    Legacy(LegacyPingVersion, LegacyPingResult),
+}
+
+/// A pong is a packet id and a long, anything much longer isn't one.
+const MAX_PONG_LENGTH: i32 = 32;
+
+/// The status is a protocol string, up to 32767 UTF-16 units of at most three
+/// bytes each in UTF-8.
+const MAX_STATUS_LENGTH: usize = MAX_STRING_LENGTH * 3;
+
+/// How long each step of [`Ping::ping_with_latency`] took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PingTimings {
+    /// Opening the TCP connection.
+    pub connect: Duration,
+    /// Sending the handshake. Servers don't answer it, so this is mostly
+    /// local.
+    pub handshake: Duration,
+    /// From the status request to the whole status response.
+    pub status: Duration,
+    /// From the ping request to the pong.
+    pub latency: Duration,
+}
+
+/// A status response and how long the server took to answer.
+#[derive(Debug)]
+pub struct StatusPing {
+    pub status: StatusResponse,
+    /// The ping request round trip, what the multiplayer screen shows.
+    pub latency: Duration,
+    pub timings: PingTimings,
+}
+
 #[derive(Debug)]
 pub struct Ping {}
 
 impl Ping {
+    /// Read the status response and hang up without measuring the latency.
+    #[deprecated(note = "use `ping_with_latency`, which also measures the latency")]
     pub async fn ping(
         host: &str,
         port: Option<u16>,
         input_protocol_version: Option<usize>,
         input_hostname: Option<&str>,
         input_port: Option<u16>,
-    ) -> Result<Value, Box<dyn Error>> {
+    ) -> Result<StatusResponse, Error> {
+        let (_, status, _) = Self::status(
+            host,
+            port,
+            input_protocol_version,
+            input_hostname,
+            input_port,
+        )
+        .await?;
+        Ok(status)
+    }
+
+    /// Read the status response, then send a timestamped ping request and
+    /// wait for the server to echo it back to measure the latency.
+    /// Fails if the echo doesn't match, or if the server closes the
+    /// connection instead of answering, which some proxies do.
+    pub async fn ping_with_latency(
+        host: &str,
+        port: Option<u16>,
+        input_protocol_version: Option<usize>,
+        input_hostname: Option<&str>,
+        input_port: Option<u16>,
+    ) -> Result<StatusPing, Error> {
+        let (mut connection, status, mut timings) = Self::status(
+            host,
+            port,
+            input_protocol_version,
+            input_hostname,
+            input_port,
+        )
+        .await?;
+
+        let payload = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_millis() as i64);
+        let ping = PingRequest { payload }.to_packet(0)?;
+        let mut ping_packet: Vec<u8> = vec![];
+        VarInt(ping.packet_id).write_to(&mut ping_packet)?;
+        ping_packet.extend(ping.buffer);
+
+        let start = Instant::now();
+        send_prefixed_packet(&mut connection, &ping_packet)
+            .await
+            .during(Phase::Status)?;
+
+        let len = read_varint(&mut connection).await.during(Phase::Status)?;
+        if !(1..=MAX_PONG_LENGTH).contains(&len) {
+            return Err(Error::invalid_response(
+                Phase::Status,
+                format!("Bad pong length {len}"),
+            ));
+        }
+        let mut data = vec![0; len as usize];
+        connection
+            .read_exact(&mut data)
+            .await
+            .during(Phase::Status)?;
+        timings.latency = start.elapsed();
+
+        let mut data = data.as_slice();
+        let packet_id = VarInt::read_from(&mut data)?.0;
+        let pong = PongResponse::from_packet(
+            &MinecraftPacket {
+                buffer: data.to_vec(),
+                packet_id,
+            },
+            0,
+        )?;
+        if pong.payload != payload {
+            return Err(Error::invalid_response(
+                Phase::Status,
+                format!("Pong payload {} doesn't match {payload}", pong.payload),
+            ));
+        }
+
+        Ok(StatusPing {
+            status,
+            latency: timings.latency,
+            timings,
+        })
+    }
+
+    /// Connect, handshake and read the status response, leaving the
+    /// connection open for a ping request.
+    async fn status(
+        host: &str,
+        port: Option<u16>,
+        input_protocol_version: Option<usize>,
+        input_hostname: Option<&str>,
+        input_port: Option<u16>,
+    ) -> Result<(TcpStream, StatusResponse, PingTimings), Error> {
         const DEFAULT_PROTOCOL_VERSION: usize = 0xf807;
         const DEFAULT_HOSTNAME: &str = "shrecked.dev";
         const DEFAULT_PORT: u16 = 25565;
@@ -49,57 +246,90 @@ impl Ping {
         let send_protocol_version = input_protocol_version.unwrap_or(DEFAULT_PROTOCOL_VERSION);
         let send_hostname = input_hostname.unwrap_or(DEFAULT_HOSTNAME);
         let send_port = input_port.unwrap_or(DEFAULT_PORT);
+        let mut timings = PingTimings::default();
 
-        let mut connection =
-            TcpStream::connect(format!("{}:{}", host, port.unwrap_or(25565))).await?;
+        let start = Instant::now();
+        let mut connection = ServerAddress::new(host, port.unwrap_or(25565))
+            .connect()
+            .await?;
+        timings.connect = start.elapsed();
 
+        let send_protocol_version = i32::try_from(send_protocol_version).map_err(|_| {
+            Error::InvalidArgument(format!(
+                "Protocol version {send_protocol_version} too large"
+            ))
+        })?;
+        let handshake = Handshake {
+            protocol_version: VarInt(send_protocol_version),
+            server_address: send_hostname.to_string(),
+            server_port: send_port,
+            next_state: VarInt(0x01), // ping
+        }
+        .to_packet(send_protocol_version)?;
         let mut connect_packet: Vec<u8> = vec![];
-        connect_packet.write_u8(0x00).await?;
-        write_varint(&mut connect_packet, i32::try_from(send_protocol_version)?).await?; // protocol version - 762 (1.19.4)
-        write_varint(&mut connect_packet, i32::try_from(send_hostname.len())?).await?; // host length - 12
-        connect_packet.write_all(send_hostname.as_bytes()).await?; // host name - shrecked.dev
-        connect_packet.write_u16(send_port).await?; // port number - 42069
-        connect_packet.write_u8(0x01).await?; // next state - 1 (ping)
+        VarInt(handshake.packet_id).write_to(&mut connect_packet)?;
+        connect_packet.extend(handshake.buffer);
 
-        send_prefixed_packet(&mut connection, &connect_packet).await?;
-
-        let mut ping_packet: Vec<u8> = vec![];
-        ping_packet.write_u8(0x00).await?;
+        let start = Instant::now();
+        send_prefixed_packet(&mut connection, &connect_packet)
+            .await
+            .during(Phase::Handshake)?;
+        timings.handshake = start.elapsed();
 
-        send_prefixed_packet(&mut connection, &ping_packet).await?;
+        let start = Instant::now();
+        send_prefixed_packet(&mut connection, &[0x00])
+            .await
+            .during(Phase::Status)?;
 
-        connection.readable().await?;
-
-        let _debug_0 = read_varint(&mut connection).await?;
-        let _debug_1 = connection.read_u8().await?;
-        if _debug_1 != 0x00 {
-            return Err("Invalid response".into());
+        let packet_len = read_varint(&mut connection).await.during(Phase::Status)?;
+        let packet_id = connection.read_u8().await.during(Phase::Status)?;
+        if packet_id != 0x00 {
+            return Err(Error::invalid_response(
+                Phase::Status,
+                format!("Expected Status Response, got packet {packet_id:#04x}"),
+            ));
         }
-        let len = read_varint(&mut connection).await?;
-
-        let mut data = vec![0; len.try_into()?];
-        connection.read_exact(&mut data).await?;
+        let len = read_varint(&mut connection).await.during(Phase::Status)?;
+        let len = usize::try_from(len)
+            .ok()
+            // it also has to fit in the packet, after the id and its own length
+            .filter(|len| {
+                *len <= MAX_STATUS_LENGTH
+                    && usize::try_from(packet_len).is_ok_and(|packet_len| *len < packet_len)
+            })
+            .ok_or_else(|| {
+                Error::invalid_response(Phase::Status, format!("Bad status length {len}"))
+            })?;
+        let mut data = vec![0; len];
+        connection
+            .read_exact(&mut data)
+            .await
+            .during(Phase::Status)?;
+        timings.status = start.elapsed();
 
-        let source: String = data.iter().map(|x| char::from(*x)).collect();
-        let parse_res: Result<Value, serde_json::Error> = serde_json::from_str(&source);
+        let source = String::from_utf8_lossy(&data);
 
-        Ok(parse_res?)
+        Ok((connection, StatusResponse::from_json(&source)?, timings))
     }
 
-    pub fn get_protocol_version(json: &Value) -> Result<i32, Box<dyn Error>> {
-        let val = json["version"]["protocol"].clone().as_i64();
-        match val {
-            Some(res) => Ok(res.try_into()?),
-            None => Err("Could not find protocol version".into()),
+    pub fn get_protocol_version(status: &StatusResponse) -> Result<i32, Error> {
+        match &status.version {
+            Some(version) => Ok(version.protocol),
+            None => Err(Error::invalid_response(
+                Phase::Status,
+                "Could not find protocol version",
+            )),
         }
     }
 
+    /// Ping with the 1.6 legacy request, which also carries the protocol
+    /// version and the address the client connected to.
     pub async fn ping_legacy_protocol(
         addr: &ServerAddress,
         input_protocol_version: Option<u8>,
         input_hostname: Option<&str>,
         input_port: Option<u16>,
-    ) -> Result<LegacyPingResult, Box<dyn Error>> {
+    ) -> Result<LegacyPingResult, Error> {
         const DEFAULT_PROTOCOL_VERSION: u8 = 69;
         const DEFAULT_HOSTNAME: &str = "shrecked.dev";
         const DEFAULT_PORT: u16 = 25565;
@@ -108,15 +338,10 @@ impl Ping {
         let send_hostname = input_hostname.unwrap_or(DEFAULT_HOSTNAME);
         let send_port = input_port.unwrap_or(DEFAULT_PORT);
 
-        let mut connection = TcpStream::connect(format!("{}:{}", addr.host, addr.port)).await?;
-
-        let mut ping_packet: Vec<u8> = vec![];
-        ping_packet
-            .write_all(&[
-                0xFEu8, 0x01, 0xFA, 0x00, 0x0B, 0x00, 0x4D, 0x00, 0x43, 0x00, 0x7C, 0x00, 0x50,
-                0x00, 0x69, 0x00, 0x6E, 0x00, 0x67, 0x00, 0x48, 0x00, 0x6F, 0x00, 0x73, 0x00, 0x74,
-            ])
-            .await?;
+        let mut ping_packet: Vec<u8> = vec![
+            0xFEu8, 0x01, 0xFA, 0x00, 0x0B, 0x00, 0x4D, 0x00, 0x43, 0x00, 0x7C, 0x00, 0x50, 0x00,
+            0x69, 0x00, 0x6E, 0x00, 0x67, 0x00, 0x48, 0x00, 0x6F, 0x00, 0x73, 0x00, 0x74,
+        ];
         let write_hostname = send_hostname.encode_utf16().collect::<Vec<u16>>();
         let mut write_hostname_bytes: Vec<u8> = vec![];
         for i in write_hostname.chunks_exact(1) {
@@ -124,26 +349,128 @@ impl Ping {
             write_hostname_bytes.push(bytes[0]);
             write_hostname_bytes.push(bytes[1]);
         }
-        ping_packet
-            .write_u16(u16::try_from(7 + write_hostname_bytes.len())?)
-            .await?;
-        ping_packet.write_u8(send_protocol_version).await?;
-        ping_packet
-            .write_u16(u16::try_from(write_hostname.len())?)
-            .await?;
-        ping_packet.write_all(&write_hostname_bytes).await?;
-        ping_packet.write_i32(i32::from(send_port)).await?;
+        let too_long = |_| Error::InvalidArgument(format!("Hostname '{send_hostname}' too long"));
+        ping_packet.extend(
+            u16::try_from(7 + write_hostname_bytes.len())
+                .map_err(too_long)?
+                .to_be_bytes(),
+        );
+        ping_packet.push(send_protocol_version);
+        ping_packet.extend(
+            u16::try_from(write_hostname.len())
+                .map_err(too_long)?
+                .to_be_bytes(),
+        );
+        ping_packet.extend(&write_hostname_bytes);
+        ping_packet.extend(i32::from(send_port).to_be_bytes());
+
+        Self::legacy_exchange(addr, &ping_packet).await
+    }
+
+    /// Ping with one of the legacy requests. The 1.6 request is sent with
+    /// `addr` as the address the client connected to.
+    ///
+    /// Servers answer in whatever format they know, so a Beta 1.8 server
+    /// sent a 1.6 request still answers, just without a protocol or
+    /// version.
+    pub async fn ping_legacy(
+        addr: &ServerAddress,
+        version: LegacyPingVersion,
+    ) -> Result<LegacyPingResult, Error> {
+        match version {
+            LegacyPingVersion::Beta1_8 => Self::legacy_exchange(addr, &[0xFE]).await,
+            LegacyPingVersion::V1_4 => Self::legacy_exchange(addr, &[0xFE, 0x01]).await,
+            LegacyPingVersion::V1_6 => {
+                Self::ping_legacy_protocol(addr, None, Some(&addr.host), Some(addr.port)).await
+            }
+        }
+    }
 
-        connection.write_all(&ping_packet).await?;
+    /// Ping with the modern status request, then each legacy request from
+    /// newest to oldest until one works, giving each attempt `timeout`.
+    /// Old servers don't understand newer requests and either hang up or
+    /// wait for more data, hence the timeout.
+    ///
+    /// Returns the error of the last attempt if none work. A refused
+    /// connection fails right away, as there's nothing to fall back to.
+    /// Other connection errors, like a failed lookup, still get every
+    /// request.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use minecraft_utilities::{AnyPing, Ping, ServerAddress};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), minecraft_utilities::Error> {
+    /// let addr = ServerAddress::try_from("localhost:25565")?;
+    /// match Ping::ping_any(&addr, Duration::from_secs(3)).await? {
+    ///     AnyPing::Modern(status) => println!("modern: {status:?}"),
+    ///     AnyPing::Legacy(version, res) => println!("{version:?}: {}", res.motd),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn ping_any(addr: &ServerAddress, timeout: Duration) -> Result<AnyPing, Error> {
+        let modern = Self::status(
+            &addr.host,
+            Some(addr.port),
+            None,
+            Some(&addr.host),
+            Some(addr.port),
+        );
+        let mut last_err = match tokio::time::timeout(timeout, modern).await {
+            Ok(Ok((_, status, _))) => return Ok(AnyPing::Modern(Box::new(status))),
+            Ok(Err(err)) if err.is_refused() => return Err(err),
+            Ok(Err(err)) => err,
+            Err(_) => Error::Timeout(Phase::Status),
+        };
 
-        let identifier = connection.read_u8().await?;
+        for version in [
+            LegacyPingVersion::V1_6,
+            LegacyPingVersion::V1_4,
+            LegacyPingVersion::Beta1_8,
+        ] {
+            match tokio::time::timeout(timeout, Self::ping_legacy(addr, version)).await {
+                Ok(Ok(res)) => return Ok(AnyPing::Legacy(version, res)),
+                Ok(Err(err)) if err.is_refused() => return Err(err),
+                Ok(Err(err)) => last_err = err,
+                Err(_) => last_err = Error::Timeout(Phase::LegacyPing),
+            }
+        }
+        Err(last_err)
+    }
+
+    /// Send a legacy ping request and read the kick packet servers answer
+    /// with.
+    async fn legacy_exchange(
+        addr: &ServerAddress,
+        request: &[u8],
+    ) -> Result<LegacyPingResult, Error> {
+        let mut connection = addr.connect().await?;
+
+        connection
+            .write_all(request)
+            .await
+            .during(Phase::LegacyPing)?;
+
+        let identifier = connection.read_u8().await.during(Phase::LegacyPing)?;
         if identifier != 0xFF {
-            return Err(format!("Bad identifier '{identifier}'").into());
+            return Err(Error::invalid_response(
+                Phase::LegacyPing,
+                format!("Bad identifier '{identifier}'"),
+            ));
         }
 
-        let _len = connection.read_u16().await?;
-        let mut res = vec![];
-        connection.read_to_end(&mut res).await?;
+        // the length is in UTF-16 code units
+        let len = connection.read_u16().await.during(Phase::LegacyPing)?;
+        let mut res = vec![0; usize::from(len) * 2];
+        connection
+            .read_exact(&mut res)
+            .await
+            .during(Phase::LegacyPing)?;
         let mut res_u16s: Vec<u16> = vec![];
         for chunk in res.chunks_exact(2) {
             res_u16s.push(((chunk[0] as u16) << 8) | chunk[1] as u16);
@@ -151,16 +478,7 @@ impl Ping {
         let res_string = char::decode_utf16(res_u16s)
             .map(|r| r.unwrap_or(char::REPLACEMENT_CHARACTER))
             .collect::<String>();
-        let mut res_iter = res_string.split('\0');
-
-        let _header = res_iter.next();
-        let mut ping_res = LegacyPingResult::default();
-        ping_res.protocol_version = str::parse::<u8>(res_iter.next().unwrap_or("0"))?;
-        ping_res.server_version = res_iter.next().unwrap_or("???").to_string();
-        ping_res.motd = res_iter.next().unwrap_or("???").to_string();
-        ping_res.player_count = str::parse::<isize>(res_iter.next().unwrap_or("-1"))?;
-        ping_res.max_player_count = str::parse::<isize>(res_iter.next().unwrap_or("-1"))?;
 
-        Ok(ping_res)
+        LegacyPingResult::parse(&res_string)
     }
 }
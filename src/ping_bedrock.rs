@@ -1,7 +1,34 @@
-use std::error::Error;
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
-use crate::ServerAddress;
-use rust_raknet::RaknetSocket;
+use tokio::{
+    net::{ToSocketAddrs, UdpSocket},
+    sync::oneshot,
+    task::JoinHandle,
+};
+
+use crate::{
+    chat::ChatComponent,
+    error::{Error, IoResultExt, Phase},
+    raknet::{parse_pong, ping_packet},
+    server_address::{unspecified_for, ServerAddress},
+    versions::{bedrock_version_consistent, parse_bedrock_version},
+};
+
+/// How long [`BedrockPinger`] waits for each attempt unless told otherwise.
+pub const DEFAULT_BEDROCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many times [`BedrockPinger`] pings before giving up unless told
+/// otherwise. Pings are single UDP packets, so they do get lost.
+pub const DEFAULT_BEDROCK_ATTEMPTS: u32 = 3;
 
 #[derive(Debug, PartialEq)]
 pub enum BedrockServerEdition {
@@ -33,63 +60,343 @@ pub struct PingBedrock {
     pub game_mode_numeric: i8,
     pub port_v4: u16,
     pub port_v6: u16,
+    /// Fields after the IPv6 port, which newer servers send.
+    pub extra: Vec<String>,
+    /// The advertisement as the server sent it.
+    pub raw: String,
 }
 
 impl PingBedrock {
-    pub async fn ping(addr: &ServerAddress) -> Result<Self, Box<dyn Error>> {
-        let deref_addr: std::net::SocketAddr = addr.clone().try_into()?;
-        let (latency, buf);
-        match RaknetSocket::ping(&deref_addr).await {
-            Ok(v) => {
-                (latency, buf) = v;
-            }
-            Err(_err) => {
-                return Err("Error connecting to server".into());
-            }
-        };
-        let mut sections = buf.split(';');
+    /// Resolve `addr` and ping it from a socket of its own, with the
+    /// default timeout and attempts. Use a [`BedrockPinger`] to ping many
+    /// servers.
+    pub async fn ping(addr: &ServerAddress) -> Result<Self, Error> {
+        let target = addr.lookup().await?;
+        BedrockPinger::bind(unspecified_for(&target))
+            .await?
+            .ping(target)
+            .await
+    }
+
+    /// The MOTD, with its `§` formatting codes turned into components.
+    pub fn motd_component(&self) -> ChatComponent {
+        ChatComponent::from_legacy(&self.motd)
+    }
+
+    /// Whether the version name and protocol agree, as
+    /// [`bedrock_version_consistent`] decides. Spoofed listings often get
+    /// this wrong.
+    pub fn version_consistent(&self) -> Option<bool> {
+        match i32::try_from(self.protocol_version) {
+            Ok(protocol) => bedrock_version_consistent(&self.version_name, protocol),
+            Err(_) => parse_bedrock_version(&self.version_name)
+                .ok()
+                .map(|_| false),
+        }
+    }
 
-        // potential issue: what if motd has ';' character?
-        if sections.clone().collect::<Vec<_>>().len() != 14 {
-            return Err("Received invalid data".into());
+    /// Parse the `;` separated advertisement from an unconnected pong.
+    ///
+    /// Servers disagree on the fields they send: older ones and some
+    /// third-party servers stop after a few, newer ones add some at the
+    /// end. Whatever is there is filled in, missing numbers are `-1` (ports
+    /// `0`) and missing text is empty, and anything past the IPv6 port ends
+    /// up in [`extra`](PingBedrock::extra). A MOTD or level name containing
+    /// `;` is put back together by looking for where the numeric fields
+    /// after it start.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minecraft_utilities::PingBedrock;
+    ///
+    /// let ping = PingBedrock::parse(
+    ///     "MCPE;\u{a7}aFun; Games;594;1.20.12;2;10;123;Lobby;Survival;1;19132;19133;0;",
+    ///     12,
+    /// )?;
+    /// assert_eq!(ping.motd, "\u{a7}aFun; Games\nLobby");
+    /// assert_eq!(ping.motd_component().to_plain(), "Fun; Games\nLobby");
+    /// assert_eq!(ping.protocol_version, 594);
+    /// assert_eq!(ping.extra, ["0"]);
+    ///
+    /// // an old server with only the basics
+    /// let ping = PingBedrock::parse("MCPE;Old Server;137;1.2.0;1;20", 12)?;
+    /// assert_eq!(ping.max_player_count, 20);
+    /// assert_eq!(ping.port_v4, 0);
+    /// # Ok::<(), minecraft_utilities::Error>(())
+    /// ```
+    pub fn parse(advertisement: &str, response_time: i64) -> Result<Self, Error> {
+        let mut sections: Vec<&str> = advertisement.split(';').collect();
+        // most servers end with a ';'
+        if sections.last() == Some(&"") {
+            sections.pop();
         }
+        if sections.len() < 2 {
+            return Err(Error::invalid_response(
+                Phase::BedrockPing,
+                format!("Not a server advertisement: '{advertisement}'"),
+            ));
+        }
+
+        let text = |i: usize| sections.get(i).copied().unwrap_or_default();
+        let number = |i: usize| text(i).trim().parse::<i64>().ok();
 
-        let edition = match sections.next().unwrap_or("?") {
+        // the protocol is the first number followed by a version and two
+        // more numbers (as far as the advertisement goes)
+        let protocol_at = (2..sections.len())
+            .find(|&i| {
+                number(i).is_some()
+                    && [i + 2, i + 3]
+                        .iter()
+                        .all(|&j| j >= sections.len() || number(j).is_some())
+            })
+            .unwrap_or(2);
+        // the level name ends at the game mode, which is followed by its
+        // number
+        let level_at = protocol_at + 5;
+        let game_mode_at = (level_at + 1..sections.len())
+            .find(|&i| {
+                parse_game_mode(text(i)) != BedrockServerGamemode::Unknown
+                    || (number(i).is_none() && number(i + 1).is_some())
+            })
+            .unwrap_or(level_at + 1);
+
+        let edition = match text(0) {
             "MCPE" => BedrockServerEdition::BedrockEdition,
             "MCEE" => BedrockServerEdition::EducationEdition,
             _ => BedrockServerEdition::Unknown,
         };
-        let mut motd = sections.next().unwrap_or("?").to_string();
-        let protocol_version = sections.next().unwrap_or("-1").parse::<i64>()?;
-        let version_name = sections.next().unwrap_or("?").to_string();
-        let player_count = sections.next().unwrap_or("-1").parse::<i64>()?;
-        let max_player_count = sections.next().unwrap_or("-1").parse::<i64>()?;
-        let server_unique_id = sections.next().unwrap_or("?").to_string();
-        motd = format!("{motd}\n{}", sections.next().unwrap_or("?"));
-        let game_mode = match sections.next().unwrap_or("?") {
-            "Creative" => BedrockServerGamemode::Creative,
-            "Survival" => BedrockServerGamemode::Survival,
-            "Adventure" => BedrockServerGamemode::Adventure,
-            "Spectator" => BedrockServerGamemode::Spectator,
-            _ => BedrockServerGamemode::Unknown,
-        };
-        let game_mode_numeric = sections.next().unwrap_or("-1").parse::<i8>()?;
-        let port_v4 = sections.next().unwrap_or("0").parse::<u16>()?;
-        let port_v6 = sections.next().unwrap_or("0").parse::<u16>()?;
+        let mut motd = sections[1..protocol_at.min(sections.len())].join(";");
+        if level_at < sections.len() {
+            let level = sections[level_at..game_mode_at.min(sections.len())].join(";");
+            motd = format!("{motd}\n{level}");
+        }
+        let port = |i: usize| text(i).trim().parse::<u16>().unwrap_or(0);
 
         Ok(PingBedrock {
-            response_time: latency,
+            response_time,
             edition,
             motd,
-            protocol_version,
-            version_name,
-            player_count,
-            max_player_count,
-            server_unique_id,
-            game_mode,
-            game_mode_numeric,
-            port_v4,
-            port_v6,
+            protocol_version: number(protocol_at).unwrap_or(-1),
+            version_name: text(protocol_at + 1).to_string(),
+            player_count: number(protocol_at + 2).unwrap_or(-1),
+            max_player_count: number(protocol_at + 3).unwrap_or(-1),
+            server_unique_id: text(protocol_at + 4).to_string(),
+            game_mode: parse_game_mode(text(game_mode_at)),
+            game_mode_numeric: text(game_mode_at + 1).trim().parse().unwrap_or(-1),
+            port_v4: port(game_mode_at + 2),
+            port_v6: port(game_mode_at + 3),
+            extra: sections
+                .get(game_mode_at + 4..)
+                .unwrap_or_default()
+                .iter()
+                .map(|extra| extra.to_string())
+                .collect(),
+            raw: advertisement.to_string(),
+        })
+    }
+}
+
+fn parse_game_mode(game_mode: &str) -> BedrockServerGamemode {
+    match game_mode.trim().to_ascii_lowercase().as_str() {
+        "creative" => BedrockServerGamemode::Creative,
+        "survival" => BedrockServerGamemode::Survival,
+        "adventure" => BedrockServerGamemode::Adventure,
+        "spectator" => BedrockServerGamemode::Spectator,
+        _ => BedrockServerGamemode::Unknown,
+    }
+}
+
+/// Pings Bedrock servers from one UDP socket, so a scanner can have
+/// thousands of pings in flight without a socket each. Clones share the
+/// socket, which is closed once every clone is dropped.
+///
+/// Each ping carries its own id in the ping's time field, which servers
+/// echo back, and the answer is matched up by it. Pings that go unanswered
+/// are sent again a few times before failing with [`Error::Timeout`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use minecraft_utilities::BedrockPinger;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), minecraft_utilities::Error> {
+/// let pinger = BedrockPinger::bind("0.0.0.0:0").await?;
+/// let (a, b) = tokio::join!(
+///     pinger.ping("192.0.2.1:19132".parse().unwrap()),
+///     pinger.ping("192.0.2.2:19132".parse().unwrap()),
+/// );
+/// println!("{:?}\n{:?}", a?.motd, b?.motd);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BedrockPinger {
+    shared: Arc<Shared>,
+    guid: i64,
+    timeout: Duration,
+    attempts: u32,
+}
+
+#[derive(Debug)]
+struct Shared {
+    socket: Arc<UdpSocket>,
+    pending: Arc<Mutex<HashMap<i64, Pending>>>,
+    next_id: AtomicI64,
+    receiver: JoinHandle<()>,
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        self.receiver.abort();
+    }
+}
+
+#[derive(Debug)]
+struct Pending {
+    ip: IpAddr,
+    respond: oneshot::Sender<(String, Instant)>,
+}
+
+/// Forgets a ping when it's done or its caller gives up on it.
+struct PendingGuard<'a> {
+    pending: &'a Mutex<HashMap<i64, Pending>>,
+    id: i64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl BedrockPinger {
+    /// Bind the socket pings are sent from. Bind `[::]:0` to ping both
+    /// IPv4 and IPv6 servers.
+    pub async fn bind(addr: impl ToSocketAddrs) -> Result<Self, Error> {
+        let socket = Arc::new(UdpSocket::bind(addr).await.during(Phase::BedrockPing)?);
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        let receiver = tokio::spawn(receive_pongs(socket.clone(), pending.clone()));
+        Ok(BedrockPinger {
+            shared: Arc::new(Shared {
+                socket,
+                pending,
+                next_id: AtomicI64::new(rand::random::<i64>() & i64::MAX),
+                receiver,
+            }),
+            guid: rand::random(),
+            timeout: DEFAULT_BEDROCK_TIMEOUT,
+            attempts: DEFAULT_BEDROCK_ATTEMPTS,
         })
     }
+
+    /// The client GUID sent with pings. Random unless set.
+    pub fn with_guid(mut self, guid: i64) -> Self {
+        self.guid = guid;
+        self
+    }
+
+    /// How long to wait for an answer before pinging again. Defaults to
+    /// [`DEFAULT_BEDROCK_TIMEOUT`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How many pings to send before giving up, at least one. Defaults to
+    /// [`DEFAULT_BEDROCK_ATTEMPTS`].
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.shared.socket.local_addr().during(Phase::BedrockPing)
+    }
+
+    /// Ping `addr`. The response time is measured from the last ping sent.
+    pub async fn ping(&self, addr: SocketAddr) -> Result<PingBedrock, Error> {
+        let target = self.sendable(addr)?;
+        let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
+        let (respond, mut response) = oneshot::channel();
+        self.shared.pending.lock().unwrap().insert(
+            id,
+            Pending {
+                ip: addr.ip().to_canonical(),
+                respond,
+            },
+        );
+        let _guard = PendingGuard {
+            pending: &self.shared.pending,
+            id,
+        };
+
+        let packet = ping_packet(id, self.guid);
+        for _ in 0..self.attempts {
+            let sent = Instant::now();
+            self.shared
+                .socket
+                .send_to(&packet, target)
+                .await
+                .during(Phase::BedrockPing)?;
+            match tokio::time::timeout(self.timeout, &mut response).await {
+                Ok(Ok((advertisement, received))) => {
+                    let response_time = received.saturating_duration_since(sent).as_millis();
+                    return PingBedrock::parse(&advertisement, response_time as i64);
+                }
+                Ok(Err(_)) => {
+                    return Err(Error::Io {
+                        phase: Phase::BedrockPing,
+                        source: io::Error::other("Stopped receiving pongs"),
+                    })
+                }
+                Err(_) => {}
+            }
+        }
+        Err(Error::Timeout(Phase::BedrockPing))
+    }
+
+    /// An IPv6 socket reaches IPv4 servers through mapped addresses, an
+    /// IPv4 socket can't reach IPv6 servers at all.
+    fn sendable(&self, addr: SocketAddr) -> Result<SocketAddr, Error> {
+        match (self.local_addr()?, addr) {
+            (SocketAddr::V6(_), SocketAddr::V4(v4)) => {
+                Ok((v4.ip().to_ipv6_mapped(), v4.port()).into())
+            }
+            (SocketAddr::V4(_), SocketAddr::V6(v6)) => match v6.ip().to_ipv4_mapped() {
+                Some(v4) => Ok((v4, v6.port()).into()),
+                None => Err(Error::InvalidAddress {
+                    address: addr.to_string(),
+                    reason: "IPv6 servers can't be pinged from an IPv4 socket",
+                }),
+            },
+            _ => Ok(addr),
+        }
+    }
+}
+
+async fn receive_pongs(socket: Arc<UdpSocket>, pending: Arc<Mutex<HashMap<i64, Pending>>>) {
+    let mut buf = vec![0; 2048];
+    loop {
+        let (len, from) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            // ICMP port unreachable for an earlier ping, on Windows
+            Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+            Err(_) => break,
+        };
+        let received = Instant::now();
+        let Some(pong) = parse_pong(&buf[..len]) else {
+            continue;
+        };
+        let mut pending = pending.lock().unwrap();
+        // only the server that was pinged can answer
+        if pending
+            .get(&pong.time)
+            .is_some_and(|ping| ping.ip == from.ip().to_canonical())
+        {
+            let ping = pending.remove(&pong.time).unwrap();
+            let _ = ping.respond.send((pong.advertisement, received));
+        }
+    }
 }
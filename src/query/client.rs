@@ -0,0 +1,158 @@
+use std::time::{Duration, Instant};
+
+use tokio::net::UdpSocket;
+
+use super::{
+    request_header, QueryBasicStat, QueryFullStat, Reader, SESSION_ID_MASK, TYPE_HANDSHAKE,
+    TYPE_STAT,
+};
+use crate::{
+    error::{Error, IoResultExt, Phase},
+    server_address::{unspecified_for, ServerAddress},
+};
+
+/// How long [`Query`] waits for each answer unless told otherwise.
+pub const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Vanilla forgets challenge tokens after 30 seconds, so get a new one a
+/// little before that.
+const CHALLENGE_LIFETIME: Duration = Duration::from_secs(25);
+
+/// A Query client for one server.
+///
+/// The challenge token from the handshake is kept and reused until it gets
+/// old. Servers silently ignore requests with a stale token, so a stat
+/// request that times out is retried once after a new handshake.
+///
+/// # Examples
+///
+/// ```no_run
+/// use minecraft_utilities::{Query, ServerAddress};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), minecraft_utilities::Error> {
+/// let addr = ServerAddress::try_from("localhost:25565")?;
+/// let mut query = Query::connect(&addr).await?;
+///
+/// let stat = query.full_stat().await?;
+/// println!("{} on {}: {}", stat.version, stat.map, stat.players.join(", "));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Query {
+    socket: UdpSocket,
+    session_id: i32,
+    challenge: Option<(i32, Instant)>,
+    timeout: Option<Duration>,
+}
+
+impl Query {
+    /// Resolve `addr` and set up a socket for it. Nothing is sent until the
+    /// first request.
+    pub async fn connect(addr: &ServerAddress) -> Result<Self, Error> {
+        let connect_error = |source| Error::Connect {
+            address: addr.clone(),
+            source,
+        };
+        let target = addr.lookup().await?;
+        let socket = UdpSocket::bind(unspecified_for(&target))
+            .await
+            .map_err(connect_error)?;
+        socket.connect(target).await.map_err(connect_error)?;
+
+        Ok(Query {
+            socket,
+            session_id: rand::random::<i32>() & SESSION_ID_MASK,
+            challenge: None,
+            timeout: Some(DEFAULT_QUERY_TIMEOUT),
+        })
+    }
+
+    /// How long to wait for each answer before failing with
+    /// [`Error::Timeout`]. `None` waits forever, which with UDP means
+    /// forever if a packet gets lost.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Get a new challenge token from the server. Stat requests do this
+    /// themselves when needed.
+    pub async fn handshake(&mut self) -> Result<i32, Error> {
+        let request = request_header(TYPE_HANDSHAKE, self.session_id);
+        let response = self.exchange(TYPE_HANDSHAKE, &request).await?;
+        let token = Reader::new(&response).string()?;
+        let token = token.trim().parse::<i32>().map_err(|_| {
+            Error::invalid_response(Phase::Query, format!("Bad challenge token '{token}'"))
+        })?;
+        self.challenge = Some((token, Instant::now()));
+        Ok(token)
+    }
+
+    pub async fn basic_stat(&mut self) -> Result<QueryBasicStat, Error> {
+        let response = self.stat(false).await?;
+        QueryBasicStat::read_from(&mut Reader::new(&response))
+    }
+
+    pub async fn full_stat(&mut self) -> Result<QueryFullStat, Error> {
+        let response = self.stat(true).await?;
+        QueryFullStat::read_from(&mut Reader::new(&response))
+    }
+
+    async fn stat(&mut self, full: bool) -> Result<Vec<u8>, Error> {
+        let fresh = self.challenge.is_none();
+        match self.try_stat(full).await {
+            Err(Error::Timeout(_)) if !fresh => {
+                // the token might have expired
+                self.challenge = None;
+                self.try_stat(full).await
+            }
+            res => res,
+        }
+    }
+
+    async fn try_stat(&mut self, full: bool) -> Result<Vec<u8>, Error> {
+        let token = match self.challenge {
+            Some((token, issued)) if issued.elapsed() < CHALLENGE_LIFETIME => token,
+            _ => self.handshake().await?,
+        };
+
+        let mut request = request_header(TYPE_STAT, self.session_id);
+        request.extend(token.to_be_bytes());
+        if full {
+            // four more bytes is what makes it a full stat request
+            request.extend([0; 4]);
+        }
+        self.exchange(TYPE_STAT, &request).await
+    }
+
+    /// Send `request` and return the body of the answer to it, skipping
+    /// anything that isn't (like late answers to earlier requests).
+    async fn exchange(&mut self, response_type: u8, request: &[u8]) -> Result<Vec<u8>, Error> {
+        self.socket.send(request).await.during(Phase::Query)?;
+
+        let receive = async {
+            // the biggest UDP payload there is
+            let mut buf = vec![0; 65535];
+            loop {
+                let len = self.socket.recv(&mut buf).await.during(Phase::Query)?;
+                let mut reader = Reader::new(&buf[..len]);
+                if reader.u8().ok() == Some(response_type)
+                    && reader.i32_be().ok() == Some(self.session_id)
+                {
+                    return Ok(reader.remaining().to_vec());
+                }
+            }
+        };
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, receive)
+                .await
+                .unwrap_or(Err(Error::Timeout(Phase::Query))),
+            None => receive.await,
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! The GameSpy4 based Query protocol servers with `enable-query=true` answer
+//! on UDP (`query.port`, by default the game port).
+//!
+//! Every request starts with `FE FD`, a type and a session id. A handshake
+//! (type 9) returns a challenge token, which stat requests (type 0) have to
+//! carry. Basic stat has the MOTD, map and player counts; full stat adds
+//! the version, plugins and the whole player list.
+
+mod client;
+pub use client::{Query, DEFAULT_QUERY_TIMEOUT};
+
+mod server;
+pub use server::{QueryServer, QueryStats, DEFAULT_CHALLENGE_LIFETIME};
+
+use crate::error::{Error, Phase};
+
+pub(crate) const MAGIC: [u8; 2] = [0xFE, 0xFD];
+pub(crate) const TYPE_HANDSHAKE: u8 = 9;
+pub(crate) const TYPE_STAT: u8 = 0;
+
+/// Vanilla masks session ids with this, so only these bits round trip.
+pub(crate) const SESSION_ID_MASK: i32 = 0x0F0F0F0F;
+
+/// Between the session id and the key-value section of a full stat.
+pub(crate) const FULL_STAT_PADDING: [u8; 11] = *b"splitnum\0\x80\0";
+/// Between the key-value section and the player list.
+pub(crate) const PLAYERS_PADDING: [u8; 10] = *b"\x01player_\0\0";
+
+/// The answer to a basic stat request.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QueryBasicStat {
+    pub motd: String,
+    pub game_type: String,
+    pub map: String,
+    pub player_count: u32,
+    pub max_player_count: u32,
+    pub host_port: u16,
+    pub host_ip: String,
+}
+
+/// The answer to a full stat request.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QueryFullStat {
+    pub motd: String,
+    pub game_type: String,
+    pub game_id: String,
+    pub version: String,
+    /// The server software, like `CraftBukkit on Bukkit 1.2.5-R4.0`. Vanilla
+    /// doesn't send one.
+    pub software: Option<String>,
+    pub plugins: Vec<String>,
+    pub map: String,
+    pub player_count: u32,
+    pub max_player_count: u32,
+    pub host_port: u16,
+    pub host_ip: String,
+    /// Every online player, not just a sample like the status ping.
+    pub players: Vec<String>,
+    /// Keys the server sent that aren't fields above, in the order they
+    /// came.
+    pub extra: Vec<(String, String)>,
+}
+
+impl QueryFullStat {
+    /// The `plugins` value as servers send it: `software: plugin; plugin`.
+    pub fn plugins_string(&self) -> String {
+        match &self.software {
+            Some(software) if self.plugins.is_empty() => software.clone(),
+            Some(software) => format!("{software}: {}", self.plugins.join("; ")),
+            None => String::new(),
+        }
+    }
+
+    fn set_plugins(&mut self, plugins: &str) {
+        if plugins.is_empty() {
+            return;
+        }
+        match plugins.split_once(':') {
+            Some((software, list)) => {
+                self.software = Some(software.trim().to_string());
+                self.plugins = list
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|plugin| !plugin.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            None => self.software = Some(plugins.trim().to_string()),
+        }
+    }
+}
+
+/// Reads the pieces of a Query packet, failing with
+/// [`Error::InvalidResponse`] when the packet ends early.
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Reader { buf }
+    }
+
+    pub(crate) fn remaining(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.buf.len() < len {
+            return Err(Error::invalid_response(Phase::Query, "Packet ended early"));
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn i32_be(&mut self) -> Result<i32, Error> {
+        Ok(i32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    /// The nul terminated strings Query uses everywhere.
+    pub(crate) fn string(&mut self) -> Result<String, Error> {
+        let len = self
+            .buf
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or_else(|| Error::invalid_response(Phase::Query, "Unterminated string"))?;
+        let string = String::from_utf8_lossy(&self.buf[..len]).into_owned();
+        self.buf = &self.buf[len + 1..];
+        Ok(string)
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, Error> {
+        let string = self.string()?;
+        parse_number(&string)
+    }
+
+    /// Skip `expected`, which servers send but doesn't mean anything.
+    fn padding(&mut self, expected: &[u8]) -> Result<(), Error> {
+        if self.bytes(expected.len())? != expected {
+            return Err(Error::invalid_response(Phase::Query, "Bad padding"));
+        }
+        Ok(())
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(string: &str) -> Result<T, Error> {
+    string
+        .trim()
+        .parse()
+        .map_err(|_| Error::invalid_response(Phase::Query, format!("Bad number '{string}'")))
+}
+
+pub(crate) fn write_string(buf: &mut Vec<u8>, string: &str) {
+    // a nul would end the string early
+    buf.extend(string.bytes().filter(|&byte| byte != 0));
+    buf.push(0);
+}
+
+/// `FE FD`, the type and the session id.
+pub(crate) fn request_header(request_type: u8, session_id: i32) -> Vec<u8> {
+    let mut buf = MAGIC.to_vec();
+    buf.push(request_type);
+    buf.extend(session_id.to_be_bytes());
+    buf
+}
+
+/// The type and session id that start every response.
+pub(crate) fn response_header(response_type: u8, session_id: i32) -> Vec<u8> {
+    let mut buf = vec![response_type];
+    buf.extend(session_id.to_be_bytes());
+    buf
+}
+
+impl QueryBasicStat {
+    pub(crate) fn read_from(reader: &mut Reader) -> Result<Self, Error> {
+        let motd = reader.string()?;
+        let game_type = reader.string()?;
+        let map = reader.string()?;
+        let player_count = reader.number()?;
+        let max_player_count = reader.number()?;
+        let host_port = u16::from_le_bytes(reader.bytes(2)?.try_into().unwrap());
+        let host_ip = reader.string()?;
+        Ok(QueryBasicStat {
+            motd,
+            game_type,
+            map,
+            player_count,
+            max_player_count,
+            host_port,
+            host_ip,
+        })
+    }
+
+    pub(crate) fn write_to(&self, buf: &mut Vec<u8>) {
+        write_string(buf, &self.motd);
+        write_string(buf, &self.game_type);
+        write_string(buf, &self.map);
+        write_string(buf, &self.player_count.to_string());
+        write_string(buf, &self.max_player_count.to_string());
+        buf.extend(self.host_port.to_le_bytes());
+        write_string(buf, &self.host_ip);
+    }
+}
+
+/// The part of a full stat that basic stat has too.
+impl From<&QueryFullStat> for QueryBasicStat {
+    fn from(stat: &QueryFullStat) -> Self {
+        QueryBasicStat {
+            motd: stat.motd.clone(),
+            game_type: stat.game_type.clone(),
+            map: stat.map.clone(),
+            player_count: stat.player_count,
+            max_player_count: stat.max_player_count,
+            host_port: stat.host_port,
+            host_ip: stat.host_ip.clone(),
+        }
+    }
+}
+
+impl QueryFullStat {
+    pub(crate) fn read_from(reader: &mut Reader) -> Result<Self, Error> {
+        reader.padding(&FULL_STAT_PADDING)?;
+
+        let mut stat = QueryFullStat::default();
+        loop {
+            let key = reader.string()?;
+            if key.is_empty() {
+                break;
+            }
+            let value = reader.string()?;
+            match key.as_str() {
+                "hostname" => stat.motd = value,
+                "gametype" => stat.game_type = value,
+                "game_id" => stat.game_id = value,
+                "version" => stat.version = value,
+                "plugins" => stat.set_plugins(&value),
+                "map" => stat.map = value,
+                "numplayers" => stat.player_count = parse_number(&value)?,
+                "maxplayers" => stat.max_player_count = parse_number(&value)?,
+                "hostport" => stat.host_port = parse_number(&value)?,
+                "hostip" => stat.host_ip = value,
+                _ => stat.extra.push((key, value)),
+            }
+        }
+
+        reader.padding(&PLAYERS_PADDING)?;
+        // the list ends with an empty name, but some servers cut it off
+        while !reader.remaining().is_empty() {
+            let player = reader.string()?;
+            if player.is_empty() {
+                break;
+            }
+            stat.players.push(player);
+        }
+
+        Ok(stat)
+    }
+
+    pub(crate) fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend(FULL_STAT_PADDING);
+        let plugins = self.plugins_string();
+        let player_count = self.player_count.to_string();
+        let max_player_count = self.max_player_count.to_string();
+        let host_port = self.host_port.to_string();
+        let fields = [
+            ("hostname", self.motd.as_str()),
+            ("gametype", &self.game_type),
+            ("game_id", &self.game_id),
+            ("version", &self.version),
+            ("plugins", &plugins),
+            ("map", &self.map),
+            ("numplayers", &player_count),
+            ("maxplayers", &max_player_count),
+            ("hostport", &host_port),
+            ("hostip", &self.host_ip),
+        ];
+        let extra = self.extra.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+        for (key, value) in fields.into_iter().chain(extra) {
+            write_string(buf, key);
+            write_string(buf, value);
+        }
+        buf.push(0);
+
+        buf.extend(PLAYERS_PADDING);
+        for player in &self.players {
+            write_string(buf, player);
+        }
+        buf.push(0);
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use tokio::net::{ToSocketAddrs, UdpSocket};
+
+use super::{
+    response_header, write_string, QueryBasicStat, QueryFullStat, Reader, MAGIC, TYPE_HANDSHAKE,
+    TYPE_STAT,
+};
+use crate::error::{Error, IoResultExt, Phase};
+
+/// How long challenge tokens from [`QueryServer`] stay valid, like vanilla.
+pub const DEFAULT_CHALLENGE_LIFETIME: Duration = Duration::from_secs(30);
+
+/// Where a [`QueryServer`] gets the stats it answers with. Implemented for
+/// [`QueryFullStat`] itself, for fixed answers, and for closures returning
+/// one, for answers that change.
+pub trait QueryStats: Send + Sync {
+    fn stats(&self) -> QueryFullStat;
+}
+
+impl QueryStats for QueryFullStat {
+    fn stats(&self) -> QueryFullStat {
+        self.clone()
+    }
+}
+
+impl<F> QueryStats for F
+where
+    F: Fn() -> QueryFullStat + Send + Sync,
+{
+    fn stats(&self) -> QueryFullStat {
+        self()
+    }
+}
+
+/// A Query server that behaves like vanilla's: handshakes get a challenge
+/// token for the sender's address, and stat requests with a missing, wrong
+/// or expired token are ignored. Basic stat answers with the parts of the
+/// stats that fit.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::{Query, QueryFullStat, QueryServer, ServerAddress};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), minecraft_utilities::Error> {
+/// let stats = QueryFullStat {
+///     motd: "A Minecraft Server".to_string(),
+///     players: vec!["Notch".to_string()],
+///     player_count: 1,
+///     max_player_count: 20,
+///     ..Default::default()
+/// };
+/// let server = QueryServer::bind("127.0.0.1:0", stats).await?;
+/// let addr = ServerAddress::from(server.local_addr()?);
+/// tokio::spawn(server.run());
+///
+/// let mut query = Query::connect(&addr).await?;
+/// assert_eq!(query.full_stat().await?.players, ["Notch"]);
+/// assert_eq!(query.basic_stat().await?.player_count, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct QueryServer {
+    socket: UdpSocket,
+    stats: Box<dyn QueryStats>,
+    challenge_lifetime: Duration,
+}
+
+impl std::fmt::Debug for QueryServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryServer")
+            .field("socket", &self.socket)
+            .field("challenge_lifetime", &self.challenge_lifetime)
+            .finish_non_exhaustive()
+    }
+}
+
+impl QueryServer {
+    pub async fn bind(
+        addr: impl ToSocketAddrs,
+        stats: impl QueryStats + 'static,
+    ) -> Result<Self, Error> {
+        Ok(QueryServer {
+            socket: UdpSocket::bind(addr).await.during(Phase::Query)?,
+            stats: Box::new(stats),
+            challenge_lifetime: DEFAULT_CHALLENGE_LIFETIME,
+        })
+    }
+
+    /// How long a challenge token can be used. Defaults to
+    /// [`DEFAULT_CHALLENGE_LIFETIME`].
+    pub fn with_challenge_lifetime(mut self, challenge_lifetime: Duration) -> Self {
+        self.challenge_lifetime = challenge_lifetime;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.socket.local_addr().during(Phase::Query)
+    }
+
+    /// Answer requests until receiving fails.
+    pub async fn run(self) -> Result<(), Error> {
+        // sender => challenge token and when it was handed out
+        let mut challenges: HashMap<SocketAddr, (i32, Instant)> = HashMap::new();
+        let mut last_cleanup = Instant::now();
+        let mut buf = vec![0; 1500];
+
+        loop {
+            let (len, from) = self.socket.recv_from(&mut buf).await.during(Phase::Query)?;
+
+            if last_cleanup.elapsed() >= self.challenge_lifetime {
+                challenges.retain(|_, (_, issued)| issued.elapsed() < self.challenge_lifetime);
+                last_cleanup = Instant::now();
+            }
+
+            let Some(response) = self.respond(&buf[..len], from, &mut challenges) else {
+                continue;
+            };
+            // the sender being gone is their problem
+            let _ = self.socket.send_to(&response, from).await;
+        }
+    }
+
+    fn respond(
+        &self,
+        request: &[u8],
+        from: SocketAddr,
+        challenges: &mut HashMap<SocketAddr, (i32, Instant)>,
+    ) -> Option<Vec<u8>> {
+        let mut reader = Reader::new(request);
+        if reader.bytes(2).ok()? != MAGIC {
+            return None;
+        }
+        let request_type = reader.u8().ok()?;
+        let session_id = reader.i32_be().ok()?;
+        let mut response = response_header(request_type, session_id);
+
+        match request_type {
+            TYPE_HANDSHAKE => {
+                let token = rand::random::<i32>();
+                challenges.insert(from, (token, Instant::now()));
+                write_string(&mut response, &token.to_string());
+            }
+            TYPE_STAT => {
+                let token = reader.i32_be().ok()?;
+                match challenges.get(&from) {
+                    Some(&(expected, issued))
+                        if expected == token && issued.elapsed() < self.challenge_lifetime => {}
+                    _ => return None,
+                }
+                let stats = self.stats.stats();
+                // full stat requests have four bytes of padding after the token
+                if reader.remaining().len() >= 4 {
+                    stats.write_to(&mut response);
+                } else {
+                    QueryBasicStat::from(&stats).write_to(&mut response);
+                }
+            }
+            _ => return None,
+        }
+
+        Some(response)
+    }
+}
@@ -0,0 +1,78 @@
+//! The two RakNet offline messages Bedrock server lists use: the unconnected
+//! ping a client sends, and the unconnected pong carrying the server's
+//! advertisement (MOTD, version, player counts...).
+
+/// Sent in every offline message so they can't be mistaken for anything
+/// else.
+pub(crate) const OFFLINE_MAGIC: [u8; 16] = [
+    0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+
+pub(crate) const UNCONNECTED_PING: u8 = 0x01;
+/// Sent by clients that only want servers with open slots to answer.
+pub(crate) const UNCONNECTED_PING_OPEN_CONNECTIONS: u8 = 0x02;
+pub(crate) const UNCONNECTED_PONG: u8 = 0x1c;
+
+/// `time` is echoed back in the pong, whatever it is.
+pub(crate) fn ping_packet(time: i64, client_guid: i64) -> Vec<u8> {
+    let mut buf = vec![UNCONNECTED_PING];
+    buf.extend(time.to_be_bytes());
+    buf.extend(OFFLINE_MAGIC);
+    buf.extend(client_guid.to_be_bytes());
+    buf
+}
+
+/// The time and client GUID of an unconnected ping, `None` if `buf` isn't
+/// one.
+pub(crate) fn parse_ping(buf: &[u8]) -> Option<(i64, i64)> {
+    if buf.len() < 33
+        || ![UNCONNECTED_PING, UNCONNECTED_PING_OPEN_CONNECTIONS].contains(&buf[0])
+        || buf[9..25] != OFFLINE_MAGIC
+    {
+        return None;
+    }
+    let time = i64::from_be_bytes(buf[1..9].try_into().unwrap());
+    let client_guid = i64::from_be_bytes(buf[25..33].try_into().unwrap());
+    Some((time, client_guid))
+}
+
+/// Advertisements longer than a u16 can count are cut off, between
+/// characters.
+pub(crate) fn pong_packet(time: i64, server_guid: i64, advertisement: &str) -> Vec<u8> {
+    let mut len = advertisement.len().min(u16::MAX as usize);
+    while !advertisement.is_char_boundary(len) {
+        len -= 1;
+    }
+    let advertisement = &advertisement.as_bytes()[..len];
+    let mut buf = vec![UNCONNECTED_PONG];
+    buf.extend(time.to_be_bytes());
+    buf.extend(server_guid.to_be_bytes());
+    buf.extend(OFFLINE_MAGIC);
+    buf.extend((advertisement.len() as u16).to_be_bytes());
+    buf.extend(advertisement);
+    buf
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pong {
+    pub(crate) time: i64,
+    pub(crate) server_guid: i64,
+    pub(crate) advertisement: String,
+}
+
+/// `None` if `buf` isn't an unconnected pong.
+pub(crate) fn parse_pong(buf: &[u8]) -> Option<Pong> {
+    if buf.len() < 35 || buf[0] != UNCONNECTED_PONG || buf[17..33] != OFFLINE_MAGIC {
+        return None;
+    }
+    let time = i64::from_be_bytes(buf[1..9].try_into().unwrap());
+    let server_guid = i64::from_be_bytes(buf[9..17].try_into().unwrap());
+    let len = u16::from_be_bytes(buf[33..35].try_into().unwrap()) as usize;
+    // some servers get the length wrong, trust the packet over it
+    let advertisement = &buf[35..buf.len().min(35 + len)];
+    Some(Pong {
+        time,
+        server_guid,
+        advertisement: String::from_utf8_lossy(advertisement).into_owned(),
+    })
+}
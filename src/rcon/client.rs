@@ -0,0 +1,304 @@
+use std::{future::Future, io, time::Duration};
+
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
+
+use super::{
+    RconCodec, RconError, RconPacket, SERVERDATA_AUTH, SERVERDATA_EXECCOMMAND,
+    SERVERDATA_RESPONSE_VALUE,
+};
+use crate::{
+    error::{Error, IoResultExt, Phase},
+    ServerAddress,
+};
+
+/// How long [`RconClient`] waits for a connection, a login or a command
+/// unless told otherwise.
+pub const DEFAULT_RCON_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An RCON connection.
+///
+/// If the connection drops after logging in, the next command reconnects
+/// and logs in again with the same password before running. Dropping the
+/// client closes the connection, [`close`](RconClient::close) does it
+/// gracefully.
+#[derive(Debug)]
+pub struct RconClient {
+    address: ServerAddress,
+    request_id: i32,
+    /// Source servers answer it a second time, after the command is done.
+    last_terminator_id: Option<i32>,
+    pub(super) connected: bool,
+    password: Option<String>,
+    pub(super) timeout: Option<Duration>,
+    strip_formatting: bool,
+    pub(super) stream: Option<Framed<TcpStream, RconCodec>>,
+}
+
+impl RconClient {
+    pub async fn connect(addr: &ServerAddress, password: Option<&str>) -> Result<Self, Error> {
+        let mut client = RconClient {
+            address: addr.clone(),
+            request_id: 0,
+            last_terminator_id: None,
+            connected: false,
+            password: None,
+            timeout: Some(DEFAULT_RCON_TIMEOUT),
+            strip_formatting: false,
+            stream: None,
+        };
+
+        client.ensure_stream().await?;
+
+        if let Some(password) = password {
+            client.login(password).await?;
+        }
+
+        Ok(client)
+    }
+
+    /// How long connecting, logging in and each command may take before
+    /// failing with [`Error::Timeout`]. `None` waits forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Remove `§` formatting codes from command output. Off by default, and
+    /// kept by [`into_handle`](RconClient::into_handle).
+    pub fn set_strip_formatting(&mut self, strip_formatting: bool) {
+        self.strip_formatting = strip_formatting;
+    }
+
+    /// Command output as text, with or without formatting codes.
+    pub(super) fn output_to_string(&self, output: &RconPacket) -> String {
+        if self.strip_formatting {
+            output.payload_to_plain_string()
+        } else {
+            output.payload_to_string()
+        }
+    }
+
+    pub async fn login(&mut self, password: &str) -> Result<RconPacket, Error> {
+        if self.connected {
+            return Err(RconError::DoubleLogin.into());
+        }
+
+        let login_res = self.authenticate(password).await?;
+        self.connected = true;
+
+        Ok(login_res)
+    }
+
+    /// Shut down the connection, letting the server know we're leaving.
+    pub async fn close(mut self) -> Result<(), Error> {
+        if let Some(mut stream) = self.stream.take() {
+            stream.close().await.during(Phase::Rcon)?;
+        }
+        Ok(())
+    }
+
+    async fn authenticate(&mut self, password: &str) -> Result<RconPacket, Error> {
+        self.ensure_stream().await?;
+
+        let mut login_packet = RconPacket::new();
+        let login_packet_id = self.next_request_id();
+        login_packet.request_id = login_packet_id;
+        login_packet.request_type = SERVERDATA_AUTH;
+        login_packet.payload = password.into();
+
+        let res = within(self.timeout, Phase::Rcon, async {
+            self.write_packet(&login_packet).await?;
+            loop {
+                let res = self.read_packet().await?;
+                // Source servers send an empty SERVERDATA_RESPONSE_VALUE
+                // before the actual SERVERDATA_AUTH_RESPONSE
+                if res.request_type == SERVERDATA_RESPONSE_VALUE && res.payload.is_empty() {
+                    continue;
+                }
+                break Ok(res);
+            }
+        })
+        .await;
+        let login_res = match res {
+            Ok(login_res) => login_res,
+            Err(err) => {
+                self.stream = None;
+                return Err(err);
+            }
+        };
+
+        if login_res.request_id == -1 {
+            return Err(RconError::AuthenticationFailure.into());
+        } else if login_res.request_id != login_packet_id {
+            self.stream = None;
+            return Err(RconError::UnexpectedRequestId {
+                expected: login_packet_id,
+                found: login_res.request_id,
+            }
+            .into());
+        }
+
+        self.password = Some(password.to_string());
+
+        Ok(login_res)
+    }
+
+    async fn ensure_stream(&mut self) -> Result<(), Error> {
+        if self.stream.is_none() {
+            let stream = within(self.timeout, Phase::Connect, self.address.connect()).await?;
+            self.stream = Some(Framed::new(stream, RconCodec::new()));
+        }
+        Ok(())
+    }
+
+    pub(super) async fn reconnect(&mut self) -> Result<(), Error> {
+        let password = self.password.clone().ok_or(RconError::EarlyCommand)?;
+        self.stream = None;
+        self.authenticate(&password).await?;
+        Ok(())
+    }
+
+    pub(super) fn next_request_id(&mut self) -> i32 {
+        let request_id = self.request_id;
+        // -1 is what the server answers failed logins with
+        self.request_id = self.request_id.checked_add(1).unwrap_or(0);
+        request_id
+    }
+
+    async fn write_packet(&mut self, packet: &RconPacket) -> Result<(), Error> {
+        let stream = self.stream.as_mut().ok_or(RconError::EarlyPacket)?;
+        stream.send(packet).await.during(Phase::Rcon)
+    }
+
+    async fn read_packet(&mut self) -> Result<RconPacket, Error> {
+        let stream = self.stream.as_mut().ok_or(RconError::EarlyPacket)?;
+        next_packet(stream).await
+    }
+
+    /// Run a command and return its output.
+    ///
+    /// If the connection turns out to have dropped, it is reestablished and
+    /// the command sent again. A server that goes away after running the
+    /// command but before answering can end up running it twice.
+    pub async fn command(&mut self, command: &str) -> Result<String, Error> {
+        if !self.connected {
+            return Err(RconError::EarlyCommand.into());
+        }
+
+        let reconnected = self.stream.is_none();
+        if reconnected {
+            self.reconnect().await?;
+        }
+
+        match self.try_command(command).await {
+            Err(err) if !reconnected && is_connection_lost(&err) => {
+                self.reconnect().await?;
+                self.try_command(command).await
+            }
+            res => res,
+        }
+    }
+
+    async fn try_command(&mut self, command: &str) -> Result<String, Error> {
+        let res = within(self.timeout, Phase::Rcon, self.run_command(command)).await;
+        if res.is_err() {
+            // whatever is still in flight would be mistaken for the next
+            // command's output
+            self.stream = None;
+        }
+        res
+    }
+
+    /// The server splits output longer than 4096 bytes over several packets
+    /// without saying how many there are, so the command is followed by an
+    /// empty `SERVERDATA_RESPONSE_VALUE` packet. The server answers packets in
+    /// order, so once the answer to that one comes back every part of the
+    /// command's output has arrived.
+    async fn run_command(&mut self, command: &str) -> Result<String, Error> {
+        let mut packet = RconPacket::new();
+        let command_id = self.next_request_id();
+        packet.request_id = command_id;
+        packet.request_type = SERVERDATA_EXECCOMMAND;
+        packet.payload = command.into();
+
+        let mut terminator = RconPacket::new();
+        let terminator_id = self.next_request_id();
+        terminator.request_id = terminator_id;
+        terminator.request_type = SERVERDATA_RESPONSE_VALUE;
+
+        self.write_packet(&packet).await?;
+        self.write_packet(&terminator).await?;
+
+        let mut payload = vec![];
+        loop {
+            let res = self.read_packet().await?;
+            if res.request_id == command_id {
+                payload.extend(res.payload);
+            } else if res.request_id == terminator_id {
+                self.last_terminator_id = Some(terminator_id);
+                break;
+            } else if Some(res.request_id) == self.last_terminator_id {
+                // Source servers answer the terminator twice, leaving the
+                // second answer to be read with the next command
+                continue;
+            } else {
+                return Err(RconError::UnexpectedRequestId {
+                    expected: command_id,
+                    found: res.request_id,
+                }
+                .into());
+            }
+        }
+
+        let res = RconPacket {
+            request_id: command_id,
+            request_type: SERVERDATA_RESPONSE_VALUE,
+            payload,
+        };
+        Ok(self.output_to_string(&res))
+    }
+}
+
+pub(super) async fn next_packet(
+    stream: &mut Framed<TcpStream, RconCodec>,
+) -> Result<RconPacket, Error> {
+    match stream.next().await {
+        Some(packet) => packet.during(Phase::Rcon),
+        None => Err(Error::io(
+            Phase::Rcon,
+            io::Error::new(io::ErrorKind::UnexpectedEof, "Connection closed by server"),
+        )),
+    }
+}
+
+pub(super) async fn within<T>(
+    timeout: Option<Duration>,
+    phase: Phase,
+    future: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future)
+            .await
+            .unwrap_or(Err(Error::Timeout(phase))),
+        None => future.await,
+    }
+}
+
+fn is_connection_lost(err: &Error) -> bool {
+    match err {
+        Error::Io { source, .. } => matches!(
+            source.kind(),
+            io::ErrorKind::BrokenPipe
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+                | io::ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
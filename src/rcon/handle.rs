@@ -0,0 +1,253 @@
+use std::{collections::HashMap, io, sync::Arc, time::Duration};
+
+use futures::SinkExt;
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+
+use super::{
+    client::next_packet, RconClient, RconError, RconPacket, SERVERDATA_EXECCOMMAND,
+    SERVERDATA_RESPONSE_VALUE,
+};
+use crate::{
+    error::{Error, IoResultExt, Phase},
+    ServerAddress,
+};
+
+/// A logged in RCON connection that can be shared between tasks. Clones use
+/// the same connection.
+///
+/// A background task owns the socket. Every command gets its own request
+/// ids, so commands from many tasks can be in flight at once and each
+/// caller gets back exactly its own output. The task (and the connection)
+/// stops once every handle is dropped.
+///
+/// If the connection drops, or a command isn't answered in time, commands in
+/// flight fail and the next one reconnects and logs in again.
+///
+/// # Examples
+///
+/// ```no_run
+/// use minecraft_utilities::{RconHandle, ServerAddress};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), minecraft_utilities::Error> {
+/// let addr = ServerAddress::try_from("localhost:25575")?;
+/// let rcon = RconHandle::connect(&addr, "hunter2").await?;
+///
+/// let (list, seed) = tokio::join!(rcon.command("list"), rcon.command("seed"));
+/// println!("{}\n{}", list?, seed?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RconHandle {
+    requests: mpsc::UnboundedSender<Request>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct Request {
+    command: String,
+    deadline: Option<Instant>,
+    respond: oneshot::Sender<Result<String, Error>>,
+}
+
+#[derive(Debug)]
+struct PendingCommand {
+    output: Vec<u8>,
+    deadline: Option<Instant>,
+    respond: oneshot::Sender<Result<String, Error>>,
+}
+
+impl RconHandle {
+    pub async fn connect(addr: &ServerAddress, password: &str) -> Result<Self, Error> {
+        RconClient::connect(addr, Some(password))
+            .await?
+            .into_handle()
+    }
+
+    /// Run a command and return its output. Times out after the
+    /// [timeout](RconClient::set_timeout) of the client the handle was made
+    /// from.
+    pub async fn command(&self, command: &str) -> Result<String, Error> {
+        let (respond, response) = oneshot::channel();
+        self.requests
+            .send(Request {
+                command: command.to_string(),
+                deadline: self.timeout.map(|timeout| Instant::now() + timeout),
+                respond,
+            })
+            .map_err(|_| RconError::Closed)?;
+        response.await.map_err(|_| RconError::Closed)?
+    }
+}
+
+impl RconClient {
+    /// Hand the connection to a background task and get a cloneable
+    /// [`RconHandle`] to it. The client has to be logged in.
+    pub fn into_handle(self) -> Result<RconHandle, Error> {
+        if !self.connected {
+            return Err(RconError::EarlyCommand.into());
+        }
+
+        let (requests, requests_rx) = mpsc::unbounded_channel();
+        let timeout = self.timeout;
+        tokio::spawn(handle_loop(self, requests_rx));
+
+        Ok(RconHandle { requests, timeout })
+    }
+}
+
+async fn handle_loop(mut client: RconClient, mut requests: mpsc::UnboundedReceiver<Request>) {
+    let mut pending: HashMap<i32, PendingCommand> = HashMap::new();
+    // terminator request id => command request id
+    let mut terminators: HashMap<i32, i32> = HashMap::new();
+
+    loop {
+        let deadline = pending
+            .values()
+            .filter_map(|command| command.deadline)
+            .min();
+        let packet = tokio::select! {
+            request = requests.recv() => {
+                let Some(request) = request else {
+                    break;
+                };
+                if client.stream.is_none() {
+                    if let Err(err) = client.reconnect().await {
+                        let _ = request.respond.send(Err(err));
+                        continue;
+                    }
+                }
+                let sent =
+                    send_command(&mut client, request, &mut pending, &mut terminators).await;
+                if let Err(err) = sent {
+                    fail_pending(&mut client, err, &mut pending, &mut terminators);
+                }
+                continue;
+            }
+            packet = next_packet_if_connected(&mut client) => packet,
+            () = sleep_until(deadline) => {
+                // a server that stopped answering won't answer the rest
+                // either, and a late answer would be mistaken for the output
+                // of the next command
+                let now = Instant::now();
+                let expired: Vec<i32> = pending
+                    .iter()
+                    .filter(|(_, command)| command.deadline.is_some_and(|deadline| deadline <= now))
+                    .map(|(command_id, _)| *command_id)
+                    .collect();
+                for command_id in expired {
+                    if let Some(command) = pending.remove(&command_id) {
+                        let _ = command.respond.send(Err(Error::Timeout(Phase::Rcon)));
+                    }
+                }
+                fail_pending(&mut client, Error::Timeout(Phase::Rcon), &mut pending, &mut terminators);
+                continue;
+            }
+        };
+
+        let packet = match packet {
+            Ok(packet) => packet,
+            Err(err) => {
+                fail_pending(&mut client, err, &mut pending, &mut terminators);
+                continue;
+            }
+        };
+
+        if let Some(command) = pending.get_mut(&packet.request_id) {
+            command.output.extend(packet.payload);
+        } else if let Some(command_id) = terminators.remove(&packet.request_id) {
+            // the server answers in order, so the command's output is complete
+            if let Some(command) = pending.remove(&command_id) {
+                let res = RconPacket {
+                    request_id: command_id,
+                    request_type: SERVERDATA_RESPONSE_VALUE,
+                    payload: command.output,
+                };
+                let _ = command.respond.send(Ok(client.output_to_string(&res)));
+            }
+        }
+        // anything else is a second answer to a terminator, or an answer to
+        // a command whose caller is gone
+    }
+
+    let _ = client.close().await;
+}
+
+async fn next_packet_if_connected(client: &mut RconClient) -> Result<RconPacket, Error> {
+    match &mut client.stream {
+        Some(stream) => next_packet(stream).await,
+        // wait for a command to reconnect
+        None => std::future::pending().await,
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Send a command followed by an empty terminator packet, see
+/// [`RconClient::command`].
+async fn send_command(
+    client: &mut RconClient,
+    request: Request,
+    pending: &mut HashMap<i32, PendingCommand>,
+    terminators: &mut HashMap<i32, i32>,
+) -> Result<(), Error> {
+    let command_id = client.next_request_id();
+    let terminator_id = client.next_request_id();
+    pending.insert(
+        command_id,
+        PendingCommand {
+            output: vec![],
+            deadline: request.deadline,
+            respond: request.respond,
+        },
+    );
+    terminators.insert(terminator_id, command_id);
+
+    let stream = client.stream.as_mut().ok_or(RconError::EarlyPacket)?;
+    let packet = RconPacket {
+        request_id: command_id,
+        request_type: SERVERDATA_EXECCOMMAND,
+        payload: request.command.into_bytes(),
+    };
+    let terminator = RconPacket {
+        request_id: terminator_id,
+        request_type: SERVERDATA_RESPONSE_VALUE,
+        payload: vec![],
+    };
+    stream.feed(&packet).await.during(Phase::Rcon)?;
+    stream.send(&terminator).await.during(Phase::Rcon)
+}
+
+/// Drop the connection and fail every command that was waiting on it. Each
+/// gets an io error of the same kind carrying `err`, which can be downcast to
+/// an `Arc<Error>`.
+fn fail_pending(
+    client: &mut RconClient,
+    err: Error,
+    pending: &mut HashMap<i32, PendingCommand>,
+    terminators: &mut HashMap<i32, i32>,
+) {
+    client.stream = None;
+    terminators.clear();
+    let kind = match &err {
+        Error::Io { source, .. } => source.kind(),
+        Error::Timeout(_) => io::ErrorKind::TimedOut,
+        _ => io::ErrorKind::ConnectionAborted,
+    };
+    let err = Arc::new(err);
+    for (_, command) in pending.drain() {
+        let _ = command.respond.send(Err(Error::Io {
+            phase: Phase::Rcon,
+            source: io::Error::new(kind, Arc::clone(&err)),
+        }));
+    }
+}
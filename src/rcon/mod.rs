@@ -0,0 +1,199 @@
+//! The RCON protocol, which runs commands on a server over TCP.
+//!
+//! See <https://wiki.vg/RCON>.
+
+use std::io;
+
+use bytes::{Buf, BufMut, BytesMut};
+use thiserror::Error;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{chat::strip_legacy_codes, error::Error};
+
+mod client;
+pub use client::{RconClient, DEFAULT_RCON_TIMEOUT};
+
+mod handle;
+pub use handle::RconHandle;
+
+mod server;
+pub use server::{RconHandler, RconServer, MAX_COMMAND_PAYLOAD, MAX_RESPONSE_PAYLOAD};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RconError {
+    #[error("Attempted to authenticate while already logged into RCON server.")]
+    DoubleLogin,
+    #[error("Authentication failure, make sure you sent the correct password.")]
+    AuthenticationFailure,
+    #[error("Recieved a strange packet from the server. Are you sure this is an RCON server?")]
+    StrangePacket,
+    #[error("Attempted to send packet before establishing a connection to the server.")]
+    EarlyPacket,
+    #[error("Attempted to send command before client is authenticated.")]
+    EarlyCommand,
+    #[error("Expected a response to request {expected}, got one to request {found}.")]
+    UnexpectedRequestId { expected: i32, found: i32 },
+    #[error("The RCON connection has been closed.")]
+    Closed,
+    #[error("RCON packet length {0} is invalid, packets are at least 10 bytes.")]
+    InvalidLength(i64),
+    #[error("RCON packet payload is not nul terminated.")]
+    MissingTerminator,
+}
+
+/// Packet types. `SERVERDATA_AUTH_RESPONSE` and `SERVERDATA_EXECCOMMAND`
+/// share the same number, the direction tells them apart.
+const SERVERDATA_AUTH: i32 = 3;
+const SERVERDATA_EXECCOMMAND: i32 = 2;
+const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+
+/// The largest payload [`RconCodec::new`] accepts. Vanilla splits output
+/// every 4096 characters, which can take up to three bytes each in UTF-8,
+/// plus some room for servers that are more generous.
+pub const DEFAULT_RCON_MAX_PAYLOAD: usize = 4096 * 4;
+
+#[derive(Debug)]
+pub struct RconPacket {
+    pub request_id: i32,
+    pub request_type: i32,
+    pub payload: Vec<u8>,
+}
+
+impl Default for RconPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RconPacket {
+    pub fn new() -> Self {
+        RconPacket {
+            request_id: 0,
+            request_type: 0,
+            payload: vec![],
+        }
+    }
+
+    pub async fn build(&self) -> Result<Vec<u8>, Error> {
+        let mut buffer = BytesMut::new();
+        self.write_to(&mut buffer);
+        Ok(buffer.to_vec())
+    }
+
+    pub async fn parse(&mut self, raw: &[u8]) -> Result<(), RconError> {
+        *self = Self::read_from(raw)?;
+        Ok(())
+    }
+
+    /// The payload as UTF-8. Invalid sequences become `U+FFFD`.
+    pub fn payload_to_string(&self) -> String {
+        String::from_utf8_lossy(&self.payload).into_owned()
+    }
+
+    /// The payload as UTF-8 without `§` formatting codes.
+    pub fn payload_to_plain_string(&self) -> String {
+        strip_legacy_codes(&self.payload_to_string())
+    }
+
+    fn write_to(&self, buffer: &mut BytesMut) {
+        buffer.reserve(14 + self.payload.len());
+        buffer.put_i32_le(self.payload.len() as i32 + 10);
+        buffer.put_i32_le(self.request_id);
+        buffer.put_i32_le(self.request_type); // 3 for login, 2 to run a command, 0 for a multi-packet response
+        buffer.put_slice(&self.payload);
+        buffer.put_slice(b"\0\0");
+    }
+
+    /// Read a packet without its length prefix. The payload has to be
+    /// followed by a nul byte, usually there are two (the second one
+    /// terminating an empty string), but the second one is optional.
+    fn read_from(mut raw: &[u8]) -> Result<Self, RconError> {
+        if raw.len() < 10 {
+            return Err(RconError::InvalidLength(raw.len() as i64));
+        }
+        let request_id = raw.get_i32_le();
+        let request_type = raw.get_i32_le();
+        let payload = raw
+            .strip_suffix(b"\0\0")
+            .or_else(|| raw.strip_suffix(b"\0"))
+            .ok_or(RconError::MissingTerminator)?
+            .to_vec();
+
+        Ok(RconPacket {
+            request_id,
+            request_type,
+            payload,
+        })
+    }
+}
+
+/// A [`tokio_util::codec`] for length prefixed [`RconPacket`]s.
+#[derive(Debug)]
+pub struct RconCodec {
+    max_payload: usize,
+}
+
+impl Default for RconCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RconCodec {
+    pub fn new() -> Self {
+        Self::with_max_payload(DEFAULT_RCON_MAX_PAYLOAD)
+    }
+
+    /// Refuse packets with payloads longer than `max_payload` bytes, failing
+    /// with [`Error::PacketTooLarge`].
+    pub fn with_max_payload(max_payload: usize) -> Self {
+        RconCodec { max_payload }
+    }
+}
+
+impl Decoder for RconCodec {
+    type Item = RconPacket;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let Some(mut len) = src.get(..4) else {
+            return Ok(None);
+        };
+        let len = len.get_i32_le();
+        let len = match usize::try_from(len) {
+            Ok(len) if len >= 10 => len,
+            _ => return Err(invalid_data(RconError::InvalidLength(len.into()).into())),
+        };
+        // request id, type and the two nul bytes
+        if len - 10 > self.max_payload {
+            return Err(invalid_data(Error::PacketTooLarge {
+                length: len - 10,
+                limit: self.max_payload,
+            }));
+        }
+        if src.len() < 4 + len {
+            src.reserve(4 + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(4);
+        let body = src.split_to(len);
+        Ok(Some(
+            RconPacket::read_from(&body).map_err(|err| invalid_data(err.into()))?,
+        ))
+    }
+}
+
+impl Encoder<&RconPacket> for RconCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: &RconPacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        item.write_to(dst);
+        Ok(())
+    }
+}
+
+/// Wrap one of our errors so [`Error::io`] can unwrap it again.
+fn invalid_data(err: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
@@ -0,0 +1,214 @@
+use std::{future::Future, net::SocketAddr, sync::Arc};
+
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio_util::codec::Framed;
+
+use super::{
+    RconCodec, RconPacket, SERVERDATA_AUTH, SERVERDATA_EXECCOMMAND, SERVERDATA_RESPONSE_VALUE,
+};
+use crate::error::{Error, IoResultExt, Phase};
+
+/// The longest command vanilla accepts. Its buffer is 1460 bytes, minus the
+/// request id, type and nul terminators.
+pub const MAX_COMMAND_PAYLOAD: usize = 1446;
+
+/// How much output goes into one packet. Longer output is split over several.
+pub const MAX_RESPONSE_PAYLOAD: usize = 4096;
+
+const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+
+/// Runs the commands an [`RconServer`] receives. Implemented for async
+/// closures taking the command and returning its output.
+#[async_trait]
+pub trait RconHandler: Send + Sync {
+    async fn handle(&self, command: String) -> String;
+}
+
+#[async_trait]
+impl<F, Fut> RconHandler for F
+where
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = String> + Send,
+{
+    async fn handle(&self, command: String) -> String {
+        self(command).await
+    }
+}
+
+/// An RCON server that behaves like vanilla's: clients log in with a
+/// password, long output is split over several packets, and anything that
+/// isn't a login or a command is answered with `Unknown request`, which
+/// [`RconClient`](super::RconClient) relies on to find the end of the output.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::{RconClient, RconServer, ServerAddress};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), minecraft_utilities::Error> {
+/// let server = RconServer::bind("127.0.0.1:0", "hunter2", |command: String| async move {
+///     format!("You said {command}")
+/// })
+/// .await?;
+/// let addr = ServerAddress::from(server.local_addr()?);
+/// tokio::spawn(server.run());
+///
+/// let mut client = RconClient::connect(&addr, Some("hunter2")).await?;
+/// assert_eq!(client.command("hi").await?, "You said hi");
+/// # Ok(())
+/// # }
+/// ```
+pub struct RconServer {
+    listener: TcpListener,
+    password: Arc<str>,
+    handler: Arc<dyn RconHandler>,
+    max_command_payload: usize,
+}
+
+impl std::fmt::Debug for RconServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RconServer")
+            .field("listener", &self.listener)
+            .field("max_command_payload", &self.max_command_payload)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RconServer {
+    pub async fn bind(
+        addr: impl ToSocketAddrs,
+        password: &str,
+        handler: impl RconHandler + 'static,
+    ) -> Result<Self, Error> {
+        Ok(RconServer {
+            listener: TcpListener::bind(addr).await.during(Phase::Rcon)?,
+            password: password.into(),
+            handler: Arc::new(handler),
+            max_command_payload: MAX_COMMAND_PAYLOAD,
+        })
+    }
+
+    /// Drop connections that send packets with payloads longer than
+    /// `max_command_payload` bytes. Defaults to [`MAX_COMMAND_PAYLOAD`].
+    pub fn with_max_command_payload(mut self, max_command_payload: usize) -> Self {
+        self.max_command_payload = max_command_payload;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr().during(Phase::Rcon)
+    }
+
+    /// Accept connections until accepting fails, serving each on its own
+    /// task.
+    pub async fn run(self) -> Result<(), Error> {
+        loop {
+            let (stream, _) = self.listener.accept().await.during(Phase::Rcon)?;
+            let connection = Framed::new(
+                stream,
+                RconCodec::with_max_payload(self.max_command_payload),
+            );
+            tokio::spawn(serve_connection(
+                connection,
+                self.password.clone(),
+                self.handler.clone(),
+            ));
+        }
+    }
+}
+
+/// Commands are run one after another, so answers go out in the order the
+/// requests came in.
+async fn serve_connection(
+    mut connection: Framed<TcpStream, RconCodec>,
+    password: Arc<str>,
+    handler: Arc<dyn RconHandler>,
+) -> Result<(), Error> {
+    let mut authenticated = false;
+
+    while let Some(packet) = connection.next().await {
+        let packet = packet.during(Phase::Rcon)?;
+        match packet.request_type {
+            SERVERDATA_AUTH => {
+                authenticated = packet.payload == password.as_bytes();
+                let request_id = if authenticated { packet.request_id } else { -1 };
+                send(
+                    &mut connection,
+                    request_id,
+                    SERVERDATA_AUTH_RESPONSE,
+                    vec![],
+                )
+                .await?;
+            }
+            _ if !authenticated => {
+                send(&mut connection, -1, SERVERDATA_AUTH_RESPONSE, vec![]).await?;
+            }
+            SERVERDATA_EXECCOMMAND => {
+                let command = String::from_utf8_lossy(&packet.payload).into_owned();
+                let output = handler.handle(command).await;
+                for chunk in response_chunks(&output) {
+                    connection
+                        .feed(&RconPacket {
+                            request_id: packet.request_id,
+                            request_type: SERVERDATA_RESPONSE_VALUE,
+                            payload: chunk.as_bytes().to_vec(),
+                        })
+                        .await
+                        .during(Phase::Rcon)?;
+                }
+                connection.flush().await.during(Phase::Rcon)?;
+            }
+            request_type => {
+                let answer = format!("Unknown request {request_type:x}");
+                send(
+                    &mut connection,
+                    packet.request_id,
+                    SERVERDATA_RESPONSE_VALUE,
+                    answer.into_bytes(),
+                )
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Split a command's output into packet payloads, never cutting a character
+/// in half so each packet is valid UTF-8 on its own. There is always at least
+/// one, even for no output.
+fn response_chunks(mut output: &str) -> Vec<&str> {
+    let mut res = vec![];
+    while !output.is_empty() {
+        let mut end = output.len().min(MAX_RESPONSE_PAYLOAD);
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, rest) = output.split_at(end);
+        res.push(chunk);
+        output = rest;
+    }
+    if res.is_empty() {
+        res.push("");
+    }
+    res
+}
+
+async fn send(
+    connection: &mut Framed<TcpStream, RconCodec>,
+    request_id: i32,
+    request_type: i32,
+    payload: Vec<u8>,
+) -> Result<(), Error> {
+    connection
+        .send(&RconPacket {
+            request_id,
+            request_type,
+            payload,
+        })
+        .await
+        .during(Phase::Rcon)
+}
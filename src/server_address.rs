@@ -15,10 +15,14 @@
 
 use std::{
     fmt::Display,
-    net::{AddrParseError, Ipv4Addr, SocketAddr, SocketAddrV4},
+    net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     str::FromStr,
 };
 
+use tokio::net::TcpStream;
+
+use crate::error::Error;
+
 /// A host and port. It's possible that the port doesn't resolve to anything.
 ///
 /// # Examples
@@ -30,6 +34,11 @@ use std::{
 /// let addr = ServerAddress::try_from("localhost:25565").unwrap();
 /// assert_eq!(addr.host, "localhost");
 /// assert_eq!(addr.port, 25565);
+///
+/// // IPv6 addresses need brackets to be given a port
+/// let addr = ServerAddress::try_from("[::1]:19132").unwrap();
+/// assert_eq!(addr.host, "::1");
+/// assert_eq!(addr.to_string(), "[::1]:19132");
 /// ```
 #[derive(Debug, Clone)]
 pub struct ServerAddress {
@@ -44,23 +53,85 @@ impl ServerAddress {
             port,
         }
     }
+
+    /// Open a TCP connection, reporting failure as [`Error::Connect`].
+    pub(crate) async fn connect(&self) -> Result<TcpStream, Error> {
+        TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|source| Error::Connect {
+                address: self.clone(),
+                source,
+            })
+    }
+
+    /// Resolve the host to the first address it has, reporting failure as
+    /// [`Error::Connect`].
+    pub(crate) async fn lookup(&self) -> Result<SocketAddr, Error> {
+        tokio::net::lookup_host((self.host.as_str(), self.port))
+            .await
+            .map_err(|source| Error::Connect {
+                address: self.clone(),
+                source,
+            })?
+            .next()
+            .ok_or_else(|| Error::InvalidAddress {
+                address: self.to_string(),
+                reason: "Host did not resolve to anything",
+            })
+    }
 }
 
-impl<'a> TryFrom<&'a str> for ServerAddress {
-    type Error = String;
+/// Any local address of the same family as `addr`, to bind sockets that
+/// talk to it.
+pub(crate) fn unspecified_for(addr: &SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    }
+}
+
+impl TryFrom<&str> for ServerAddress {
+    type Error = Error;
 
     /// Convert a Minecraft server address (host:port, the port is optional) to
-    /// a `ServerAddress`
+    /// a `ServerAddress`. IPv6 hosts are written `[::1]:25565`, or just `::1`
+    /// without a port.
     fn try_from(string: &str) -> Result<Self, Self::Error> {
+        let invalid = |reason| Error::InvalidAddress {
+            address: string.to_string(),
+            reason,
+        };
         if string.is_empty() {
-            return Err("Empty string".to_string());
+            return Err(invalid("Empty string"));
+        }
+        let (host, port) = if let Some(rest) = string.strip_prefix('[') {
+            let (host, rest) = rest.split_once(']').ok_or(invalid("Unclosed bracket"))?;
+            let port = match rest {
+                "" => None,
+                rest => Some(
+                    rest.strip_prefix(':')
+                        .ok_or(invalid("Invalid port specified"))?,
+                ),
+            };
+            (host, port)
+        } else if Ipv6Addr::from_str(string).is_ok() {
+            (string, None)
+        } else {
+            match string.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (string, None),
+            }
+        };
+        if host.is_empty() {
+            return Err(invalid("No host specified"));
         }
-        let mut parts = string.split(':');
-        let host = parts.next().ok_or("No host specified")?.to_string();
         // default the port to 25565
-        let port = parts.next().unwrap_or("25565");
-        let port = u16::from_str(port).map_err(|_| "Invalid port specified")?;
-        Ok(ServerAddress { host, port })
+        let port = u16::from_str(port.unwrap_or("25565"))
+            .map_err(|_| invalid("Invalid port specified"))?;
+        Ok(ServerAddress {
+            host: host.to_string(),
+            port,
+        })
     }
 }
 
@@ -81,13 +152,17 @@ impl TryFrom<ServerAddress> for SocketAddr {
 
     /// Convert an existing `ServerAddress` into a `SocketAddr`.
     fn try_from(addr: ServerAddress) -> Result<Self, Self::Error> {
-        let v4_addr = Ipv4Addr::from_str(&addr.host)?;
-        Ok(SocketAddr::V4(SocketAddrV4::new(v4_addr, addr.port)))
+        let ip = IpAddr::from_str(&addr.host)?;
+        Ok(SocketAddr::new(ip, addr.port))
     }
 }
 
 impl Display for ServerAddress {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}:{}", self.host, self.port)
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
     }
 }
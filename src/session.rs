@@ -0,0 +1,351 @@
+//! Staying connected after login.
+//!
+//! [`Client::into_session`](crate::Client::into_session) splits a logged in
+//! client into a [`PacketSender`] and a [`PacketStream`]. A background task
+//! reads from the server, answers Keep Alive and Ping on its own, gets through
+//! the configuration state added in 1.20.2 (again whenever the server or a
+//! proxy sends it back there), and hands everything else to the stream.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{SinkExt, Stream, StreamExt};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::mpsc,
+    task::JoinHandle,
+};
+use tokio_util::codec::Framed;
+
+use crate::{
+    chat::ChatComponent,
+    codec::MinecraftCodec,
+    data_types::DataTypeError,
+    packets::{configuration, play, Packet},
+    packetutil::MinecraftPacket,
+};
+
+#[derive(Error, Debug)]
+pub enum SessionError {
+    #[error("Disconnected by server: {0}")]
+    Disconnected(Box<ChatComponent>),
+    #[error("Lost connection to server: {0}")]
+    ConnectionLost(#[source] io::Error),
+    #[error("Session has ended")]
+    Closed,
+    #[error("Protocol version {0} is not supported in the play state")]
+    UnsupportedProtocol(i32),
+    #[error("Invalid packet from server: {0}")]
+    InvalidPacket(#[from] DataTypeError),
+}
+
+/// Which part of the protocol a packet from the [`PacketStream`] belongs to.
+/// Packet ids are only meaningful together with the state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolState {
+    Configuration,
+    Play,
+}
+
+/// Whether the session knows the ids of every packet it handles itself in
+/// `protocol_version`.
+fn supports_play(protocol_version: i32) -> bool {
+    play::Disconnect::id(protocol_version).is_some()
+        && play::ClientboundKeepAlive::id(protocol_version).is_some()
+        && play::ServerboundKeepAlive::id(protocol_version).is_some()
+        && play::Ping::id(protocol_version).is_some()
+        && play::Pong::id(protocol_version).is_some()
+}
+
+#[derive(Debug)]
+enum Outbound {
+    Packet(MinecraftPacket),
+    Close,
+}
+
+/// The writing half of a session. Clones share the same connection.
+#[derive(Debug, Clone)]
+pub struct PacketSender {
+    outbound: mpsc::UnboundedSender<Outbound>,
+}
+
+impl PacketSender {
+    pub async fn send(&self, packet_id: i32, data: &[u8]) -> Result<(), SessionError> {
+        self.outbound
+            .send(Outbound::Packet(MinecraftPacket {
+                packet_id,
+                buffer: data.to_vec(),
+            }))
+            .map_err(|_| SessionError::Closed)
+    }
+
+    /// Shut down the connection.
+    pub async fn close(&self) -> Result<(), SessionError> {
+        self.outbound
+            .send(Outbound::Close)
+            .map_err(|_| SessionError::Closed)
+    }
+}
+
+type Inbound = Result<(ProtocolState, MinecraftPacket), SessionError>;
+
+/// The reading half of a session, a [`Stream`] of every packet the session
+/// didn't handle itself. It ends after yielding an error, which is
+/// [`SessionError::Disconnected`] if the server kicked us.
+#[derive(Debug)]
+pub struct PacketStream {
+    receiver: mpsc::UnboundedReceiver<Inbound>,
+    state: ProtocolState,
+    task: JoinHandle<()>,
+}
+
+impl PacketStream {
+    /// The state the last packet yielded by the stream was received in.
+    pub fn state(&self) -> ProtocolState {
+        self.state
+    }
+}
+
+impl Stream for PacketStream {
+    type Item = Result<MinecraftPacket, SessionError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.receiver.poll_recv(cx) {
+            Poll::Ready(Some(Ok((state, packet)))) => {
+                self.state = state;
+                Poll::Ready(Some(Ok(packet)))
+            }
+            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for PacketStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Start a session on a connection that has just finished logging in.
+pub(crate) fn start_session<T>(
+    connection: Framed<T, MinecraftCodec>,
+    protocol_version: i32,
+) -> Result<(PacketSender, PacketStream), SessionError>
+where
+    T: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    if !supports_play(protocol_version) {
+        return Err(SessionError::UnsupportedProtocol(protocol_version));
+    }
+
+    let protocol_state = if configuration::FinishConfiguration::id(protocol_version).is_some() {
+        ProtocolState::Configuration
+    } else {
+        ProtocolState::Play
+    };
+
+    let (outbound, outbound_rx) = mpsc::unbounded_channel();
+    let (inbound_tx, receiver) = mpsc::unbounded_channel();
+    let task = tokio::spawn(session_loop(
+        connection,
+        protocol_version,
+        protocol_state,
+        outbound_rx,
+        inbound_tx,
+    ));
+
+    Ok((
+        PacketSender { outbound },
+        PacketStream {
+            receiver,
+            state: protocol_state,
+            task,
+        },
+    ))
+}
+
+async fn session_loop<T>(
+    mut connection: Framed<T, MinecraftCodec>,
+    protocol_version: i32,
+    mut protocol_state: ProtocolState,
+    mut outbound: mpsc::UnboundedReceiver<Outbound>,
+    inbound: mpsc::UnboundedSender<Inbound>,
+) where
+    T: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    loop {
+        let packet = tokio::select! {
+            packet = connection.next() => packet,
+            message = outbound.recv() => {
+                let res = match message {
+                    Some(Outbound::Packet(packet)) => connection.send(packet).await,
+                    Some(Outbound::Close) | None => connection.close().await,
+                };
+                if let Err(err) = res {
+                    let _ = inbound.send(Err(SessionError::ConnectionLost(err)));
+                    return;
+                }
+                continue;
+            }
+        };
+        let packet = match packet {
+            Some(Ok(packet)) => packet,
+            Some(Err(err)) => {
+                let _ = inbound.send(Err(SessionError::ConnectionLost(err)));
+                return;
+            }
+            None => {
+                let _ = inbound.send(Err(SessionError::ConnectionLost(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed by server",
+                ))));
+                return;
+            }
+        };
+
+        let handled = match protocol_state {
+            ProtocolState::Configuration => {
+                handle_config_packet(
+                    &packet,
+                    &mut connection,
+                    protocol_version,
+                    &mut protocol_state,
+                )
+                .await
+            }
+            ProtocolState::Play => {
+                handle_play_packet(
+                    &packet,
+                    &mut connection,
+                    protocol_version,
+                    &mut protocol_state,
+                )
+                .await
+            }
+        };
+        match handled {
+            Ok(true) => {}
+            Ok(false) => {
+                if inbound.send(Ok((protocol_state, packet))).is_err() {
+                    // nobody is listening anymore
+                    return;
+                }
+            }
+            Err(err) => {
+                let _ = inbound.send(Err(err));
+                return;
+            }
+        }
+    }
+}
+
+async fn reply<T>(
+    connection: &mut Framed<T, MinecraftCodec>,
+    packet: impl Packet,
+    protocol_version: i32,
+) -> Result<(), SessionError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let packet = packet.to_packet(protocol_version)?;
+    connection
+        .send(packet)
+        .await
+        .map_err(SessionError::ConnectionLost)
+}
+
+/// Returns whether the packet was dealt with, or an error if the session is
+/// over.
+async fn handle_config_packet<T>(
+    packet: &MinecraftPacket,
+    connection: &mut Framed<T, MinecraftCodec>,
+    protocol_version: i32,
+    protocol_state: &mut ProtocolState,
+) -> Result<bool, SessionError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    use configuration::*;
+
+    if Disconnect::matches(packet, protocol_version) {
+        Err(disconnected(
+            Disconnect::from_packet(packet, protocol_version).map(|packet| packet.reason()),
+        ))
+    } else if ClientboundKeepAlive::matches(packet, protocol_version) {
+        let ClientboundKeepAlive { id } =
+            ClientboundKeepAlive::from_packet(packet, protocol_version)?;
+        reply(connection, ServerboundKeepAlive { id }, protocol_version).await?;
+        Ok(true)
+    } else if Ping::matches(packet, protocol_version) {
+        let Ping { id } = Ping::from_packet(packet, protocol_version)?;
+        reply(connection, Pong { id }, protocol_version).await?;
+        Ok(true)
+    } else if ClientboundKnownPacks::matches(packet, protocol_version) {
+        // claim to know no packs, so the server sends every registry in full
+        let packs = vec![];
+        reply(
+            connection,
+            ServerboundKnownPacks { packs },
+            protocol_version,
+        )
+        .await?;
+        Ok(true)
+    } else if FinishConfiguration::matches(packet, protocol_version) {
+        reply(
+            connection,
+            AcknowledgeFinishConfiguration {},
+            protocol_version,
+        )
+        .await?;
+        *protocol_state = ProtocolState::Play;
+        Ok(true)
+    } else {
+        // Registry Data, Update Tags, Feature Flags, plugin messages...
+        Ok(false)
+    }
+}
+
+async fn handle_play_packet<T>(
+    packet: &MinecraftPacket,
+    connection: &mut Framed<T, MinecraftCodec>,
+    protocol_version: i32,
+    protocol_state: &mut ProtocolState,
+) -> Result<bool, SessionError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    use play::*;
+
+    if Disconnect::matches(packet, protocol_version) {
+        Err(disconnected(
+            Disconnect::from_packet(packet, protocol_version).map(|packet| packet.reason()),
+        ))
+    } else if ClientboundKeepAlive::matches(packet, protocol_version) {
+        let ClientboundKeepAlive { id } =
+            ClientboundKeepAlive::from_packet(packet, protocol_version)?;
+        reply(connection, ServerboundKeepAlive { id }, protocol_version).await?;
+        Ok(true)
+    } else if Ping::matches(packet, protocol_version) {
+        let Ping { id } = Ping::from_packet(packet, protocol_version)?;
+        reply(connection, Pong { id }, protocol_version).await?;
+        Ok(true)
+    } else if StartConfiguration::matches(packet, protocol_version) {
+        reply(connection, AcknowledgeConfiguration {}, protocol_version).await?;
+        *protocol_state = ProtocolState::Configuration;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// A Disconnect packet that can't be read still means the server kicked us.
+fn disconnected(reason: Result<ChatComponent, DataTypeError>) -> SessionError {
+    let reason = reason.unwrap_or_else(|_| ChatComponent::text("Disconnected"));
+    SessionError::Disconnected(Box::new(reason))
+}
@@ -0,0 +1,208 @@
+//! Typed model of the JSON document returned by a Server List Ping.
+//!
+//! See <https://wiki.vg/Server_List_Ping#Status_Response> for the format.
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::chat::ChatComponent;
+
+/// The response to a Status Request.
+///
+/// Every field is optional because servers (and proxies) are free to leave
+/// parts of the document out. Fields this struct doesn't know about end up in
+/// [`StatusResponse::extra`], and the untouched document is kept in
+/// [`StatusResponse::raw`].
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::StatusResponse;
+///
+/// let status = StatusResponse::from_json(
+///     r#"{
+///         "version": { "name": "1.19.4", "protocol": 762 },
+///         "players": {
+///             "max": 20,
+///             "online": 1,
+///             "sample": [{ "name": "Shrecknt", "id": "b64dfb9c-82ec-426d-918c-73f62afc4e01" }]
+///         },
+///         "description": "A Minecraft Server",
+///         "enforcesSecureChat": true,
+///         "someCustomField": 42
+///     }"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(status.version.unwrap().protocol, 762);
+/// assert_eq!(status.players.unwrap().sample[0].name, "Shrecknt");
+/// assert_eq!(status.description.unwrap().to_plain(), "A Minecraft Server");
+/// assert_eq!(status.enforces_secure_chat, Some(true));
+/// assert_eq!(status.extra["someCustomField"], 42);
+/// assert_eq!(status.raw["players"]["online"], 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<StatusVersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub players: Option<StatusPlayers>,
+    /// The MOTD, sent either as a plain string or a chat component.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<ChatComponent>,
+    /// A `data:image/png;base64,...` URI of the server icon.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enforces_secure_chat: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previews_chat: Option<bool>,
+    /// Sent by Forge 1.13+ and NeoForge servers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forge_data: Option<ForgeData>,
+    /// Sent by Forge (FML) servers before 1.13.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modinfo: Option<LegacyForgeModInfo>,
+    /// Sent by NeoForge servers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_modded: Option<bool>,
+    /// Any fields that aren't modelled above.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+    /// The document exactly as the server sent it.
+    #[serde(skip)]
+    pub raw: Value,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusVersion {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub protocol: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusPlayers {
+    #[serde(default)]
+    pub max: i32,
+    #[serde(default)]
+    pub online: i32,
+    /// Entries that can't be read are left out.
+    #[serde(
+        default,
+        deserialize_with = "skip_invalid",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub sample: Vec<StatusPlayerSample>,
+}
+
+/// Servers and plugins put anything they like in the sample (lines of text
+/// with placeholder ids are common), so `id` is kept as sent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusPlayerSample {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub id: String,
+}
+
+impl StatusPlayerSample {
+    /// `id` as a UUID, if it is one.
+    pub fn uuid(&self) -> Option<Uuid> {
+        Uuid::parse_str(&self.id).ok()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForgeData {
+    /// Entries that can't be read are left out.
+    #[serde(default, deserialize_with = "skip_invalid")]
+    pub channels: Vec<ForgeChannel>,
+    /// Entries that can't be read are left out.
+    #[serde(default, deserialize_with = "skip_invalid")]
+    pub mods: Vec<ForgeMod>,
+    #[serde(default)]
+    pub fml_network_version: i32,
+    /// Set when the server had to leave mods out to stay under the packet size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<bool>,
+    /// Forge 1.18+ packs the mod and channel lists into this string instead
+    /// of `mods`/`channels`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub d: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForgeChannel {
+    #[serde(default)]
+    pub res: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForgeMod {
+    #[serde(default)]
+    pub mod_id: String,
+    #[serde(rename = "modmarker", default)]
+    pub mod_marker: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyForgeModInfo {
+    #[serde(rename = "type", default)]
+    pub mod_type: String,
+    /// Entries that can't be read are left out.
+    #[serde(default, deserialize_with = "skip_invalid")]
+    pub mod_list: Vec<LegacyForgeMod>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LegacyForgeMod {
+    #[serde(rename = "modid", default)]
+    pub mod_id: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+/// Read a list, dropping the entries that don't fit `T` instead of failing
+/// the whole response over one of them. Anything but a list is empty.
+fn skip_invalid<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let Value::Array(values) = Value::deserialize(deserializer)? else {
+        return Ok(vec![]);
+    };
+    Ok(values
+        .into_iter()
+        .filter_map(|value| serde_json::from_value(value).ok())
+        .collect())
+}
+
+impl StatusResponse {
+    pub fn from_json(source: &str) -> Result<Self, serde_json::Error> {
+        Self::from_value(serde_json::from_str(source)?)
+    }
+
+    pub fn from_value(raw: Value) -> Result<Self, serde_json::Error> {
+        let mut res: StatusResponse = serde_json::from_value(raw.clone())?;
+        res.raw = raw;
+        Ok(res)
+    }
+
+    /// Whether the server advertised itself as running Forge or NeoForge.
+    pub fn has_mod_loader(&self) -> bool {
+        self.forge_data.is_some() || self.modinfo.is_some() || self.is_modded == Some(true)
+    }
+}
@@ -1,20 +1,30 @@
 use phf::phf_map;
 
-pub fn parse_version(version_name: &str) -> Result<i32, String> {
+use crate::error::Error;
+
+pub fn parse_version(version_name: &str) -> Result<i32, Error> {
     let res = VERSIONS.get(version_name);
     match res {
-        Some(val) => {
-            Ok(*val)
-        }
-        None => {
-            Err(format!(
-                "Unable to find version '{}' in version table",
-                version_name
-            ))
-        }
+        Some(val) => Ok(*val),
+        None => Err(Error::UnknownVersion(version_name.to_string())),
     }
 }
 
+// Releases whose protocol changed in a way this crate cares about. Packet
+// definitions use these as the bounds of their version ranges.
+pub const PROTOCOL_1_16: i32 = 735;
+pub const PROTOCOL_1_19: i32 = 759;
+pub const PROTOCOL_1_19_1: i32 = 760;
+pub const PROTOCOL_1_19_3: i32 = 761;
+pub const PROTOCOL_1_19_4: i32 = 762;
+pub const PROTOCOL_1_20: i32 = 763;
+pub const PROTOCOL_1_20_2: i32 = 764;
+pub const PROTOCOL_1_20_3: i32 = 765;
+pub const PROTOCOL_1_20_5: i32 = 766;
+pub const PROTOCOL_1_21: i32 = 767;
+pub const PROTOCOL_1_21_2: i32 = 768;
+pub const PROTOCOL_1_21_4: i32 = 769;
+
 static VERSIONS: phf::Map<&'static str, i32> = phf_map! {
     "13w41b" => 0x0,
     "13w42b" => 0x1,
@@ -501,4 +511,164 @@ static VERSIONS: phf::Map<&'static str, i32> = phf_map! {
     "23w13a" => 0x40000080,
     "23w14a" => 0x40000082,
     "23w16a" => 0x40000083,
+    "1.20" => 0x2fb,
+    "1.20.1" => 0x2fb,
+    "1.20.2" => 0x2fc,
+    "1.20.3" => 0x2fd,
+    "1.20.4" => 0x2fd,
+    "1.20.5" => 0x2fe,
+    "1.20.6" => 0x2fe,
+    "1.21" => 0x2ff,
+    "1.21.1" => 0x2ff,
+    "1.21.2" => 0x300,
+    "1.21.3" => 0x300,
+    "1.21.4" => 0x301,
 };
+
+/// Look up the protocol of a Bedrock release. Patch releases not in the
+/// table get the protocol of the latest release before them in the same
+/// line (`1.20.12` is `1.20.10`'s 594), hotfixes rarely change it.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::parse_bedrock_version;
+///
+/// assert_eq!(parse_bedrock_version("1.20.10").unwrap(), 594);
+/// assert_eq!(parse_bedrock_version("1.20.12").unwrap(), 594);
+/// assert!(parse_bedrock_version("1.99.0").is_err());
+/// ```
+pub fn parse_bedrock_version(version_name: &str) -> Result<i32, Error> {
+    let unknown = || Error::UnknownVersion(version_name.to_string());
+    let version = bedrock_version_numbers(version_name).ok_or_else(unknown)?;
+    let (latest_name, _) = BEDROCK_VERSIONS.last().expect("the table isn't empty");
+    if version > bedrock_version_numbers(latest_name).unwrap() {
+        // could be anything, the table doesn't go that far
+        return Err(unknown());
+    }
+    BEDROCK_VERSIONS
+        .iter()
+        .rev()
+        .find(|(name, _)| {
+            let release = bedrock_version_numbers(name).unwrap();
+            release[..2] == version[..2] && release <= version
+        })
+        .map(|&(_, protocol)| protocol)
+        .ok_or_else(unknown)
+}
+
+/// The Bedrock releases that introduced `protocol`, oldest first. Empty if
+/// it isn't known.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::bedrock_version_names;
+///
+/// assert_eq!(bedrock_version_names(594), ["1.20.10"]);
+/// assert!(bedrock_version_names(12345).is_empty());
+/// ```
+pub fn bedrock_version_names(protocol: i32) -> Vec<&'static str> {
+    BEDROCK_VERSIONS
+        .iter()
+        .filter(|&&(_, release_protocol)| release_protocol == protocol)
+        .map(|&(name, _)| name)
+        .collect()
+}
+
+/// Whether a Bedrock server's reported version name and protocol go
+/// together, `None` if the name isn't one this crate knows. Real servers
+/// always agree with themselves, so a mismatch means the listing was made
+/// up or edited.
+///
+/// # Examples
+///
+/// ```
+/// use minecraft_utilities::bedrock_version_consistent;
+///
+/// assert_eq!(bedrock_version_consistent("1.20.12", 594), Some(true));
+/// assert_eq!(bedrock_version_consistent("1.20.12", 630), Some(false));
+/// assert_eq!(bedrock_version_consistent("Spoofed!", 594), None);
+/// ```
+pub fn bedrock_version_consistent(version_name: &str, protocol: i32) -> Option<bool> {
+    parse_bedrock_version(version_name)
+        .ok()
+        .map(|expected| expected == protocol)
+}
+
+/// The numbers of a version name like `1.20.12`, with missing ones as 0.
+/// Anything after the numbers (` beta`, `-rc1`) is ignored.
+fn bedrock_version_numbers(version_name: &str) -> Option<[u32; 3]> {
+    let version_name = version_name.trim().trim_start_matches(['v', 'V']);
+    let end = version_name
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(version_name.len());
+    let mut numbers = [0; 3];
+    let mut parts = version_name[..end].split('.');
+    for number in &mut numbers {
+        match parts.next() {
+            Some(part) => *number = part.parse().ok()?,
+            None => break,
+        }
+    }
+    // at least a major and minor version
+    (numbers != [0; 3] && version_name[..end].contains('.')).then_some(numbers)
+}
+
+/// Bedrock releases that changed the protocol, oldest first.
+static BEDROCK_VERSIONS: &[(&str, i32)] = &[
+    ("1.7.0", 291),
+    ("1.8.0", 313),
+    ("1.9.0", 332),
+    ("1.10.0", 340),
+    ("1.11.0", 354),
+    ("1.12.0", 361),
+    ("1.13.0", 388),
+    ("1.14.0", 389),
+    ("1.14.60", 390),
+    ("1.16.0", 407),
+    ("1.16.20", 408),
+    ("1.16.100", 419),
+    ("1.16.200", 422),
+    ("1.16.210", 428),
+    ("1.16.220", 431),
+    ("1.17.0", 440),
+    ("1.17.10", 448),
+    ("1.17.30", 465),
+    ("1.17.40", 471),
+    ("1.18.0", 475),
+    ("1.18.10", 486),
+    ("1.18.30", 503),
+    ("1.19.0", 527),
+    ("1.19.10", 534),
+    ("1.19.20", 544),
+    ("1.19.21", 545),
+    ("1.19.30", 554),
+    ("1.19.40", 557),
+    ("1.19.50", 560),
+    ("1.19.60", 567),
+    ("1.19.63", 568),
+    ("1.19.70", 575),
+    ("1.19.80", 582),
+    ("1.20.0", 589),
+    ("1.20.10", 594),
+    ("1.20.30", 618),
+    ("1.20.40", 622),
+    ("1.20.50", 630),
+    ("1.20.60", 649),
+    ("1.20.70", 662),
+    ("1.20.80", 671),
+    ("1.21.0", 685),
+    ("1.21.2", 686),
+    ("1.21.20", 712),
+    ("1.21.30", 729),
+    ("1.21.40", 748),
+    ("1.21.50", 766),
+    ("1.21.60", 776),
+    ("1.21.70", 786),
+    ("1.21.80", 800),
+    ("1.21.90", 818),
+    ("1.21.93", 819),
+    ("1.21.100", 827),
+    ("1.21.111", 844),
+];
@@ -0,0 +1,256 @@
+//! Pings local stand-ins for Bedrock servers over RakNet's unconnected
+//! ping/pong.
+
+use std::{net::SocketAddr, time::Duration};
+
+use futures::future::join_all;
+use minecraft_utilities::{
+    bedrock_version_names, parse_bedrock_version, BedrockPinger, BedrockServerEdition,
+    BedrockServerGamemode, Error, Phase, PingBedrock, ServerAddress,
+};
+use tokio::{net::UdpSocket, sync::mpsc};
+
+const MAGIC: [u8; 16] = [
+    0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+
+fn advertisement(motd: &str) -> String {
+    format!(
+        "MCPE;{motd};594;1.20.12;2;10;13253860892328930865;Bedrock level;Survival;1;19132;19133;0;"
+    )
+}
+
+/// Answers pings with `advertisement` after ignoring the first `drop`
+/// ones, and sends back the client GUID of each ping it answers.
+async fn start_server(
+    bind: &str,
+    advertisement: String,
+    mut drop: usize,
+) -> (SocketAddr, mpsc::UnboundedReceiver<i64>) {
+    let socket = UdpSocket::bind(bind).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    let (guids, guids_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut buf = [0; 1500];
+        loop {
+            let (len, from) = socket.recv_from(&mut buf).await.unwrap();
+            assert_eq!(len, 33);
+            assert_eq!(buf[0], 0x01);
+            assert_eq!(buf[9..25], MAGIC);
+            if drop > 0 {
+                drop -= 1;
+                continue;
+            }
+            let _ = guids.send(i64::from_be_bytes(buf[25..33].try_into().unwrap()));
+
+            let mut pong = vec![0x1c];
+            pong.extend(&buf[1..9]);
+            pong.extend(0x1234_5678_i64.to_be_bytes());
+            pong.extend(MAGIC);
+            pong.extend((advertisement.len() as u16).to_be_bytes());
+            pong.extend(advertisement.as_bytes());
+            socket.send_to(&pong, from).await.unwrap();
+        }
+    });
+    (addr, guids_rx)
+}
+
+#[tokio::test]
+async fn pings_a_server() {
+    let (addr, _) = start_server("127.0.0.1:0", advertisement("Dedicated Server"), 0).await;
+    let ping = PingBedrock::ping(&ServerAddress::from(addr)).await.unwrap();
+    assert_eq!(ping.edition, BedrockServerEdition::BedrockEdition);
+    assert_eq!(ping.motd, "Dedicated Server\nBedrock level");
+    assert_eq!(ping.protocol_version, 594);
+    assert_eq!(ping.version_name, "1.20.12");
+    assert_eq!((ping.player_count, ping.max_player_count), (2, 10));
+    assert_eq!(ping.game_mode, BedrockServerGamemode::Survival);
+    assert_eq!((ping.port_v4, ping.port_v6), (19132, 19133));
+    assert_eq!(ping.extra, ["0"]);
+}
+
+#[tokio::test]
+async fn pings_over_ipv6() {
+    let (addr, _) = start_server("[::1]:0", advertisement("IPv6"), 0).await;
+    let ping = PingBedrock::ping(&ServerAddress::from(addr)).await.unwrap();
+    assert!(ping.motd.starts_with("IPv6"));
+
+    // typed in as text, and printed so it parses back
+    let parsed = ServerAddress::try_from(format!("[::1]:{}", addr.port()).as_str()).unwrap();
+    assert_eq!((parsed.host.as_str(), parsed.port), ("::1", addr.port()));
+    assert_eq!(parsed.to_string(), format!("[::1]:{}", addr.port()));
+    let ping = PingBedrock::ping(&parsed).await.unwrap();
+    assert!(ping.motd.starts_with("IPv6"));
+    let reparsed = ServerAddress::try_from(parsed.to_string().as_str()).unwrap();
+    assert_eq!(SocketAddr::try_from(reparsed).unwrap(), addr);
+    let bare = ServerAddress::try_from("::1").unwrap();
+    assert_eq!((bare.host.as_str(), bare.port), ("::1", 25565));
+    for bad in ["[::1", "[::1]19132", "[::1]:port", "[]:19132"] {
+        assert!(ServerAddress::try_from(bad).is_err(), "{bad}");
+    }
+
+    // an IPv6 socket reaches IPv4 servers too
+    let (addr, _) = start_server("127.0.0.1:0", advertisement("IPv4"), 0).await;
+    let pinger = BedrockPinger::bind("[::]:0").await.unwrap();
+    assert!(pinger.ping(addr).await.unwrap().motd.starts_with("IPv4"));
+}
+
+#[tokio::test]
+async fn retries_lost_pings() {
+    let (addr, _) = start_server("127.0.0.1:0", advertisement("Lossy"), 2).await;
+    let pinger = BedrockPinger::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .with_timeout(Duration::from_millis(100))
+        .with_attempts(3);
+    assert!(pinger.ping(addr).await.unwrap().motd.starts_with("Lossy"));
+}
+
+#[tokio::test]
+async fn times_out() {
+    let (addr, _) = start_server("127.0.0.1:0", advertisement("Silent"), usize::MAX).await;
+    let pinger = BedrockPinger::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .with_timeout(Duration::from_millis(50))
+        .with_attempts(2);
+    let err = pinger.ping(addr).await.unwrap_err();
+    assert!(matches!(err, Error::Timeout(Phase::BedrockPing)));
+}
+
+#[tokio::test]
+async fn sends_the_client_guid() {
+    let (addr, mut guids) = start_server("127.0.0.1:0", advertisement("GUID"), 0).await;
+    let pinger = BedrockPinger::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .with_guid(-42);
+    pinger.ping(addr).await.unwrap();
+    assert_eq!(guids.recv().await.unwrap(), -42);
+}
+
+#[tokio::test]
+async fn shares_one_socket() {
+    let mut servers = vec![];
+    for i in 0..50 {
+        let (addr, _) = start_server("127.0.0.1:0", advertisement(&format!("Server {i}")), 0).await;
+        servers.push(addr);
+    }
+    let pinger = BedrockPinger::bind("127.0.0.1:0").await.unwrap();
+    let pings = join_all(servers.iter().map(|&addr| {
+        let pinger = pinger.clone();
+        async move { pinger.ping(addr).await }
+    }))
+    .await;
+    for (i, ping) in pings.into_iter().enumerate() {
+        assert_eq!(ping.unwrap().motd, format!("Server {i}\nBedrock level"));
+    }
+}
+
+#[test]
+fn parses_short_advertisements() {
+    // Nukkit and old servers stop after the player counts
+    let ping = PingBedrock::parse("MCPE;Nukkit Server;137;1.2.0;3;50", 0).unwrap();
+    assert_eq!(ping.motd, "Nukkit Server");
+    assert_eq!(ping.protocol_version, 137);
+    assert_eq!(ping.version_name, "1.2.0");
+    assert_eq!((ping.player_count, ping.max_player_count), (3, 50));
+    assert_eq!(ping.server_unique_id, "");
+    assert_eq!(ping.game_mode, BedrockServerGamemode::Unknown);
+    assert_eq!(ping.game_mode_numeric, -1);
+    assert!(ping.extra.is_empty());
+
+    let ping = PingBedrock::parse("MCEE;Classroom;", 0).unwrap();
+    assert_eq!(ping.edition, BedrockServerEdition::EducationEdition);
+    assert_eq!(ping.motd, "Classroom");
+    assert_eq!(ping.protocol_version, -1);
+
+    assert!(PingBedrock::parse("garbage", 0).is_err());
+    assert!(PingBedrock::parse("", 0).is_err());
+}
+
+#[test]
+fn keeps_extra_fields() {
+    let raw = "MCPE;Extra;594;1.20.12;2;10;1;world;creative;1;19132;19133;0;1;";
+    let ping = PingBedrock::parse(raw, 0).unwrap();
+    assert_eq!(ping.game_mode, BedrockServerGamemode::Creative);
+    assert_eq!((ping.port_v4, ping.port_v6), (19132, 19133));
+    assert_eq!(ping.extra, ["0", "1"]);
+    assert_eq!(ping.raw, raw);
+}
+
+#[test]
+fn recovers_semicolons() {
+    let ping = PingBedrock::parse(
+        "MCPE;Season 2;10;fun;594;1.20.12;2;10;42;my;world;Adventure;2;19132;19133;",
+        0,
+    )
+    .unwrap();
+    assert_eq!(ping.motd, "Season 2;10;fun\nmy;world");
+    assert_eq!(ping.protocol_version, 594);
+    assert_eq!((ping.player_count, ping.max_player_count), (2, 10));
+    assert_eq!(ping.server_unique_id, "42");
+    assert_eq!(ping.game_mode, BedrockServerGamemode::Adventure);
+    assert_eq!(ping.game_mode_numeric, 2);
+    assert_eq!(ping.port_v6, 19133);
+    assert!(ping.extra.is_empty());
+}
+
+#[test]
+fn formats_the_motd() {
+    let ping = PingBedrock::parse(
+        "MCPE;\u{a7}6\u{a7}lGold;594;1.20.12;2;10;42;\u{a7}oLobby;Survival;1;19132;19133;",
+        0,
+    )
+    .unwrap();
+    let component = ping.motd_component();
+    assert_eq!(component.to_plain(), "Gold\nLobby");
+    assert_eq!(component.to_legacy(), ping.motd);
+}
+
+#[test]
+fn parse_survives_random_advertisements() {
+    // xorshift64, so failures can be reproduced
+    let mut state = 0x5eed_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let pieces = [
+        "MCPE", ";", "1", "-5", "1.20", "Survival", "\u{a7}a", " ", "x", "",
+    ];
+    for _ in 0..10_000 {
+        let len = next() % 30;
+        let advertisement: String = (0..len)
+            .map(|_| pieces[(next() % pieces.len() as u64) as usize])
+            .collect();
+        if let Ok(ping) = PingBedrock::parse(&advertisement, 0) {
+            assert_eq!(ping.raw, advertisement);
+        }
+    }
+}
+
+#[test]
+fn checks_versions() {
+    let ping = PingBedrock::parse(&advertisement("Real"), 0).unwrap();
+    assert_eq!(ping.version_consistent(), Some(true));
+
+    let spoofed = "MCPE;Spoofed;594;1.21.50;2;10;1;world;Survival;1;19132;19133;";
+    let ping = PingBedrock::parse(spoofed, 0).unwrap();
+    assert_eq!(ping.version_consistent(), Some(false));
+    assert_eq!(bedrock_version_names(766), ["1.21.50"]);
+
+    // hotfixes that changed the protocol
+    assert_eq!(parse_bedrock_version("1.19.22").unwrap(), 545);
+    assert_eq!(parse_bedrock_version("1.21.3").unwrap(), 686);
+    assert_eq!(parse_bedrock_version("1.21.1").unwrap(), 685);
+
+    // too old, too new or not a version at all
+    for name in ["1.2.0", "1.15.0", "9.0.0", "1", "", "latest"] {
+        assert!(parse_bedrock_version(name).is_err(), "{name}");
+    }
+    let ping = PingBedrock::parse("MCPE;Nukkit Server;137;1.2.0;3;50", 0).unwrap();
+    assert_eq!(ping.version_consistent(), None);
+}
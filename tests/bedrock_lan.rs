@@ -0,0 +1,155 @@
+//! Finds `BedrockLanAdvertiser`s with `BedrockLan` over loopback.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::StreamExt;
+use minecraft_utilities::{
+    BedrockLan, BedrockLanAdvertiser, BedrockPinger, BedrockServerGamemode, PingBedrock,
+    ServerAddress,
+};
+use tokio::net::UdpSocket;
+
+fn advertisement(motd: &str) -> String {
+    format!("MCPE;{motd};594;1.20.12;2;10;42;Bedrock level;Survival;1;19132;19133;0;")
+}
+
+async fn start_advertiser(motd: &str, guid: i64) -> std::net::SocketAddr {
+    let advertiser = BedrockLanAdvertiser::bind("127.0.0.1:0", advertisement(motd))
+        .await
+        .unwrap()
+        .with_guid(guid);
+    let addr = advertiser.local_addr().unwrap();
+    tokio::spawn(advertiser.run());
+    addr
+}
+
+async fn lan(targets: Vec<std::net::SocketAddr>) -> BedrockLan {
+    BedrockLan::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .with_targets(targets)
+        .with_interval(Duration::from_millis(50))
+}
+
+#[tokio::test]
+async fn discovers_an_advertiser() {
+    let addr = start_advertiser("LAN World", 7).await;
+    let mut servers = lan(vec![addr]).await.discover();
+    let server = tokio::time::timeout(Duration::from_secs(5), servers.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(server.addr, addr);
+    assert_eq!(server.server_guid, 7);
+    assert_eq!(server.ping.motd, "LAN World\nBedrock level");
+    assert_eq!(server.ping.game_mode, BedrockServerGamemode::Survival);
+    assert!(server.ping.response_time >= 0);
+}
+
+#[tokio::test]
+async fn streams_every_answer() {
+    let a = start_advertiser("A", 1).await;
+    let b = start_advertiser("B", 2).await;
+    let servers = lan(vec![a, b]).await.discover();
+
+    // two answers a round, several rounds
+    let mut seen = HashMap::new();
+    let mut servers = servers.take(6);
+    while let Some(server) = tokio::time::timeout(Duration::from_secs(5), servers.next())
+        .await
+        .unwrap()
+    {
+        let server = server.unwrap();
+        *seen.entry(server.server_guid).or_insert(0) += 1;
+    }
+    assert_eq!(seen.len(), 2);
+    assert!(seen.values().all(|&count| count >= 2), "{seen:?}");
+}
+
+#[tokio::test]
+async fn skips_garbage() {
+    let garbage = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let garbage_addr = garbage.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0; 1500];
+        loop {
+            let (_, from) = garbage.recv_from(&mut buf).await.unwrap();
+            garbage.send_to(b"not a pong", from).await.unwrap();
+        }
+    });
+    let addr = start_advertiser("Real", 3).await;
+
+    let mut servers = lan(vec![garbage_addr, addr]).await.discover();
+    for _ in 0..3 {
+        let server = tokio::time::timeout(Duration::from_secs(5), servers.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(server.addr, addr);
+    }
+}
+
+#[tokio::test]
+async fn advertiser_answers_pingers() {
+    let pings = Arc::new(AtomicU32::new(0));
+    let advertiser = BedrockLanAdvertiser::bind("127.0.0.1:0", {
+        let pings = pings.clone();
+        move || advertisement(&format!("Ping {}", pings.fetch_add(1, Ordering::Relaxed)))
+    })
+    .await
+    .unwrap();
+    let addr = advertiser.local_addr().unwrap();
+    tokio::spawn(advertiser.run());
+
+    let ping = PingBedrock::ping(&ServerAddress::from(addr)).await.unwrap();
+    assert_eq!(ping.motd, "Ping 0\nBedrock level");
+    let pinger = BedrockPinger::bind("127.0.0.1:0").await.unwrap();
+    assert_eq!(
+        pinger.ping(addr).await.unwrap().motd,
+        "Ping 1\nBedrock level"
+    );
+}
+
+#[tokio::test]
+async fn survives_any_pong_time() {
+    const MAGIC: [u8; 16] = [
+        0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56,
+        0x78,
+    ];
+    // answers every ping with a pong from the far past or future
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0; 1500];
+        for time in [i64::MIN, i64::MAX].into_iter().cycle() {
+            let (_, from) = socket.recv_from(&mut buf).await.unwrap();
+            let advertisement = advertisement("Odd clock");
+            let mut pong = vec![0x1c];
+            pong.extend(time.to_be_bytes());
+            pong.extend(1_i64.to_be_bytes());
+            pong.extend(MAGIC);
+            pong.extend((advertisement.len() as u16).to_be_bytes());
+            pong.extend(advertisement.as_bytes());
+            socket.send_to(&pong, from).await.unwrap();
+        }
+    });
+
+    let mut servers = lan(vec![addr]).await.discover();
+    for _ in 0..2 {
+        let server = tokio::time::timeout(Duration::from_secs(5), servers.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(server.ping.response_time >= 0);
+    }
+}
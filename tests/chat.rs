@@ -0,0 +1,136 @@
+//! Renders chat components parsed from JSON and legacy `§` codes in every
+//! output format.
+
+use minecraft_utilities::{strip_legacy_codes, ChatColor, ChatComponent, NamedColor};
+
+#[test]
+fn hex_colors() {
+    let legacy = ChatComponent::from_legacy("§x§1§2§a§B§5§6Hex §lbold");
+    assert_eq!(legacy.to_plain(), "Hex bold");
+    assert_eq!(legacy.extra[0].style.color, Some(ChatColor::Hex(0x12ab56)));
+    // a format code keeps the hex color
+    assert_eq!(legacy.extra[1].style.color, Some(ChatColor::Hex(0x12ab56)));
+    assert_eq!(legacy.extra[1].style.bold, Some(true));
+    assert_eq!(
+        legacy.to_ansi(),
+        "\x1b[0m\x1b[38;2;18;171;86mHex \x1b[0m\x1b[38;2;18;171;86m\x1b[1mbold\x1b[0m"
+    );
+    assert_eq!(
+        legacy.to_html(),
+        "<span style=\"color:#12ab56;\">Hex </span>\
+         <span style=\"color:#12ab56;font-weight:bold;\">bold</span>"
+    );
+
+    // too few digits isn't a hex color, so the `§x` stays as text
+    let short = ChatComponent::from_legacy("§x§1§2Hex");
+    assert_eq!(short.to_plain(), "§xHex");
+    assert_eq!(
+        short.extra.last().unwrap().style.color,
+        Some(ChatColor::Named(NamedColor::DarkGreen))
+    );
+
+    // the nearest named color in legacy output
+    let json = ChatComponent::parse(r##"{"text":"close to red","color":"#ff5050"}"##);
+    assert_eq!(json.to_legacy(), "§cclose to red");
+    assert_eq!(json.to_json()["color"], "#ff5050");
+}
+
+#[test]
+fn translation_arguments() {
+    let chat = ChatComponent::parse(
+        r#"{"translate":"chat.type.text","fallback":"<%s> %s","with":["Steve","hello"]}"#,
+    );
+    assert_eq!(chat.to_plain(), "<Steve> hello");
+
+    let positional = ChatComponent::parse(
+        r#"{"translate":"x","fallback":"%2$s before %1$s, 100%% and %s","with":["a","b"]}"#,
+    );
+    assert_eq!(positional.to_plain(), "b before a, 100% and a");
+
+    // without a fallback the key is the format, and missing arguments are empty
+    let missing = ChatComponent::parse(r#"{"translate":"%s and %3$s","with":["one"]}"#);
+    assert_eq!(missing.to_plain(), "one and ");
+    let trailing = ChatComponent::parse(r#"{"translate":"50%"}"#);
+    assert_eq!(trailing.to_plain(), "50%");
+}
+
+#[test]
+fn translation_arguments_keep_their_style() {
+    let chat = ChatComponent::parse(
+        r#"{
+            "translate": "death.attack.player",
+            "fallback": "%s was slain by %s",
+            "color": "gray",
+            "with": [
+                {"text": "Alex"},
+                {"text": "Steve", "color": "yellow", "bold": true}
+            ]
+        }"#,
+    );
+    assert_eq!(chat.to_plain(), "Alex was slain by Steve");
+    assert_eq!(chat.to_legacy(), "§7Alex was slain by §e§lSteve");
+    assert_eq!(
+        chat.to_html(),
+        "<span style=\"color:#aaaaaa;\">Alex</span>\
+         <span style=\"color:#aaaaaa;\"> was slain by </span>\
+         <span style=\"color:#ffff55;font-weight:bold;\">Steve</span>"
+    );
+    assert_eq!(
+        chat.to_ansi(),
+        "\x1b[0m\x1b[37mAlex\x1b[0m\x1b[37m was slain by \x1b[0m\x1b[93m\x1b[1mSteve\x1b[0m"
+    );
+}
+
+#[test]
+fn escapes_html() {
+    let chat = ChatComponent::parse(r#"{"text":"<b>\"Tom\" & 'Jerry'</b>\nnext","italic":true}"#);
+    assert_eq!(
+        chat.to_html(),
+        "<span style=\"font-style:italic;\">\
+         &lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;<br>next</span>"
+    );
+    assert_eq!(ChatComponent::text("<script>").to_html(), "&lt;script&gt;");
+}
+
+#[test]
+fn legacy_round_trips() {
+    for legacy in [
+        "plain",
+        "§aA §lMinecraft§r Server",
+        "§c§lRed bold §9blue",
+        "§6§ngold underlined §6gold",
+        "§kmagic§r normal §m§ostruck italic",
+        "keep §z and a trailing §",
+    ] {
+        let chat = ChatComponent::from_legacy(legacy);
+        assert_eq!(chat.to_legacy(), legacy);
+        assert_eq!(ChatComponent::from_legacy(&chat.to_legacy()), chat);
+    }
+
+    // through JSON and back
+    let chat = ChatComponent::from_legacy("§eYellow §lbold§r and §x§0§0§f§f§f§faqua");
+    let json = ChatComponent::from_json(&chat.to_json());
+    assert_eq!(json, chat);
+    assert_eq!(json.to_legacy(), "§eYellow §lbold§r and §baqua");
+}
+
+#[test]
+fn stripping_agrees_with_parsing() {
+    for legacy in [
+        "§6Gold §land bold",
+        "§x§f§f§0§0§0§0Red",
+        "§X§F§F§0§0§0§0Upper",
+        "§x§fshort",
+        "unknown §z and §§a and trailing §",
+        "§K§L§M§N§O§R§A",
+        "no codes",
+    ] {
+        assert_eq!(
+            strip_legacy_codes(legacy),
+            ChatComponent::from_legacy(legacy).to_plain(),
+            "{legacy}"
+        );
+    }
+    assert_eq!(strip_legacy_codes("50§ off §zcode"), "50§ off §zcode");
+    assert_eq!(strip_legacy_codes("§§aGreen"), "§Green");
+}
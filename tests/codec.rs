@@ -0,0 +1,235 @@
+//! Frames packets with `MinecraftCodec` the way they arrive off the network:
+//! in pieces, compressed, encrypted and sometimes lying about their size.
+
+use std::io::Read;
+
+use bytes::BytesMut;
+use flate2::{read::ZlibEncoder, Compression};
+use futures::{SinkExt, StreamExt};
+use minecraft_utilities::{
+    Aes128Cfb8, Error, McWrite, MinecraftCodec, MinecraftPacket, VarInt, ZlibCompression,
+};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+const SECRET: [u8; 16] = *b"sixteen byte key";
+
+fn packet(packet_id: i32, len: usize) -> MinecraftPacket {
+    MinecraftPacket {
+        packet_id,
+        buffer: (0..len).map(|i| i as u8).collect(),
+    }
+}
+
+fn compressed(threshold: usize) -> MinecraftCodec {
+    let mut codec = MinecraftCodec::new();
+    codec.set_compression(Some(Box::new(ZlibCompression::new(threshold))));
+    codec
+}
+
+fn encrypted(mut codec: MinecraftCodec) -> MinecraftCodec {
+    codec.set_encryption(Some(Box::new(Aes128Cfb8::new(&SECRET))));
+    codec
+}
+
+fn encode(codec: &mut MinecraftCodec, packets: &[MinecraftPacket]) -> BytesMut {
+    let mut bytes = BytesMut::new();
+    for packet in packets {
+        codec.encode(packet.clone(), &mut bytes).unwrap();
+    }
+    bytes
+}
+
+/// Feed `bytes` to `codec` `chunk` bytes at a time, decoding everything it
+/// can after each chunk like `Framed` does.
+fn decode_in_chunks(
+    codec: &mut MinecraftCodec,
+    bytes: &[u8],
+    chunk: usize,
+) -> Vec<MinecraftPacket> {
+    let mut src = BytesMut::new();
+    let mut res = vec![];
+    for chunk in bytes.chunks(chunk) {
+        src.extend_from_slice(chunk);
+        while let Some(packet) = codec.decode(&mut src).unwrap() {
+            res.push(packet);
+        }
+    }
+    assert!(src.is_empty());
+    res
+}
+
+fn varint(val: i32) -> Vec<u8> {
+    let mut res = vec![];
+    VarInt(val).write_to(&mut res).unwrap();
+    res
+}
+
+/// A frame with a body that is exactly `body`.
+fn frame(body: &[u8]) -> BytesMut {
+    let mut res = BytesMut::from(varint(body.len() as i32).as_slice());
+    res.extend_from_slice(body);
+    res
+}
+
+fn too_large(err: &std::io::Error) -> Option<(usize, usize)> {
+    match err.get_ref()?.downcast_ref::<Error>()? {
+        Error::PacketTooLarge { length, limit } => Some((*length, *limit)),
+        _ => None,
+    }
+}
+
+#[test]
+fn reassembles_split_frames() {
+    let packets = [packet(0x00, 0), packet(0x7f, 200), packet(0x1234, 3)];
+    let bytes = encode(&mut MinecraftCodec::new(), &packets);
+    for chunk in [1, 2, 3, 7, bytes.len()] {
+        let decoded = decode_in_chunks(&mut MinecraftCodec::new(), &bytes, chunk);
+        assert_eq!(decoded, packets, "{chunk} byte chunks");
+    }
+
+    // a length prefix cut in half
+    let mut src = BytesMut::from(&bytes[2..3]);
+    assert!(MinecraftCodec::new().decode(&mut src).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn reassembles_frames_split_across_reads() {
+    // a tiny pipe, so every packet takes several reads
+    let (client, server) = tokio::io::duplex(5);
+    let mut client = Framed::new(client, encrypted(compressed(16)));
+    let mut server = Framed::new(server, encrypted(compressed(16)));
+    let packets = [packet(0x01, 10), packet(0x02, 300), packet(0x03, 0)];
+
+    let sent = packets.clone();
+    tokio::spawn(async move {
+        for packet in sent {
+            client.send(packet).await.unwrap();
+        }
+    });
+    for packet in packets {
+        assert_eq!(server.next().await.unwrap().unwrap(), packet);
+    }
+}
+
+#[test]
+fn decrypts_each_byte_once() {
+    let packets = [packet(0x01, 20), packet(0x02, 1), packet(0x03, 150)];
+    let bytes = encode(&mut encrypted(MinecraftCodec::new()), &packets);
+    assert_ne!(bytes, encode(&mut MinecraftCodec::new(), &packets));
+
+    // chunks that end mid-packet leave decrypted bytes in the buffer
+    for chunk in [1, 5, 30, bytes.len()] {
+        let decoded = decode_in_chunks(&mut encrypted(MinecraftCodec::new()), &bytes, chunk);
+        assert_eq!(decoded, packets, "{chunk} byte chunks");
+    }
+}
+
+#[test]
+fn encrypts_only_after_switching() {
+    let plain = [packet(0x01, 40)];
+    let secret = [packet(0x02, 40), packet(0x03, 2)];
+    let mut sender = MinecraftCodec::new();
+    let mut bytes = encode(&mut sender, &plain);
+    sender.set_encryption(Some(Box::new(Aes128Cfb8::new(&SECRET))));
+    let encrypted_bytes = encode(&mut sender, &secret);
+
+    let mut receiver = MinecraftCodec::new();
+    // the end of the plaintext packet and the start of the encrypted ones
+    // arrive together, after the plaintext was seen
+    let split = bytes.len() - 10;
+    let mut src = bytes.split_to(split);
+    assert!(receiver.decode(&mut src).unwrap().is_none());
+    src.extend_from_slice(&bytes);
+    assert_eq!(receiver.decode(&mut src).unwrap(), Some(plain[0].clone()));
+    assert!(receiver.decode(&mut src).unwrap().is_none());
+
+    receiver.set_encryption(Some(Box::new(Aes128Cfb8::new(&SECRET))));
+    let (first, rest) = encrypted_bytes.split_at(20);
+    src.extend_from_slice(first);
+    assert!(receiver.decode(&mut src).unwrap().is_none());
+    src.extend_from_slice(rest);
+    assert_eq!(receiver.decode(&mut src).unwrap(), Some(secret[0].clone()));
+    assert_eq!(receiver.decode(&mut src).unwrap(), Some(secret[1].clone()));
+    assert!(src.is_empty());
+}
+
+#[test]
+fn refuses_packets_over_the_sanity_limit() {
+    // refused from the length prefix alone
+    let mut src = BytesMut::from(varint(101).as_slice());
+    let err = MinecraftCodec::with_sanity_limit(100)
+        .decode(&mut src)
+        .unwrap_err();
+    assert_eq!(too_large(&err), Some((101, 100)));
+
+    let mut src = encode(&mut MinecraftCodec::new(), &[packet(0x01, 99)]);
+    assert!(MinecraftCodec::with_sanity_limit(100)
+        .decode(&mut src)
+        .unwrap()
+        .is_some());
+
+    // a small compressed packet claiming to inflate past the limit
+    let mut body = varint(1_000_000);
+    body.extend([0x78, 0x9c, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01]);
+    let mut codec = MinecraftCodec::with_sanity_limit(1000);
+    codec.set_compression(Some(Box::new(ZlibCompression::new(64))));
+    let err = codec.decode(&mut frame(&body)).unwrap_err();
+    assert_eq!(too_large(&err), Some((1_000_000, 1000)));
+
+    let err = MinecraftCodec::new().decode(&mut frame(&[])).unwrap_err();
+    assert_eq!(too_large(&err), None);
+}
+
+#[test]
+fn refuses_wrong_decompressed_sizes() {
+    let data = [0x05; 100];
+    let mut zlib = vec![];
+    ZlibEncoder::new(&data[..], Compression::default())
+        .read_to_end(&mut zlib)
+        .unwrap();
+
+    for declared in [99, 101, 1, 5000] {
+        let mut body = varint(declared);
+        body.extend(&zlib);
+        let err = compressed(64).decode(&mut frame(&body)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{declared}");
+    }
+
+    let mut body = varint(100);
+    body.extend(&zlib);
+    let packet = compressed(64).decode(&mut frame(&body)).unwrap().unwrap();
+    assert_eq!(packet.packet_id, 0x05);
+    assert_eq!(packet.buffer, [0x05; 99]);
+
+    // not zlib at all
+    let mut body = varint(100);
+    body.extend([0xde, 0xad, 0xbe, 0xef]);
+    assert!(compressed(64).decode(&mut frame(&body)).is_err());
+}
+
+#[test]
+fn compresses_from_the_threshold() {
+    let threshold = 64;
+    // the packet id takes one byte, so 63 bytes of data reach the threshold
+    for (len, compressed_on_wire) in [(0, false), (62, false), (63, true), (1000, true)] {
+        let packet = packet(0x10, len);
+        let bytes = encode(&mut compressed(threshold), std::slice::from_ref(&packet));
+
+        let mut src = bytes.clone();
+        let _frame_len = src.split_to(if bytes[0] & 0x80 == 0 { 1 } else { 2 });
+        // the data length is 0 for packets sent as is
+        if compressed_on_wire {
+            assert_ne!(src[0], 0, "{len}");
+        } else {
+            assert_eq!(src[0], 0, "{len}");
+            assert_eq!(src[1], 0x10);
+            assert_eq!(&src[2..], packet.buffer.as_slice());
+        }
+
+        let decoded = decode_in_chunks(&mut compressed(threshold), &bytes, 3);
+        assert_eq!(decoded, std::slice::from_ref(&packet), "{len}");
+        // both sides need to agree compression is on
+        let decoded = MinecraftCodec::new().decode(&mut bytes.clone()).unwrap();
+        assert_ne!(decoded, Some(packet));
+    }
+}
@@ -0,0 +1,77 @@
+//! Runs the `rcon` subcommand of the binary against a local `RconServer`.
+
+use std::process::Stdio;
+
+use minecraft_utilities::RconServer;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+async fn start_server() -> u16 {
+    let server = RconServer::bind("127.0.0.1:0", "hunter2", |command: String| async move {
+        format!("§aYou said§r {command}")
+    })
+    .await
+    .unwrap();
+    let port = server.local_addr().unwrap().port();
+    tokio::spawn(server.run());
+    port
+}
+
+fn console(port: u16, password: &str) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_minecraft_utilities"));
+    command
+        .args(["rcon", "-H", "127.0.0.1", "-P", &port.to_string()])
+        .env("MCRCON_PASS", password)
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    command
+}
+
+#[tokio::test]
+async fn runs_commands_from_arguments() {
+    let port = start_server().await;
+    let output = console(port, "hunter2")
+        .args(["list", "say hi"])
+        .output()
+        .await
+        .unwrap();
+    assert!(output.status.success());
+    // stdout isn't a terminal, so no colors
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "You said list\nYou said say hi\n"
+    );
+}
+
+#[tokio::test]
+async fn runs_commands_from_stdin() {
+    let port = start_server().await;
+    let mut child = console(port, "hunter2")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"seed\n\ntime query day\n").await.unwrap();
+    drop(stdin);
+
+    let output = child.wait_with_output().await.unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "You said seed\nYou said time query day\n"
+    );
+}
+
+#[tokio::test]
+async fn fails_on_wrong_password() {
+    let port = start_server().await;
+    let output = console(port, "wrong").arg("list").output().await.unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[tokio::test]
+async fn rejects_unknown_options() {
+    let output = console(1, "hunter2").arg("--bogus").output().await.unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
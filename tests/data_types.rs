@@ -0,0 +1,198 @@
+//! Reads and writes the protocol's primitive types at their edges.
+
+use minecraft_utilities::{
+    read_bounded_string, Angle, DataTypeError, McRead, McWrite, Position, VarInt, VarLong,
+    MAX_STRING_LENGTH,
+};
+
+fn encode(value: &impl McWrite) -> Vec<u8> {
+    let mut bytes = vec![];
+    value.write_to(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn var_int_boundaries() {
+    for (value, bytes) in [
+        (0, &[0x00][..]),
+        (1, &[0x01]),
+        (127, &[0x7f]),
+        (128, &[0x80, 0x01]),
+        (255, &[0xff, 0x01]),
+        (16383, &[0xff, 0x7f]),
+        (16384, &[0x80, 0x80, 0x01]),
+        (2097151, &[0xff, 0xff, 0x7f]),
+        (2097152, &[0x80, 0x80, 0x80, 0x01]),
+        (i32::MAX, &[0xff, 0xff, 0xff, 0xff, 0x07]),
+        (-1, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+        (i32::MIN, &[0x80, 0x80, 0x80, 0x80, 0x08]),
+    ] {
+        assert_eq!(encode(&VarInt(value)), bytes, "{value}");
+        assert_eq!(VarInt::len(value), bytes.len(), "{value}");
+        assert_eq!(VarInt::read_from(&mut &bytes[..]).unwrap(), VarInt(value));
+    }
+}
+
+#[test]
+fn var_long_boundaries() {
+    for (value, bytes) in [
+        (0, &[0x00][..]),
+        (127, &[0x7f]),
+        (128, &[0x80, 0x01]),
+        (i64::from(i32::MAX), &[0xff, 0xff, 0xff, 0xff, 0x07]),
+        (
+            i64::MAX,
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+        ),
+        (
+            -1,
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+        ),
+        (
+            i64::MIN,
+            &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01],
+        ),
+    ] {
+        assert_eq!(encode(&VarLong(value)), bytes, "{value}");
+        assert_eq!(VarLong::read_from(&mut &bytes[..]).unwrap(), VarLong(value));
+    }
+}
+
+#[test]
+fn rejects_overlong_var_ints() {
+    // padded but within the byte limit is still read, like vanilla
+    assert_eq!(
+        VarInt::read_from(&mut &[0x80, 0x80, 0x00][..]).unwrap(),
+        VarInt(0)
+    );
+
+    assert!(matches!(
+        VarInt::read_from(&mut &[0x80, 0x80, 0x80, 0x80, 0x80, 0x00][..]),
+        Err(DataTypeError::VarIntTooLong)
+    ));
+    assert!(matches!(
+        VarInt::read_from(&mut &[0xff; 5][..]),
+        Err(DataTypeError::VarIntTooLong)
+    ));
+    assert!(matches!(
+        VarLong::read_from(&mut &[0xff; 10][..]),
+        Err(DataTypeError::VarLongTooLong)
+    ));
+    // the continuation bit promises another byte
+    assert!(matches!(
+        VarInt::read_from(&mut &[0x80, 0x80][..]),
+        Err(DataTypeError::UnexpectedEof)
+    ));
+}
+
+#[test]
+fn rejects_negative_lengths() {
+    let mut bytes = encode(&VarInt(-1));
+    bytes.extend([0; 8]);
+    assert!(matches!(
+        String::read_from(&mut bytes.as_slice()),
+        Err(DataTypeError::NegativeLength(-1))
+    ));
+    assert!(matches!(
+        Vec::<u8>::read_from(&mut bytes.as_slice()),
+        Err(DataTypeError::NegativeLength(-1))
+    ));
+
+    let bytes = encode(&VarInt(i32::MIN));
+    assert!(matches!(
+        Vec::<i64>::read_from(&mut bytes.as_slice()),
+        Err(DataTypeError::NegativeLength(len)) if len == i64::from(i32::MIN)
+    ));
+}
+
+#[test]
+fn string_length_bounds() {
+    let longest = "a".repeat(MAX_STRING_LENGTH);
+    let bytes = encode(&longest);
+    assert_eq!(String::read_from(&mut bytes.as_slice()).unwrap(), longest);
+
+    let too_long = "a".repeat(MAX_STRING_LENGTH + 1);
+    assert!(matches!(
+        too_long.write_to(&mut vec![]),
+        Err(DataTypeError::StringTooLong { .. })
+    ));
+
+    // the limit counts UTF-16 code units: 3 bytes each here, 4 bytes for 2 there
+    let wide = "\u{20ac}".repeat(4);
+    let bytes = encode(&wide);
+    assert_eq!(read_bounded_string(&mut bytes.as_slice(), 4).unwrap(), wide);
+    let emoji = "\u{1F600}".repeat(2);
+    let bytes = encode(&emoji);
+    assert_eq!(
+        read_bounded_string(&mut bytes.as_slice(), 4).unwrap(),
+        emoji
+    );
+    assert!(matches!(
+        read_bounded_string(&mut bytes.as_slice(), 3),
+        Err(DataTypeError::StringTooLong { length: 4, max: 3 })
+    ));
+
+    // a length no string within the bound could have is refused before reading
+    let mut bytes = encode(&VarInt(13));
+    bytes.extend([b'a'; 13]);
+    assert!(matches!(
+        read_bounded_string(&mut bytes.as_slice(), 4),
+        Err(DataTypeError::StringTooLong {
+            length: 13,
+            max: 12
+        })
+    ));
+
+    // a length longer than the data
+    let mut bytes = encode(&VarInt(10));
+    bytes.extend(b"short");
+    assert!(matches!(
+        String::read_from(&mut bytes.as_slice()),
+        Err(DataTypeError::UnexpectedEof)
+    ));
+
+    let mut bytes = encode(&VarInt(2));
+    bytes.extend([0xc3, 0x28]);
+    assert!(matches!(
+        String::read_from(&mut bytes.as_slice()),
+        Err(DataTypeError::InvalidUtf8(_))
+    ));
+}
+
+#[test]
+fn position_sign_extension() {
+    for pos in [
+        Position::new(0, 0, 0),
+        Position::new(-1, -1, -1),
+        Position::new(33_554_431, 2047, 33_554_431),
+        Position::new(-33_554_432, -2048, -33_554_432),
+        Position::new(-30_000_000, -64, 30_000_000),
+        Position::new(1, -1, 1),
+    ] {
+        let bytes = encode(&pos);
+        assert_eq!(Position::read_from(&mut bytes.as_slice()).unwrap(), pos);
+    }
+
+    assert_eq!(Position::new(-1, -1, -1).to_packed(), -1);
+    assert_eq!(
+        Position::from_packed(1 << 63 | 1 << 37 | 1 << 11),
+        Position::new(-33_554_432, -2048, -33_554_432)
+    );
+    // out of range coordinates keep their low bits
+    assert_eq!(
+        Position::from_packed(Position::new(1 << 25, 1 << 11, 0).to_packed()),
+        Position::new(-(1 << 25), -(1 << 11), 0)
+    );
+}
+
+#[test]
+fn angles_wrap() {
+    assert_eq!(Angle::from_degrees(0.0), Angle(0));
+    assert_eq!(Angle::from_degrees(90.0), Angle(64));
+    assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+    assert_eq!(Angle::from_degrees(720.0 + 180.0), Angle(128));
+    // rounds up past 255 to a full turn
+    assert_eq!(Angle::from_degrees(359.9), Angle(0));
+    assert_eq!(Angle::from_degrees(-0.1), Angle(0));
+    assert_eq!(Angle(192).to_degrees(), 270.0);
+}
@@ -0,0 +1,107 @@
+//! Announces LAN worlds with `JavaLanBroadcaster` and finds them with
+//! `JavaLanListener` over loopback.
+
+use std::{net::Ipv4Addr, time::Duration};
+
+use futures::StreamExt;
+use minecraft_utilities::{
+    Error, JavaLanBroadcaster, JavaLanGame, JavaLanListener, Phase, JAVA_LAN_GROUP,
+};
+use tokio::net::UdpSocket;
+
+async fn broadcaster(motd: &str, port: u16) -> JavaLanBroadcaster {
+    JavaLanBroadcaster::bind("127.0.0.1:0".parse().unwrap(), motd, port)
+        .await
+        .unwrap()
+        .with_interval(Duration::from_millis(20))
+}
+
+#[tokio::test]
+async fn discovers_announcements() {
+    let listener = JavaLanListener::bind("127.0.0.1:0").await.unwrap();
+    let broadcaster = broadcaster("\u{a7}aSteve - New World", 41234)
+        .await
+        .with_target(listener.local_addr().unwrap());
+    tokio::spawn(broadcaster.run());
+
+    // announced over and over
+    let games: Vec<_> = tokio::time::timeout(
+        Duration::from_secs(5),
+        listener.discover().take(3).collect::<Vec<_>>(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(games.len(), 3);
+    for game in games {
+        let game = game.unwrap();
+        assert_eq!(game.address.to_string(), "127.0.0.1:41234");
+        assert_eq!(game.motd_component().to_plain(), "Steve - New World");
+    }
+}
+
+#[tokio::test]
+async fn skips_other_packets() {
+    let listener = JavaLanListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    socket.send_to(b"hello", addr).await.unwrap();
+    socket.send_to(b"[MOTD]no port[/MOTD]", addr).await.unwrap();
+    socket
+        .send_to(b"[MOTD]World[/MOTD][AD]25565[/AD]", addr)
+        .await
+        .unwrap();
+    assert_eq!(listener.recv().await.unwrap().motd, "World");
+}
+
+#[test]
+fn parses_announcements() {
+    let from = "10.0.0.5:50000".parse().unwrap();
+
+    let game = JavaLanGame::parse("[MOTD]A [AD]1[/AD] trap[/MOTD][AD] 25565 [/AD]", from).unwrap();
+    assert_eq!(game.motd, "A [AD]1[/AD] trap");
+    assert_eq!(game.address.to_string(), "10.0.0.5:25565");
+
+    // no MOTD is fine, a full address is taken as is
+    let game = JavaLanGame::parse("[AD]play.example.com:25570[/AD]", from).unwrap();
+    assert_eq!(game.motd, "");
+    assert_eq!(game.address.to_string(), "play.example.com:25570");
+
+    // the IPv4 address of a dual-stack socket's sender
+    let game = JavaLanGame::parse(
+        "[MOTD]x[/MOTD][AD]25565[/AD]",
+        "[::ffff:10.0.0.5]:50000".parse().unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.address.to_string(), "10.0.0.5:25565");
+
+    let err = JavaLanGame::parse("[MOTD]no port[/MOTD]", from).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidResponse {
+            phase: Phase::LanDiscovery,
+            ..
+        }
+    ));
+    assert!(JavaLanGame::parse("[AD]not a port[/AD]", from).is_err());
+}
+
+#[tokio::test]
+async fn multicast_on_loopback() {
+    let listener = JavaLanListener::join(Ipv4Addr::LOCALHOST).await.unwrap();
+    let broadcaster = broadcaster("Multicast", 25565).await;
+    tokio::spawn(broadcaster.run());
+
+    let game = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            // other tests or a real client on this machine might be announcing
+            let game = listener.recv().await.unwrap();
+            if game.motd == "Multicast" {
+                return game;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(game.address.to_string(), "127.0.0.1:25565");
+    assert_eq!(listener.local_addr().unwrap().port(), JAVA_LAN_GROUP.port());
+}
@@ -0,0 +1,199 @@
+//! Joins local stand-in servers that do the optional parts of login:
+//! turning on compression partway through and asking login plugin questions.
+
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use minecraft_utilities::{
+    packets::{handshake::Handshake, login, Packet},
+    Client, Error, GameProfileProperty, LoginError, MinecraftCodec, MinecraftPacket,
+    RemainingBytes, ServerAddress, VarInt, ZlibCompression, PROTOCOL_1_20, PROTOCOL_1_20_5,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::Framed;
+use uuid::Uuid;
+
+struct Server {
+    connection: Framed<TcpStream, MinecraftCodec>,
+    protocol_version: i32,
+}
+
+impl Server {
+    async fn accept(listener: TcpListener, protocol_version: i32) -> (Self, login::LoginStart) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = Server {
+            connection: Framed::new(stream, MinecraftCodec::new()),
+            protocol_version,
+        };
+        let _: Handshake = server.read().await;
+        let login_start = server.read().await;
+        (server, login_start)
+    }
+
+    async fn send(&mut self, packet: impl Packet) {
+        let packet = packet.to_packet(self.protocol_version).unwrap();
+        self.connection.send(packet).await.unwrap();
+    }
+
+    async fn read<P: Packet>(&mut self) -> P {
+        let packet = tokio::time::timeout(Duration::from_secs(5), self.connection.next())
+            .await
+            .expect("the client didn't answer")
+            .unwrap()
+            .unwrap();
+        P::from_packet(&packet, self.protocol_version).unwrap()
+    }
+
+    /// Tell the client to compress from `threshold` bytes, then do so too.
+    async fn set_compression(&mut self, threshold: i32) {
+        self.send(login::SetCompression {
+            threshold: VarInt(threshold),
+        })
+        .await;
+        let compression = usize::try_from(threshold)
+            .ok()
+            .map(|threshold| Box::new(ZlibCompression::new(threshold)) as _);
+        self.connection.codec_mut().set_compression(compression);
+    }
+
+    async fn plugin_request(&mut self, message_id: i32, channel: &str) {
+        self.send(login::LoginPluginRequest {
+            message_id: VarInt(message_id),
+            channel: channel.to_string(),
+            data: RemainingBytes(vec![0xab; 300]),
+        })
+        .await;
+        let response: login::LoginPluginResponse = self.read().await;
+        assert_eq!(response.message_id, VarInt(message_id));
+        assert!(!response.successful);
+        assert!(response.data.0.is_empty());
+    }
+
+    async fn login_success(&mut self, login_start: login::LoginStart) {
+        // an optional field before 1.20.2
+        let uuid = login_start.player_uuid.unwrap_or(login_start.uuid);
+        self.send(login::LoginSuccess {
+            uuid,
+            uuid_string: uuid.hyphenated().to_string(),
+            username: login_start.name,
+            // big enough to get compressed
+            properties: vec![GameProfileProperty {
+                name: "textures".to_string(),
+                value: "e".repeat(500),
+                signature: None,
+            }],
+            strict_error_handling: false,
+        })
+        .await;
+        if login::LoginAcknowledged::id(self.protocol_version).is_some() {
+            let _: login::LoginAcknowledged = self.read().await;
+        }
+    }
+}
+
+async fn listen() -> (TcpListener, ServerAddress) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = ServerAddress::from(listener.local_addr().unwrap());
+    (listener, address)
+}
+
+async fn join(address: &ServerAddress, protocol_version: i32) -> Result<Uuid, Error> {
+    let mut client = Client::connect(address).await.unwrap();
+    let login_success = client
+        .join(
+            Some(protocol_version),
+            None,
+            None,
+            Some("Shrecknt"),
+            Some(Uuid::from_u128(42)),
+        )
+        .await?;
+    assert_eq!(login_success.username, "Shrecknt");
+    assert_eq!(login_success.properties[0].value.len(), 500);
+    Ok(login_success.uuid)
+}
+
+#[tokio::test]
+async fn compression_then_plugin_requests() {
+    for protocol_version in [PROTOCOL_1_20, PROTOCOL_1_20_5] {
+        let (listener, address) = listen().await;
+        let server = tokio::spawn(async move {
+            let (mut server, login_start) = Server::accept(listener, protocol_version).await;
+            server.set_compression(64).await;
+            // both of these go over the compressed connection, one compressed
+            // and one not
+            server.plugin_request(1, "velocity:player_info").await;
+            server
+                .plugin_request(2, "fabric-networking-api-v1:early_registration")
+                .await;
+            server.login_success(login_start).await;
+        });
+
+        assert_eq!(
+            join(&address, protocol_version).await.unwrap(),
+            Uuid::from_u128(42)
+        );
+        server.await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn plugin_requests_then_compression() {
+    let (listener, address) = listen().await;
+    let server = tokio::spawn(async move {
+        let (mut server, login_start) = Server::accept(listener, PROTOCOL_1_20).await;
+        server.plugin_request(9, "bungeecord:main").await;
+        server.set_compression(0).await;
+        server.plugin_request(10, "bungeecord:main").await;
+        // a negative threshold turns it back off
+        server.set_compression(-1).await;
+        server.login_success(login_start).await;
+    });
+
+    join(&address, PROTOCOL_1_20).await.unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn kicked_during_login() {
+    let (listener, address) = listen().await;
+    tokio::spawn(async move {
+        let (mut server, _) = Server::accept(listener, PROTOCOL_1_20).await;
+        server.set_compression(16).await;
+        server
+            .send(login::LoginDisconnect {
+                reason: r#"{"text":"You are not whitelisted on this server!"}"#.to_string(),
+            })
+            .await;
+    });
+
+    match join(&address, PROTOCOL_1_20).await {
+        Err(Error::Login(LoginError::Disconnected(reason))) => {
+            assert_eq!(reason.to_plain(), "You are not whitelisted on this server!")
+        }
+        other => panic!("expected a kick, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn unexpected_login_packet() {
+    let (listener, address) = listen().await;
+    tokio::spawn(async move {
+        let (mut server, _) = Server::accept(listener, PROTOCOL_1_20).await;
+        server
+            .connection
+            .send(MinecraftPacket {
+                packet_id: 0x2a,
+                buffer: vec![],
+            })
+            .await
+            .unwrap();
+        // hold the connection open until the client gives up
+        let _ = server.connection.next().await;
+    });
+
+    assert!(matches!(
+        join(&address, PROTOCOL_1_20).await,
+        Err(Error::Login(LoginError::UnexpectedPacket(0x2a)))
+    ));
+}
@@ -0,0 +1,382 @@
+//! Reads and writes NBT in every form: binary files, network tags, SNBT and
+//! serde types.
+
+use minecraft_utilities::nbt::{
+    self, ByteArray, Compound, FileCompression, IntArray, LongArray, NbtError, Tag, MAX_DEPTH,
+    TAG_BYTE_ARRAY, TAG_COMPOUND, TAG_INT, TAG_INT_ARRAY, TAG_LIST, TAG_LONG_ARRAY,
+};
+use serde::{Deserialize, Serialize};
+
+fn sample() -> Compound {
+    let mut root = Compound::new();
+    root.insert("byte", Tag::Byte(-1));
+    root.insert("long", Tag::Long(i64::MIN));
+    root.insert("double", Tag::Double(0.5));
+    root.insert("name", Tag::String("Shrecknt".to_string()));
+    root.insert("bytes", Tag::ByteArray(vec![1, -2, 3]));
+    root.insert("ints", Tag::IntArray(vec![i32::MAX]));
+    root.insert("longs", Tag::LongArray(vec![]));
+    root.insert("list", Tag::List(vec![Tag::Short(1), Tag::Short(2)]));
+    let mut inner = Compound::new();
+    inner.insert("empty", Tag::List(vec![]));
+    root.insert("inner", Tag::Compound(inner));
+    root
+}
+
+#[test]
+fn modified_utf8_round_trips() {
+    for string in [
+        "plain",
+        "nul\0in the middle",
+        "\u{1F600} emoji",
+        "\0\u{10FFFF}é",
+    ] {
+        let mut root = Compound::new();
+        root.insert(string, Tag::String(string.to_string()));
+        let mut bytes = vec![];
+        nbt::write_named(&mut bytes, string, &root).unwrap();
+        let (name, read) = nbt::read_named(&mut bytes.as_slice()).unwrap();
+        assert_eq!(name, string);
+        assert_eq!(read, root);
+    }
+}
+
+#[test]
+fn encodes_nul_and_supplementary_characters_like_java() {
+    let mut bytes = vec![];
+    nbt::write_network(&mut bytes, Some(&Tag::String("\0\u{1F600}".to_string()))).unwrap();
+    assert_eq!(
+        bytes,
+        [
+            8, 0, 8, // TAG_String, 8 bytes long
+            0xC0, 0x80, // NUL
+            0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, // surrogate pair
+        ]
+    );
+
+    // real UTF-8 for a character outside the BMP isn't modified UTF-8
+    let bytes = [8, 0, 4, 0xF0, 0x9F, 0x98, 0x80];
+    assert_eq!(
+        nbt::read_network(&mut &bytes[..]).unwrap(),
+        Some(Tag::String("\u{1F600}".to_string()))
+    );
+    let bytes = [8, 0, 2, 0xC0, 0x41];
+    assert!(matches!(
+        nbt::read_network(&mut &bytes[..]),
+        Err(NbtError::InvalidString)
+    ));
+}
+
+#[test]
+fn files_round_trip_with_any_compression() {
+    let root = sample();
+    for compression in [
+        FileCompression::None,
+        FileCompression::Gzip,
+        FileCompression::Zlib,
+    ] {
+        let bytes = nbt::write_file("level", &root, compression).unwrap();
+        match compression {
+            FileCompression::None => assert_eq!(bytes[0], TAG_COMPOUND),
+            FileCompression::Gzip => assert_eq!(bytes[..2], [0x1F, 0x8B]),
+            FileCompression::Zlib => assert_eq!(bytes[0], 0x78),
+        }
+        let (name, read) = nbt::read_file(&bytes).unwrap();
+        assert_eq!(name, "level");
+        assert_eq!(read, root);
+    }
+}
+
+#[test]
+fn rejects_roots_that_are_not_compounds() {
+    assert!(matches!(
+        nbt::read_file(&[TAG_INT, 0, 0, 0, 0, 0, 1]),
+        Err(NbtError::RootNotCompound(TAG_INT))
+    ));
+    assert!(nbt::read_file(&[]).is_err());
+}
+
+/// A network list nested `depth` lists deep, each holding the next.
+fn nested_lists(depth: usize) -> Vec<u8> {
+    let mut bytes = vec![TAG_LIST];
+    for _ in 0..depth {
+        bytes.extend([TAG_LIST, 0, 0, 0, 1]);
+    }
+    bytes.extend([TAG_INT, 0, 0, 0, 0]);
+    bytes
+}
+
+#[test]
+fn limits_nesting() {
+    assert!(nbt::read_network(&mut nested_lists(MAX_DEPTH - 1).as_slice()).is_ok());
+    assert!(matches!(
+        nbt::read_network(&mut nested_lists(MAX_DEPTH + 1).as_slice()),
+        Err(NbtError::TooDeep)
+    ));
+
+    // a compound in a compound in ...
+    let mut bytes = vec![TAG_COMPOUND];
+    for _ in 0..=MAX_DEPTH {
+        bytes.extend([TAG_COMPOUND, 0, 1, b'a']);
+    }
+    assert!(matches!(
+        nbt::read_network(&mut bytes.as_slice()),
+        Err(NbtError::TooDeep)
+    ));
+
+    let snbt = format!("{}{}", "[".repeat(MAX_DEPTH + 2), "]".repeat(MAX_DEPTH + 2));
+    assert!(matches!(snbt.parse::<Tag>(), Err(NbtError::TooDeep)));
+    let snbt = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+    assert!(snbt.parse::<Tag>().is_ok());
+}
+
+#[test]
+fn rejects_negative_lengths() {
+    let minus_one = (-1_i32).to_be_bytes();
+    for prefix in [
+        vec![TAG_LIST, TAG_INT],
+        vec![TAG_BYTE_ARRAY],
+        vec![TAG_INT_ARRAY],
+        vec![TAG_LONG_ARRAY],
+    ] {
+        let mut bytes = prefix;
+        bytes.extend(minus_one);
+        assert!(matches!(
+            nbt::read_network(&mut bytes.as_slice()),
+            Err(NbtError::NegativeLength(-1))
+        ));
+    }
+
+    // a huge length with nothing behind it runs out of bytes rather than memory
+    let mut bytes = vec![TAG_LONG_ARRAY];
+    bytes.extend(i32::MAX.to_be_bytes());
+    assert!(nbt::read_network(&mut bytes.as_slice()).is_err());
+}
+
+#[test]
+fn prints_snbt() {
+    assert_eq!(
+        Tag::Compound(sample()).to_string(),
+        "{byte:-1b,long:-9223372036854775808L,double:0.5d,name:\"Shrecknt\",\
+         bytes:[B;1b,-2b,3b],ints:[I;2147483647],longs:[L;],list:[1s,2s],inner:{empty:[]}}"
+    );
+
+    let mut compound = Compound::new();
+    compound.insert("needs quotes", Tag::String("say \"hi\" \\o/".to_string()));
+    compound.insert("", Tag::Float(1.0));
+    compound.insert("minecraft:stone", Tag::Int(1));
+    compound.insert("a.b-c_d+e", Tag::Int(2));
+    assert_eq!(
+        Tag::Compound(compound).to_string(),
+        r#"{"needs quotes":"say \"hi\" \\o/","":1.0f,"minecraft:stone":1,a.b-c_d+e:2}"#
+    );
+}
+
+#[test]
+fn parses_snbt() {
+    let tag: Tag = r#"{
+        b: 1b, s: -2S, i: 3, l: 4L, f: 1.5F, d: 2.5, e: 1e3, explicit: 2d,
+        yes: true, no: FALSE,
+        word: minecraft, number_like: 12abc, inf: inf,
+        'single': 'it\'s "fine"', "double": "a \"b\" \\ c",
+        bytes: [B; 1b, 2b], ints: [I;], longs: [L; -1L],
+        list: [{}, {a: 1}]
+    }"#
+    .parse()
+    .unwrap();
+    let compound = tag.as_compound().unwrap();
+    let get = |key| compound.get(key).unwrap().clone();
+    assert_eq!(get("b"), Tag::Byte(1));
+    assert_eq!(get("s"), Tag::Short(-2));
+    assert_eq!(get("i"), Tag::Int(3));
+    assert_eq!(get("l"), Tag::Long(4));
+    assert_eq!(get("f"), Tag::Float(1.5));
+    assert_eq!(get("d"), Tag::Double(2.5));
+    assert_eq!(get("e"), Tag::Double(1000.0));
+    assert_eq!(get("explicit"), Tag::Double(2.0));
+    assert_eq!(get("yes"), Tag::Byte(1));
+    assert_eq!(get("no"), Tag::Byte(0));
+    assert_eq!(get("word"), Tag::String("minecraft".to_string()));
+    assert_eq!(get("number_like"), Tag::String("12abc".to_string()));
+    assert_eq!(get("inf"), Tag::String("inf".to_string()));
+    assert_eq!(get("single"), Tag::String("it's \"fine\"".to_string()));
+    assert_eq!(get("double"), Tag::String("a \"b\" \\ c".to_string()));
+    assert_eq!(get("bytes"), Tag::ByteArray(vec![1, 2]));
+    assert_eq!(get("ints"), Tag::IntArray(vec![]));
+    assert_eq!(get("longs"), Tag::LongArray(vec![-1]));
+    assert_eq!(
+        get("list").as_list().unwrap()[1],
+        "{a:1}".parse::<Tag>().unwrap()
+    );
+}
+
+#[test]
+fn rejects_bad_snbt() {
+    for snbt in [
+        "",
+        "{",
+        "{a 1}",
+        "{a:1,}",
+        "[1, 2b]",
+        "[B; 1, 2]",
+        "[I; 1L]",
+        "\"unterminated",
+        "{:1}",
+    ] {
+        assert!(snbt.parse::<Tag>().is_err(), "{snbt:?}");
+    }
+    assert!(matches!("[1, 2b]".parse::<Tag>(), Err(NbtError::MixedList)));
+    assert!(matches!(
+        "{a 1}".parse::<Tag>(),
+        Err(NbtError::Snbt { position: 3, .. })
+    ));
+}
+
+#[test]
+fn snbt_round_trips() {
+    let tag = Tag::Compound(sample());
+    assert_eq!(tag.to_string().parse::<Tag>().unwrap(), tag);
+
+    for snbt in [
+        "1b",
+        "-32768s",
+        "2147483647",
+        "9223372036854775807L",
+        "0.1f",
+        "1.0E-10d",
+        "\"\"",
+        "\"true\"",
+        "\"12\"",
+        "[B;]",
+        "[[],[1,2]]",
+        "{\"with space\":{}}",
+    ] {
+        let tag: Tag = snbt.parse().unwrap();
+        assert_eq!(tag.to_string().parse::<Tag>().unwrap(), tag, "{snbt}");
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Level {
+    name: String,
+    seed: i64,
+    hardcore: bool,
+    spawn: Position,
+    players: Vec<Player>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rules: Option<Rules>,
+    heightmap: LongArray,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Position {
+    x: i32,
+    y: i16,
+    z: i32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Player {
+    name: String,
+    health: f32,
+    inventory: Vec<Item>,
+    uuid: IntArray,
+    nickname: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Item {
+    id: String,
+    count: i8,
+    data: ByteArray,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Rules {
+    #[serde(rename = "doDaylightCycle")]
+    daylight: bool,
+    #[serde(rename = "randomTickSpeed")]
+    tick_speed: u8,
+}
+
+fn level(rules: Option<Rules>) -> Level {
+    Level {
+        name: "New World".to_string(),
+        seed: -4_172_144_997_902_289_642,
+        hardcore: false,
+        spawn: Position {
+            x: -120,
+            y: 64,
+            z: 3000,
+        },
+        players: vec![
+            Player {
+                name: "Shrecknt".to_string(),
+                health: 20.0,
+                inventory: vec![
+                    Item {
+                        id: "minecraft:stone".to_string(),
+                        count: 64,
+                        data: ByteArray(vec![]),
+                    },
+                    Item {
+                        id: "minecraft:diamond".to_string(),
+                        count: 3,
+                        data: ByteArray(vec![1, -1]),
+                    },
+                ],
+                uuid: IntArray(vec![1, 2, 3, 4]),
+                nickname: Some("shreck".to_string()),
+            },
+            Player {
+                name: "Alex".to_string(),
+                health: 0.5,
+                inventory: vec![],
+                uuid: IntArray(vec![-1, -2, -3, -4]),
+                nickname: None,
+            },
+        ],
+        rules,
+        heightmap: LongArray(vec![i64::MAX, 0, i64::MIN]),
+    }
+}
+
+#[test]
+fn serde_round_trips_nested_structs() {
+    for level in [
+        level(None),
+        level(Some(Rules {
+            daylight: true,
+            tick_speed: 3,
+        })),
+    ] {
+        let tag = nbt::to_tag(&level).unwrap();
+        let mut bytes = vec![];
+        nbt::write_network(&mut bytes, Some(&tag)).unwrap();
+        let read = nbt::read_network(&mut bytes.as_slice()).unwrap().unwrap();
+        assert_eq!(read, tag);
+        assert_eq!(nbt::from_tag::<Level>(read).unwrap(), level);
+
+        // and through SNBT
+        let parsed: Tag = tag.to_string().parse().unwrap();
+        assert_eq!(nbt::from_tag::<Level>(parsed).unwrap(), level);
+    }
+}
+
+#[test]
+fn serializes_to_the_expected_tags() {
+    let tag = nbt::to_tag(&level(None)).unwrap();
+    let compound = tag.as_compound().unwrap();
+    assert!(!compound.contains_key("rules"));
+    assert_eq!(compound.get("hardcore"), Some(&Tag::Byte(0)));
+    assert!(matches!(compound.get("heightmap"), Some(Tag::LongArray(_))));
+
+    let players = compound.get("players").unwrap().as_list().unwrap();
+    let alex = players[1].as_compound().unwrap();
+    assert!(!alex.contains_key("nickname"));
+    assert_eq!(alex.get("inventory"), Some(&Tag::List(vec![])));
+    assert_eq!(alex.get("uuid"), Some(&Tag::IntArray(vec![-1, -2, -3, -4])));
+    let inventory = players[0].as_compound().unwrap().get("inventory").unwrap();
+    let stone = inventory.as_list().unwrap()[0].as_compound().unwrap();
+    assert_eq!(stone.get("data"), Some(&Tag::ByteArray(vec![])));
+}
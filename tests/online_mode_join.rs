@@ -0,0 +1,159 @@
+//! Joins a local stand-in for an online mode server, authenticating through
+//! `MockSessionService` instead of the real session server.
+
+use aes::cipher::{inout::InOutBuf, BlockEncryptMut, KeyIvInit};
+use minecraft_utilities::{
+    server_hash, Client, GameProfile, MockSessionService, ServerAddress, SessionService,
+};
+use rsa::{pkcs8::EncodePublicKey, Pkcs1v15Encrypt, RsaPrivateKey};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use uuid::Uuid;
+
+fn write_varint(buf: &mut Vec<u8>, mut val: i32) {
+    loop {
+        let mut byte = (val & 0x7f) as u8;
+        val = ((val as u32) >> 7) as i32;
+        if val != 0 {
+            byte |= 0x80;
+        }
+        buf.push(byte);
+        if val == 0 {
+            break;
+        }
+    }
+}
+
+fn read_varint(buf: &[u8], idx: &mut usize) -> i32 {
+    let mut res = 0;
+    for count in 0..5 {
+        let byte = buf[*idx];
+        *idx += 1;
+        res |= ((byte & 0x7f) as i32) << (7 * count);
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    res
+}
+
+fn read_byte_array<'a>(buf: &'a [u8], idx: &mut usize) -> &'a [u8] {
+    let len = read_varint(buf, idx) as usize;
+    let res = &buf[*idx..*idx + len];
+    *idx += len;
+    res
+}
+
+fn frame(packet_id: i32, data: &[u8]) -> Vec<u8> {
+    let mut body = vec![];
+    write_varint(&mut body, packet_id);
+    body.extend_from_slice(data);
+    let mut res = vec![];
+    write_varint(&mut res, body.len() as i32);
+    res.extend(body);
+    res
+}
+
+async fn read_frame(stream: &mut TcpStream) -> (i32, Vec<u8>) {
+    let mut len = 0;
+    for count in 0..5 {
+        let byte = stream.read_u8().await.unwrap();
+        len |= ((byte & 0x7f) as i32) << (7 * count);
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0; len as usize];
+    stream.read_exact(&mut body).await.unwrap();
+    let mut idx = 0;
+    let packet_id = read_varint(&body, &mut idx);
+    (packet_id, body.split_off(idx))
+}
+
+async fn run_server(listener: TcpListener, session: MockSessionService) -> Option<GameProfile> {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+    let public_key = private_key.to_public_key().to_public_key_der().unwrap();
+
+    let (handshake_id, _) = read_frame(&mut stream).await;
+    assert_eq!(handshake_id, 0x00);
+    let (login_start_id, login_start) = read_frame(&mut stream).await;
+    assert_eq!(login_start_id, 0x00);
+    let mut idx = 0;
+    let username = String::from_utf8(read_byte_array(&login_start, &mut idx).to_vec()).unwrap();
+
+    let verify_token = [1, 2, 3, 4];
+    let mut encryption_request = vec![];
+    write_varint(&mut encryption_request, 0); // empty server id
+    write_varint(&mut encryption_request, public_key.as_bytes().len() as i32);
+    encryption_request.extend_from_slice(public_key.as_bytes());
+    write_varint(&mut encryption_request, verify_token.len() as i32);
+    encryption_request.extend_from_slice(&verify_token);
+    stream
+        .write_all(&frame(0x01, &encryption_request))
+        .await
+        .unwrap();
+
+    let (encryption_response_id, encryption_response) = read_frame(&mut stream).await;
+    assert_eq!(encryption_response_id, 0x01);
+    let mut idx = 0;
+    let shared_secret = private_key
+        .decrypt(
+            Pkcs1v15Encrypt,
+            read_byte_array(&encryption_response, &mut idx),
+        )
+        .unwrap();
+    let echoed_token = private_key
+        .decrypt(
+            Pkcs1v15Encrypt,
+            read_byte_array(&encryption_response, &mut idx),
+        )
+        .unwrap();
+    assert_eq!(echoed_token, verify_token);
+
+    let hash = server_hash("", &shared_secret, public_key.as_bytes());
+    let profile = session.has_joined(&username, &hash).await.unwrap()?;
+
+    let mut login_success = profile.id.as_bytes().to_vec();
+    write_varint(&mut login_success, profile.name.len() as i32);
+    login_success.extend_from_slice(profile.name.as_bytes());
+    write_varint(&mut login_success, 0); // no properties
+    let mut packet = frame(0x02, &login_success);
+    let mut encryptor =
+        cfb8::Encryptor::<aes::Aes128>::new_from_slices(&shared_secret, &shared_secret).unwrap();
+    let (chunks, _rest) = InOutBuf::from(packet.as_mut_slice()).into_chunks();
+    encryptor.encrypt_blocks_inout_mut(chunks);
+    stream.write_all(&packet).await.unwrap();
+
+    Some(profile)
+}
+
+#[tokio::test]
+async fn online_mode_join_with_mock_session() {
+    let profile = GameProfile {
+        id: Uuid::new_v4(),
+        name: "Shrecknt".to_string(),
+        properties: vec![],
+    };
+    let session = MockSessionService::new(profile.clone());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = ServerAddress::from(listener.local_addr().unwrap());
+    let server = tokio::spawn(run_server(listener, session.clone()));
+
+    let mut client = Client::connect(&address)
+        .await
+        .unwrap()
+        .with_session_service(session.clone());
+    let login_success = client
+        .join(Some(763), None, None, Some(&profile.name), Some(profile.id))
+        .await
+        .unwrap();
+
+    assert_eq!(login_success.uuid, profile.id);
+    assert_eq!(login_success.username, profile.name);
+    assert_eq!(server.await.unwrap(), Some(profile));
+    assert_eq!(session.joined_hashes().len(), 1);
+}
@@ -0,0 +1,296 @@
+//! Runs `Ping` against local stand-ins for modern and legacy servers' status
+//! handlers.
+
+use std::time::Duration;
+
+use minecraft_utilities::{
+    AnyPing, Error, LegacyPingResult, LegacyPingVersion, Phase, Ping, ServerAddress,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+
+const STATUS: &str =
+    r#"{"version":{"name":"1.20.4","protocol":765},"description":"A Minecraft Server"}"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pong {
+    Echo,
+    Wrong,
+    HangUp,
+}
+
+async fn read_varint(stream: &mut TcpStream) -> Option<i32> {
+    let mut value = 0;
+    for i in 0..5 {
+        let byte = stream.read_u8().await.ok()?;
+        value |= ((byte & 0x7f) as i32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn varint(mut value: i32) -> Vec<u8> {
+    let mut buf = vec![];
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value = ((value as u32) >> 7) as i32;
+        if value == 0 {
+            buf.push(byte);
+            return buf;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+async fn read_packet(stream: &mut TcpStream) -> Option<Vec<u8>> {
+    let len = read_varint(stream).await?;
+    let mut buf = vec![0; len as usize];
+    stream.read_exact(&mut buf).await.ok()?;
+    Some(buf)
+}
+
+async fn write_packet(stream: &mut TcpStream, packet: &[u8]) {
+    let mut buf = varint(packet.len() as i32);
+    buf.extend(packet);
+    stream.write_all(&buf).await.unwrap();
+}
+
+async fn start_server(pong: Pong) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let handshake = read_packet(&mut stream).await.unwrap();
+            assert_eq!(handshake[0], 0x00);
+            assert_eq!(read_packet(&mut stream).await.unwrap(), [0x00]);
+
+            let mut response = vec![0x00];
+            response.extend(varint(STATUS.len() as i32));
+            response.extend(STATUS.as_bytes());
+            write_packet(&mut stream, &response).await;
+
+            let Some(mut ping) = read_packet(&mut stream).await else {
+                continue;
+            };
+            assert_eq!(ping.len(), 9);
+            assert_eq!(ping[0], 0x01);
+            match pong {
+                Pong::Echo => write_packet(&mut stream, &ping).await,
+                Pong::Wrong => {
+                    ping[8] ^= 0xff;
+                    write_packet(&mut stream, &ping).await;
+                }
+                Pong::HangUp => {}
+            }
+        }
+    });
+    port
+}
+
+#[tokio::test]
+async fn measures_latency() {
+    let port = start_server(Pong::Echo).await;
+    let ping = Ping::ping_with_latency("127.0.0.1", Some(port), None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(Ping::get_protocol_version(&ping.status).unwrap(), 765);
+    assert_eq!(ping.latency, ping.timings.latency);
+    assert!(ping.latency > std::time::Duration::ZERO);
+    assert!(ping.timings.status > std::time::Duration::ZERO);
+}
+
+#[tokio::test]
+async fn rejects_wrong_pong() {
+    let port = start_server(Pong::Wrong).await;
+    let err = Ping::ping_with_latency("127.0.0.1", Some(port), None, None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidResponse {
+            phase: Phase::Status,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn fails_when_the_server_hangs_up() {
+    let port = start_server(Pong::HangUp).await;
+    let err = Ping::ping_with_latency("127.0.0.1", Some(port), None, None, None)
+        .await
+        .unwrap_err();
+    assert_eq!(err.phase(), Some(Phase::Status));
+}
+
+#[tokio::test]
+async fn rejects_oversized_status_lengths() {
+    // over the string limit, longer than the packet, and negative
+    for (packet_len, len) in [(200_000, 100_000), (10, 5000), (10, -1)] {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_packet(&mut stream).await.unwrap();
+            read_packet(&mut stream).await.unwrap();
+            // no body, so the client has to give up on the header alone
+            let mut header = varint(packet_len);
+            header.push(0x00);
+            header.extend(varint(len));
+            stream.write_all(&header).await.unwrap();
+            let _ = stream.read_u8().await;
+        });
+
+        let err = tokio::time::timeout(
+            Duration::from_secs(5),
+            Ping::ping_with_latency("127.0.0.1", Some(port), None, None, None),
+        )
+        .await
+        .expect("the client waited for the status")
+        .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::InvalidResponse {
+                    phase: Phase::Status,
+                    ..
+                }
+            ),
+            "{len}: {err:?}"
+        );
+    }
+}
+
+/// Answers legacy pings with `reply`, sending back each request it got.
+/// Hangs up on anything that isn't a legacy ping, like old servers do with
+/// packets they don't know.
+async fn start_legacy_server(
+    reply: &'static str,
+) -> (ServerAddress, mpsc::UnboundedReceiver<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = ServerAddress::from(listener.local_addr().unwrap());
+    let (requests, requests_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 512];
+            let mut len = stream.read(&mut request).await.unwrap();
+            if request[0] != 0xFE {
+                continue;
+            }
+            // the rest of the request might come separately
+            while let Ok(Ok(read @ 1..)) =
+                tokio::time::timeout(Duration::from_millis(50), stream.read(&mut request[len..]))
+                    .await
+            {
+                len += read;
+            }
+            requests.send(request[..len].to_vec()).unwrap();
+
+            let reply: Vec<u16> = reply.encode_utf16().collect();
+            let mut packet = vec![0xFF];
+            packet.extend((reply.len() as u16).to_be_bytes());
+            packet.extend(reply.iter().flat_map(|unit| unit.to_be_bytes()));
+            stream.write_all(&packet).await.unwrap();
+        }
+    });
+    (addr, requests_rx)
+}
+
+const BETA_REPLY: &str = "A Beta Server\u{a7}3\u{a7}20";
+const NEW_REPLY: &str = "\u{a7}1\x0061\x001.5.2\x00A \u{a7}aColored\u{a7}r Server\x003\x0020";
+
+#[tokio::test]
+async fn legacy_requests() {
+    let (addr, mut requests) = start_legacy_server(NEW_REPLY).await;
+
+    let res = Ping::ping_legacy(&addr, LegacyPingVersion::Beta1_8)
+        .await
+        .unwrap();
+    assert_eq!(requests.recv().await.unwrap(), [0xFE]);
+    assert_eq!(res.protocol_version, 61);
+    assert_eq!(res.server_version, "1.5.2");
+    assert_eq!(res.motd, "A §aColored§r Server");
+    assert_eq!(res.motd_component().to_plain(), "A Colored Server");
+
+    Ping::ping_legacy(&addr, LegacyPingVersion::V1_4)
+        .await
+        .unwrap();
+    assert_eq!(requests.recv().await.unwrap(), [0xFE, 0x01]);
+
+    Ping::ping_legacy(&addr, LegacyPingVersion::V1_6)
+        .await
+        .unwrap();
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request[..3], [0xFE, 0x01, 0xFA]);
+    // ends with the host and port the client connected to
+    let host: Vec<u8> = "127.0.0.1"
+        .encode_utf16()
+        .flat_map(|unit| unit.to_be_bytes())
+        .collect();
+    let tail = &request[request.len() - host.len() - 4..];
+    assert_eq!(tail[..host.len()], host);
+    assert_eq!(tail[host.len()..], i32::from(addr.port).to_be_bytes());
+}
+
+#[tokio::test]
+async fn beta_replies() {
+    let (addr, _requests) = start_legacy_server(BETA_REPLY).await;
+    // old servers answer the newer requests in the old format too
+    for version in [LegacyPingVersion::Beta1_8, LegacyPingVersion::V1_6] {
+        let res = Ping::ping_legacy(&addr, version).await.unwrap();
+        assert_eq!(res.protocol_version, 0);
+        assert_eq!(res.server_version, "");
+        assert_eq!(res.motd, "A Beta Server");
+        assert_eq!(res.player_count, 3);
+        assert_eq!(res.max_player_count, 20);
+    }
+}
+
+#[test]
+fn parses_replies() {
+    let res = LegacyPingResult::parse("A Beta Server\u{a7}3\u{a7}20").unwrap();
+    assert_eq!((res.player_count, res.max_player_count), (3, 20));
+    assert!(LegacyPingResult::parse("no counts").is_err());
+    assert!(LegacyPingResult::parse("motd\u{a7}three\u{a7}20").is_err());
+    assert!(LegacyPingResult::parse("\u{a7}1\0seventy").is_err());
+}
+
+#[tokio::test]
+async fn ping_any_prefers_modern_status() {
+    let port = start_server(Pong::HangUp).await;
+    let addr = ServerAddress::new("127.0.0.1", port);
+    let res = Ping::ping_any(&addr, Duration::from_secs(1)).await.unwrap();
+    assert!(
+        matches!(res, AnyPing::Modern(status) if Ping::get_protocol_version(&status).unwrap() == 765)
+    );
+}
+
+#[tokio::test]
+async fn ping_any_falls_back_to_legacy() {
+    let (addr, mut requests) = start_legacy_server(NEW_REPLY).await;
+    let res = Ping::ping_any(&addr, Duration::from_secs(1)).await.unwrap();
+    let AnyPing::Legacy(version, res) = res else {
+        panic!("expected a legacy ping, got {res:?}");
+    };
+    assert_eq!(version, LegacyPingVersion::V1_6);
+    assert_eq!(res.protocol_version, 61);
+    assert_eq!(requests.recv().await.unwrap()[..3], [0xFE, 0x01, 0xFA]);
+}
+
+#[tokio::test]
+async fn ping_any_gives_up_on_refused_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = ServerAddress::from(listener.local_addr().unwrap());
+    drop(listener);
+    let err = Ping::ping_any(&addr, Duration::from_secs(1))
+        .await
+        .unwrap_err();
+    assert!(err.is_refused());
+}
@@ -0,0 +1,245 @@
+//! Runs `Query` against a local stand-in that answers like vanilla does, and
+//! against `QueryServer`.
+
+use std::{
+    sync::{
+        atomic::{AtomicI32, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use minecraft_utilities::{Error, Phase, Query, QueryFullStat, QueryServer, ServerAddress};
+use tokio::net::UdpSocket;
+
+const FULL_STAT: &[u8] = b"splitnum\0\x80\0\
+hostname\0A \xc2\xa7aMinecraft\xc2\xa7r Server\0\
+gametype\0SMP\0\
+game_id\0MINECRAFT\0\
+version\x001.20.4\0\
+plugins\0Paper on 1.20.4: WorldEdit 7.2; LuckPerms 5.4\0\
+map\0world\0\
+numplayers\x003\0\
+maxplayers\x0020\0\
+hostport\x0025565\0\
+hostip\x00127.0.0.1\0\
+motd_extra\0hi\0\
+\0\x01player_\0\0\
+Notch\0jeb_\0Dinnerbone\0\0";
+
+/// Answers queries, accepting only the most recent challenge token. A
+/// `silent` server ignores stat requests.
+async fn start_server(silent: bool) -> (ServerAddress, Arc<AtomicI32>) {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = ServerAddress::from(socket.local_addr().unwrap());
+    let token = Arc::new(AtomicI32::new(9513307));
+    let current = token.clone();
+
+    tokio::spawn(async move {
+        let mut buf = [0; 1500];
+        loop {
+            let (len, from) = socket.recv_from(&mut buf).await.unwrap();
+            let request = &buf[..len];
+            assert_eq!(&request[..2], [0xFE, 0xFD]);
+            let session_id = &request[3..7];
+            let mut response = vec![request[2]];
+            response.extend(session_id);
+            match request[2] {
+                9 => {
+                    response.extend(current.load(Ordering::SeqCst).to_string().as_bytes());
+                    response.push(0);
+                }
+                0 if silent => continue,
+                0 => {
+                    let token = i32::from_be_bytes(request[7..11].try_into().unwrap());
+                    if token != current.load(Ordering::SeqCst) {
+                        continue;
+                    }
+                    if len == 15 {
+                        response.extend(FULL_STAT);
+                    } else {
+                        response.extend(b"A Minecraft Server\0SMP\0world\x003\x0020\0");
+                        response.extend(25565u16.to_le_bytes());
+                        response.extend(b"127.0.0.1\0");
+                    }
+                }
+                _ => continue,
+            }
+            // a stray answer for someone else first
+            let mut stray = response.clone();
+            stray[1] ^= 0x01;
+            socket.send_to(&stray, from).await.unwrap();
+            socket.send_to(&response, from).await.unwrap();
+        }
+    });
+
+    (addr, token)
+}
+
+#[tokio::test]
+async fn basic_stat() {
+    let (addr, _) = start_server(false).await;
+    let mut query = Query::connect(&addr).await.unwrap();
+    let stat = query.basic_stat().await.unwrap();
+    assert_eq!(stat.motd, "A Minecraft Server");
+    assert_eq!(stat.game_type, "SMP");
+    assert_eq!(stat.map, "world");
+    assert_eq!(stat.player_count, 3);
+    assert_eq!(stat.max_player_count, 20);
+    assert_eq!(stat.host_port, 25565);
+    assert_eq!(stat.host_ip, "127.0.0.1");
+}
+
+#[tokio::test]
+async fn full_stat() {
+    let (addr, _) = start_server(false).await;
+    let mut query = Query::connect(&addr).await.unwrap();
+    let stat = query.full_stat().await.unwrap();
+    assert_eq!(stat.motd, "A §aMinecraft§r Server");
+    assert_eq!(stat.game_id, "MINECRAFT");
+    assert_eq!(stat.version, "1.20.4");
+    assert_eq!(stat.software.as_deref(), Some("Paper on 1.20.4"));
+    assert_eq!(stat.plugins, ["WorldEdit 7.2", "LuckPerms 5.4"]);
+    assert_eq!(
+        stat.plugins_string(),
+        "Paper on 1.20.4: WorldEdit 7.2; LuckPerms 5.4"
+    );
+    assert_eq!(stat.player_count, 3);
+    assert_eq!(stat.host_port, 25565);
+    assert_eq!(stat.players, ["Notch", "jeb_", "Dinnerbone"]);
+    assert_eq!(stat.extra, [("motd_extra".to_string(), "hi".to_string())]);
+}
+
+#[tokio::test]
+async fn handshakes_again_when_the_token_expires() {
+    let (addr, token) = start_server(false).await;
+    let mut query = Query::connect(&addr).await.unwrap();
+    query.set_timeout(Some(Duration::from_millis(200)));
+    assert_eq!(query.handshake().await.unwrap(), 9513307);
+    query.basic_stat().await.unwrap();
+
+    token.store(-4421, Ordering::SeqCst);
+    query.full_stat().await.unwrap();
+}
+
+#[tokio::test]
+async fn times_out() {
+    let (addr, _) = start_server(true).await;
+    let mut query = Query::connect(&addr).await.unwrap();
+    query.set_timeout(Some(Duration::from_millis(100)));
+    let err = query.basic_stat().await.unwrap_err();
+    assert!(matches!(err, Error::Timeout(Phase::Query)));
+}
+
+fn stats() -> QueryFullStat {
+    QueryFullStat {
+        motd: "A §aMinecraft§r Server".to_string(),
+        game_type: "SMP".to_string(),
+        game_id: "MINECRAFT".to_string(),
+        version: "1.20.4".to_string(),
+        software: Some("Paper on 1.20.4".to_string()),
+        plugins: vec!["WorldEdit 7.2".to_string(), "LuckPerms 5.4".to_string()],
+        map: "world".to_string(),
+        player_count: 3,
+        max_player_count: 20,
+        host_port: 25565,
+        host_ip: "127.0.0.1".to_string(),
+        players: vec![
+            "Notch".to_string(),
+            "jeb_".to_string(),
+            "Dinnerbone".to_string(),
+        ],
+        extra: vec![("motd_extra".to_string(), "hi".to_string())],
+    }
+}
+
+async fn start_query_server(server: QueryServer) -> ServerAddress {
+    let addr = ServerAddress::from(server.local_addr().unwrap());
+    tokio::spawn(server.run());
+    addr
+}
+
+/// Handshake from `socket` and return the token.
+async fn raw_handshake(socket: &UdpSocket) -> i32 {
+    socket.send(&[0xFE, 0xFD, 9, 0, 0, 0, 1]).await.unwrap();
+    let mut buf = [0; 64];
+    let len = socket.recv(&mut buf).await.unwrap();
+    assert_eq!(buf[..5], [9, 0, 0, 0, 1]);
+    std::str::from_utf8(&buf[5..len - 1])
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+/// Send a basic stat request and see if anything comes back.
+async fn raw_basic_stat(socket: &UdpSocket, token: i32) -> bool {
+    let mut request = vec![0xFE, 0xFD, 0, 0, 0, 0, 1];
+    request.extend(token.to_be_bytes());
+    socket.send(&request).await.unwrap();
+    let mut buf = [0; 1500];
+    tokio::time::timeout(Duration::from_millis(100), socket.recv(&mut buf))
+        .await
+        .is_ok()
+}
+
+#[tokio::test]
+async fn server_round_trips_stats() {
+    let server = QueryServer::bind("127.0.0.1:0", stats()).await.unwrap();
+    let addr = start_query_server(server).await;
+    let mut query = Query::connect(&addr).await.unwrap();
+
+    assert_eq!(query.full_stat().await.unwrap(), stats());
+    let basic = query.basic_stat().await.unwrap();
+    assert_eq!(basic.motd, "A §aMinecraft§r Server");
+    assert_eq!(basic.map, "world");
+    assert_eq!(basic.player_count, 3);
+    assert_eq!(basic.max_player_count, 20);
+    assert_eq!(basic.host_port, 25565);
+    assert_eq!(basic.host_ip, "127.0.0.1");
+}
+
+#[tokio::test]
+async fn server_asks_for_stats_every_time() {
+    let count = AtomicU32::new(0);
+    let server = QueryServer::bind("127.0.0.1:0", move || QueryFullStat {
+        player_count: count.fetch_add(1, Ordering::SeqCst),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let addr = start_query_server(server).await;
+    let mut query = Query::connect(&addr).await.unwrap();
+
+    assert_eq!(query.basic_stat().await.unwrap().player_count, 0);
+    assert_eq!(query.full_stat().await.unwrap().player_count, 1);
+}
+
+#[tokio::test]
+async fn server_ignores_bad_tokens() {
+    let server = QueryServer::bind("127.0.0.1:0", stats())
+        .await
+        .unwrap()
+        .with_challenge_lifetime(Duration::from_millis(300));
+    let addr = start_query_server(server).await;
+
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    socket.connect(addr.to_string()).await.unwrap();
+    let token = raw_handshake(&socket).await;
+    assert!(raw_basic_stat(&socket, token).await);
+    assert!(!raw_basic_stat(&socket, token.wrapping_add(1)).await);
+
+    // tokens belong to the address they were given to
+    let other = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    other.connect(addr.to_string()).await.unwrap();
+    assert!(!raw_basic_stat(&other, token).await);
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(!raw_basic_stat(&socket, token).await);
+
+    // the client notices and gets a new one
+    let mut query = Query::connect(&addr).await.unwrap();
+    query.set_timeout(Some(Duration::from_millis(200)));
+    query.basic_stat().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    query.basic_stat().await.unwrap();
+}
@@ -0,0 +1,364 @@
+//! Runs `RconClient` against a local stand-in for an RCON server that splits
+//! long output the way vanilla does.
+
+use std::{sync::Arc, time::Duration};
+
+use minecraft_utilities::{
+    Error, Phase, RconClient, RconError, RconHandle, RconServer, ServerAddress,
+    MAX_RESPONSE_PAYLOAD,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+async fn read_packet(stream: &mut TcpStream) -> Option<(i32, i32, Vec<u8>)> {
+    let len = stream.read_i32_le().await.ok()?;
+    let mut buf = vec![0; len as usize];
+    stream.read_exact(&mut buf).await.ok()?;
+    let request_id = i32::from_le_bytes(buf[0..4].try_into().unwrap());
+    let request_type = i32::from_le_bytes(buf[4..8].try_into().unwrap());
+    Some((request_id, request_type, buf[8..buf.len() - 2].to_vec()))
+}
+
+async fn write_packet(stream: &mut TcpStream, request_id: i32, request_type: i32, payload: &[u8]) {
+    let mut buf = vec![];
+    buf.extend(((payload.len() + 10) as i32).to_le_bytes());
+    buf.extend(request_id.to_le_bytes());
+    buf.extend(request_type.to_le_bytes());
+    buf.extend(payload);
+    buf.extend(b"\0\0");
+    stream.write_all(&buf).await.unwrap();
+}
+
+/// Long enough to be split over a few packets.
+fn long_output(command: &str) -> String {
+    (0..1000).map(|i| format!("{command} {i}\n")).collect()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Quirks {
+    /// Behave like a Source server: an empty packet before the auth
+    /// response, and two answers to unknown packets.
+    source: bool,
+    /// Close the connection after every command.
+    hang_up: bool,
+    /// Never answer commands.
+    silent: bool,
+    /// Close the connection instead of answering a command.
+    crash: bool,
+}
+
+/// Answers commands with [`long_output`] in 4096 byte pieces.
+async fn run_server(listener: TcpListener, quirks: Quirks) {
+    loop {
+        let (stream, _) = listener.accept().await.unwrap();
+        tokio::spawn(serve_connection(stream, quirks));
+    }
+}
+
+async fn serve_connection(mut stream: TcpStream, quirks: Quirks) {
+    while let Some((request_id, request_type, payload)) = read_packet(&mut stream).await {
+        match request_type {
+            3 if payload == b"hunter2" => {
+                if quirks.source {
+                    write_packet(&mut stream, request_id, 0, b"").await;
+                }
+                write_packet(&mut stream, request_id, 2, b"").await;
+            }
+            3 => write_packet(&mut stream, -1, 2, b"").await,
+            _ if quirks.silent => {}
+            2 if quirks.crash => return,
+            2 => {
+                let output = long_output(&String::from_utf8(payload).unwrap());
+                for chunk in output.as_bytes().chunks(4096) {
+                    write_packet(&mut stream, request_id, 0, chunk).await;
+                }
+            }
+            _ => {
+                if quirks.source {
+                    write_packet(&mut stream, request_id, 0, b"").await;
+                    write_packet(&mut stream, request_id, 0, b"\x00\x01\x00\x00").await;
+                } else {
+                    let answer = format!("Unknown request {request_type:x}");
+                    write_packet(&mut stream, request_id, 0, answer.as_bytes()).await;
+                }
+                if quirks.hang_up {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn start_server(quirks: Quirks) -> ServerAddress {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = ServerAddress::from(listener.local_addr().unwrap());
+    tokio::spawn(run_server(listener, quirks));
+    addr
+}
+
+#[tokio::test]
+async fn reassembles_long_output() {
+    let addr = start_server(Quirks::default()).await;
+    let mut client = RconClient::connect(&addr, Some("hunter2")).await.unwrap();
+
+    assert_eq!(client.command("help").await.unwrap(), long_output("help"));
+    assert_eq!(client.command("help").await.unwrap(), long_output("help"));
+}
+
+#[tokio::test]
+async fn handles_source_servers() {
+    let addr = start_server(Quirks {
+        source: true,
+        ..Quirks::default()
+    })
+    .await;
+    let mut client = RconClient::connect(&addr, Some("hunter2")).await.unwrap();
+
+    for _ in 0..3 {
+        assert_eq!(client.command("help").await.unwrap(), long_output("help"));
+    }
+}
+
+#[tokio::test]
+async fn rejects_wrong_password() {
+    let addr = start_server(Quirks::default()).await;
+    let err = RconClient::connect(&addr, Some("hunter3"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Rcon(RconError::AuthenticationFailure)));
+}
+
+#[tokio::test]
+async fn reconnects_after_hang_up() {
+    let addr = start_server(Quirks {
+        hang_up: true,
+        ..Quirks::default()
+    })
+    .await;
+    let mut client = RconClient::connect(&addr, Some("hunter2")).await.unwrap();
+
+    for _ in 0..3 {
+        assert_eq!(client.command("help").await.unwrap(), long_output("help"));
+    }
+    client.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn times_out() {
+    let addr = start_server(Quirks {
+        silent: true,
+        ..Quirks::default()
+    })
+    .await;
+    let mut client = RconClient::connect(&addr, Some("hunter2")).await.unwrap();
+    client.set_timeout(Some(Duration::from_millis(100)));
+
+    let err = client.command("help").await.unwrap_err();
+    assert!(err.is_timeout());
+    assert_eq!(err.phase(), Some(Phase::Rcon));
+}
+
+#[tokio::test]
+async fn handle_runs_commands_concurrently() {
+    let addr = start_server(Quirks::default()).await;
+    let rcon = RconHandle::connect(&addr, "hunter2").await.unwrap();
+
+    let tasks: Vec<_> = (0..20)
+        .map(|i| {
+            let rcon = rcon.clone();
+            tokio::spawn(async move {
+                let command = format!("say {i}");
+                assert_eq!(rcon.command(&command).await.unwrap(), long_output(&command));
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn handle_reconnects_after_hang_up() {
+    let addr = start_server(Quirks {
+        hang_up: true,
+        source: true,
+        ..Quirks::default()
+    })
+    .await;
+    let rcon = RconHandle::connect(&addr, "hunter2").await.unwrap();
+
+    assert_eq!(rcon.command("help").await.unwrap(), long_output("help"));
+    // the connection has gone away, so either this fails or it reconnected
+    // before sending
+    match rcon.command("help").await {
+        Ok(output) => assert_eq!(output, long_output("help")),
+        Err(Error::Io {
+            phase: Phase::Rcon,
+            source,
+        }) => {
+            // the error that dropped the connection
+            let cause = source
+                .get_ref()
+                .and_then(|err| err.downcast_ref::<Arc<Error>>());
+            assert_eq!(cause.unwrap().phase(), Some(Phase::Rcon), "{source:?}");
+        }
+        Err(err) => panic!("unexpected error {err:?}"),
+    }
+    assert_eq!(rcon.command("help").await.unwrap(), long_output("help"));
+}
+
+#[tokio::test]
+async fn handle_fails_commands_when_the_connection_drops() {
+    let addr = start_server(Quirks {
+        crash: true,
+        ..Quirks::default()
+    })
+    .await;
+    let rcon = RconHandle::connect(&addr, "hunter2").await.unwrap();
+
+    let (first, second) = tokio::join!(rcon.command("stop"), rcon.command("list"));
+    let causes: Vec<_> = [first, second]
+        .into_iter()
+        .map(|res| {
+            let Err(Error::Io {
+                phase: Phase::Rcon,
+                source,
+            }) = res
+            else {
+                panic!("expected a connection error, got {res:?}");
+            };
+            // the error that dropped the connection
+            let cause = source
+                .get_ref()
+                .and_then(|err| err.downcast_ref::<Arc<Error>>());
+            let cause = Arc::clone(cause.expect("no cause"));
+            let Error::Io { source: inner, .. } = &*cause else {
+                panic!("expected an io error, got {cause:?}");
+            };
+            assert_eq!(source.kind(), inner.kind());
+            cause
+        })
+        .collect();
+    assert!(Arc::ptr_eq(&causes[0], &causes[1]));
+}
+
+#[tokio::test]
+async fn handle_reconnects_after_a_timeout() {
+    // the first connection logs in but never answers a command
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = ServerAddress::from(listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        for connection in 0.. {
+            let (stream, _) = listener.accept().await.unwrap();
+            let quirks = Quirks {
+                silent: connection == 0,
+                ..Quirks::default()
+            };
+            tokio::spawn(serve_connection(stream, quirks));
+        }
+    });
+    let mut client = RconClient::connect(&addr, Some("hunter2")).await.unwrap();
+    client.set_timeout(Some(Duration::from_millis(200)));
+    let rcon = client.into_handle().unwrap();
+
+    let (first, second) = tokio::join!(rcon.command("list"), rcon.command("seed"));
+    assert!(first.unwrap_err().is_timeout());
+    assert!(second.unwrap_err().is_timeout());
+    assert_eq!(rcon.command("help").await.unwrap(), long_output("help"));
+}
+
+async fn start_rcon_server() -> ServerAddress {
+    let server = RconServer::bind("127.0.0.1:0", "hunter2", |command: String| async move {
+        match command.as_str() {
+            "nothing" => String::new(),
+            command => long_output(command),
+        }
+    })
+    .await
+    .unwrap()
+    .with_max_command_payload(100);
+    let addr = ServerAddress::from(server.local_addr().unwrap());
+    tokio::spawn(server.run());
+    addr
+}
+
+#[tokio::test]
+async fn server_answers_client() {
+    let addr = start_rcon_server().await;
+    let mut client = RconClient::connect(&addr, Some("hunter2")).await.unwrap();
+
+    assert_eq!(client.command("help").await.unwrap(), long_output("help"));
+    assert_eq!(client.command("nothing").await.unwrap(), "");
+
+    let err = RconClient::connect(&addr, Some("hunter3"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Rcon(RconError::AuthenticationFailure)));
+}
+
+#[tokio::test]
+async fn server_proxies_to_another_server() {
+    let backend = RconHandle::connect(&start_rcon_server().await, "hunter2")
+        .await
+        .unwrap();
+    let gateway = RconServer::bind("127.0.0.1:0", "gateway", move |command: String| {
+        let backend = backend.clone();
+        async move { backend.command(&command).await.unwrap() }
+    })
+    .await
+    .unwrap();
+    let addr = ServerAddress::from(gateway.local_addr().unwrap());
+    tokio::spawn(gateway.run());
+
+    let mut client = RconClient::connect(&addr, Some("gateway")).await.unwrap();
+    assert_eq!(client.command("list").await.unwrap(), long_output("list"));
+}
+
+#[tokio::test]
+async fn server_drops_oversized_commands() {
+    let addr = start_rcon_server().await;
+    let mut client = RconClient::connect(&addr, Some("hunter2")).await.unwrap();
+
+    let err = client.command(&"a".repeat(101)).await.unwrap_err();
+    assert!(matches!(err, Error::Io { .. }), "{err:?}");
+}
+
+#[tokio::test]
+async fn server_splits_output_between_characters() {
+    // one byte of ASCII puts every later packet boundary inside a character
+    let output = format!("a{}", "é€𝄞".repeat(1000));
+    let server = RconServer::bind("127.0.0.1:0", "hunter2", {
+        let output = output.clone();
+        move |_: String| {
+            let output = output.clone();
+            async move { output }
+        }
+    })
+    .await
+    .unwrap();
+    let addr = server.local_addr().unwrap();
+    tokio::spawn(server.run());
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    write_packet(&mut stream, 1, 3, b"hunter2").await;
+    assert_eq!(read_packet(&mut stream).await.unwrap().0, 1);
+    write_packet(&mut stream, 2, 2, b"list").await;
+    let mut received = String::new();
+    let mut packets = 0;
+    while received.len() < output.len() {
+        let (request_id, _, payload) = read_packet(&mut stream).await.unwrap();
+        assert_eq!(request_id, 2);
+        assert!(payload.len() <= MAX_RESPONSE_PAYLOAD);
+        received.push_str(std::str::from_utf8(&payload).unwrap());
+        packets += 1;
+    }
+    assert_eq!(received, output);
+    assert!(packets > 1);
+
+    let mut client = RconClient::connect(&ServerAddress::from(addr), Some("hunter2"))
+        .await
+        .unwrap();
+    assert_eq!(client.command("list").await.unwrap(), output);
+}
@@ -0,0 +1,183 @@
+//! Throws random and malformed bytes at `RconPacket::parse` and `RconCodec`,
+//! which read whatever a server (or a client, for `RconServer`) sends.
+
+use bytes::BytesMut;
+use minecraft_utilities::{Error, RconCodec, RconError, RconPacket};
+use tokio_util::codec::Decoder;
+
+/// xorshift64, so failures can be reproduced.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, max: usize) -> usize {
+        (self.next() % max as u64) as usize
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+fn framed(request_id: i32, request_type: i32, payload: &[u8]) -> Vec<u8> {
+    let mut buf = vec![];
+    buf.extend(((payload.len() + 10) as i32).to_le_bytes());
+    buf.extend(request_id.to_le_bytes());
+    buf.extend(request_type.to_le_bytes());
+    buf.extend(payload);
+    buf.extend(b"\0\0");
+    buf
+}
+
+fn inner_error(err: &std::io::Error) -> &Error {
+    err.get_ref()
+        .and_then(|inner| inner.downcast_ref::<Error>())
+        .expect("codec errors carry an Error")
+}
+
+#[tokio::test]
+async fn parse_survives_random_bytes() {
+    let mut rng = Rng(0x5eed);
+    for _ in 0..10_000 {
+        let len = rng.below(64);
+        let raw = rng.bytes(len);
+        let mut packet = RconPacket::new();
+        match packet.parse(&raw).await {
+            Ok(()) => {
+                assert!(raw.len() >= 10);
+                assert!(
+                    packet.payload.len() == raw.len() - 9 || packet.payload.len() == raw.len() - 10
+                );
+                assert_eq!(raw[raw.len() - 1], 0);
+            }
+            Err(RconError::InvalidLength(found)) => {
+                assert!(raw.len() < 10);
+                assert_eq!(found, raw.len() as i64);
+            }
+            Err(RconError::MissingTerminator) => assert_ne!(raw[raw.len() - 1], 0),
+            Err(err) => panic!("unexpected error {err:?}"),
+        }
+        // whatever came out, turning it into text is fine
+        packet.payload_to_string();
+        packet.payload_to_plain_string();
+    }
+}
+
+#[tokio::test]
+async fn build_and_parse_round_trip() {
+    let mut rng = Rng(0xcafe);
+    for _ in 0..1_000 {
+        let len = rng.below(5000);
+        let original = RconPacket {
+            request_id: rng.next() as i32,
+            request_type: rng.next() as i32,
+            payload: rng.bytes(len),
+        };
+        let built = original.build().await.unwrap();
+        assert_eq!(
+            built,
+            framed(
+                original.request_id,
+                original.request_type,
+                &original.payload
+            )
+        );
+
+        let mut parsed = RconPacket::new();
+        parsed.parse(&built[4..]).await.unwrap();
+        assert_eq!(parsed.request_id, original.request_id);
+        assert_eq!(parsed.request_type, original.request_type);
+        assert_eq!(parsed.payload, original.payload);
+    }
+}
+
+#[test]
+fn codec_survives_random_streams() {
+    let mut rng = Rng(0xdead);
+    for _ in 0..2_000 {
+        let mut codec = RconCodec::with_max_payload(256);
+        let mut buf = BytesMut::new();
+        // mix well formed packets with garbage
+        for _ in 0..rng.below(4) {
+            if rng.below(2) == 0 {
+                let len = rng.below(300);
+                buf.extend(framed(rng.next() as i32, 0, &rng.bytes(len)));
+            } else {
+                let len = rng.below(32);
+                buf.extend(rng.bytes(len));
+            }
+        }
+        loop {
+            match codec.decode(&mut buf) {
+                Ok(Some(packet)) => assert!(packet.payload.len() <= 256),
+                Ok(None) => break,
+                Err(err) => {
+                    assert!(matches!(
+                        inner_error(&err),
+                        Error::PacketTooLarge { .. }
+                            | Error::Rcon(RconError::InvalidLength(_))
+                            | Error::Rcon(RconError::MissingTerminator)
+                    ));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn codec_waits_for_whole_packets() {
+    let packet = framed(7, 0, "§aHéllo wörld".as_bytes());
+    for split in 0..packet.len() {
+        let mut codec = RconCodec::new();
+        let mut buf = BytesMut::from(&packet[..split]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        buf.extend_from_slice(&packet[split..]);
+        let decoded = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(decoded.request_id, 7);
+        assert_eq!(decoded.payload_to_string(), "§aHéllo wörld");
+        assert_eq!(decoded.payload_to_plain_string(), "Héllo wörld");
+        assert!(buf.is_empty());
+    }
+}
+
+#[test]
+fn codec_rejects_bad_lengths() {
+    let mut codec = RconCodec::new();
+
+    let mut buf = BytesMut::from(&(-5i32).to_le_bytes()[..]);
+    let err = codec.decode(&mut buf).unwrap_err();
+    assert!(matches!(
+        inner_error(&err),
+        Error::Rcon(RconError::InvalidLength(-5))
+    ));
+
+    let mut buf = BytesMut::from(&3i32.to_le_bytes()[..]);
+    let err = codec.decode(&mut buf).unwrap_err();
+    assert!(matches!(
+        inner_error(&err),
+        Error::Rcon(RconError::InvalidLength(3))
+    ));
+
+    // a huge length is refused before anything is allocated for it
+    let mut buf = BytesMut::from(&i32::MAX.to_le_bytes()[..]);
+    let err = codec.decode(&mut buf).unwrap_err();
+    assert!(matches!(inner_error(&err), Error::PacketTooLarge { .. }));
+    assert!(buf.capacity() < 1024);
+}
+
+#[test]
+fn invalid_utf8_is_replaced() {
+    let packet = RconPacket {
+        request_id: 0,
+        request_type: 0,
+        payload: vec![b'o', b'k', 0xff, 0xc3],
+    };
+    assert_eq!(packet.payload_to_string(), "ok\u{fffd}\u{fffd}");
+}
@@ -0,0 +1,278 @@
+//! Runs sessions against a local stand-in server, which checks the session
+//! answers Keep Alive and Ping, gets through configuration and notices being
+//! kicked.
+
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use minecraft_utilities::{
+    nbt::{NetworkTag, Tag},
+    packets::{
+        configuration::{self, KnownPack},
+        handshake::Handshake,
+        login, play, Packet,
+    },
+    Client, MinecraftCodec, MinecraftPacket, PacketSender, PacketStream, ProtocolState,
+    ServerAddress, SessionError, PROTOCOL_1_20, PROTOCOL_1_20_3, PROTOCOL_1_20_5,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::Framed;
+use uuid::Uuid;
+
+type Connection = Framed<TcpStream, MinecraftCodec>;
+
+struct Server {
+    connection: Connection,
+    protocol_version: i32,
+}
+
+impl Server {
+    async fn send(&mut self, packet: impl Packet) {
+        let packet = packet.to_packet(self.protocol_version).unwrap();
+        self.connection.send(packet).await.unwrap();
+    }
+
+    async fn send_raw(&mut self, packet_id: i32, buffer: &[u8]) {
+        self.connection
+            .send(MinecraftPacket {
+                packet_id,
+                buffer: buffer.to_vec(),
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn read_raw(&mut self) -> MinecraftPacket {
+        tokio::time::timeout(Duration::from_secs(5), self.connection.next())
+            .await
+            .expect("the client didn't answer")
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn read<P: Packet>(&mut self) -> P {
+        let packet = self.read_raw().await;
+        P::from_packet(&packet, self.protocol_version).unwrap()
+    }
+}
+
+/// Log a client in to a stand-in server and start its session.
+async fn start(protocol_version: i32) -> (Server, PacketStream, PacketSender) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = ServerAddress::from(listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = Server {
+            connection: Framed::new(stream, MinecraftCodec::new()),
+            protocol_version,
+        };
+        let handshake: Handshake = server.read().await;
+        assert_eq!(handshake.protocol_version.0, protocol_version);
+        let login_start: login::LoginStart = server.read().await;
+        server
+            .send(login::LoginSuccess {
+                uuid: login_start.uuid,
+                uuid_string: login_start.uuid.hyphenated().to_string(),
+                username: login_start.name,
+                properties: vec![],
+                strict_error_handling: false,
+            })
+            .await;
+        if login::LoginAcknowledged::id(protocol_version).is_some() {
+            let _: login::LoginAcknowledged = server.read().await;
+        }
+        server
+    });
+
+    let mut client = Client::connect(&address).await.unwrap();
+    client
+        .join(Some(protocol_version), None, None, None, Some(Uuid::nil()))
+        .await
+        .unwrap();
+    let (sender, stream) = client.into_session().unwrap();
+    (server.await.unwrap(), stream, sender)
+}
+
+async fn next(stream: &mut PacketStream) -> Option<Result<MinecraftPacket, SessionError>> {
+    tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("the session didn't yield anything")
+}
+
+#[tokio::test]
+async fn answers_keep_alive_and_ping_in_play() {
+    let (mut server, mut stream, _sender) = start(PROTOCOL_1_20).await;
+    assert_eq!(stream.state(), ProtocolState::Play);
+
+    server
+        .send(play::ClientboundKeepAlive { id: -1234567890123 })
+        .await;
+    let keep_alive: play::ServerboundKeepAlive = server.read().await;
+    assert_eq!(keep_alive.id, -1234567890123);
+
+    server.send(play::Ping { id: 77 }).await;
+    let pong: play::Pong = server.read().await;
+    assert_eq!(pong.id, 77);
+
+    // anything else goes to the stream, in order
+    server.send_raw(0x28, b"join game").await;
+    server.send(play::ClientboundKeepAlive { id: 1 }).await;
+    server.send_raw(0x64, b"chat").await;
+    assert_eq!(server.read::<play::ServerboundKeepAlive>().await.id, 1);
+    let packet = next(&mut stream).await.unwrap().unwrap();
+    assert_eq!(
+        (packet.packet_id, packet.buffer.as_slice()),
+        (0x28, &b"join game"[..])
+    );
+    let packet = next(&mut stream).await.unwrap().unwrap();
+    assert_eq!(packet.packet_id, 0x64);
+}
+
+#[tokio::test]
+async fn configures_then_plays() {
+    let protocol_version = PROTOCOL_1_20_5;
+    let (mut server, mut stream, sender) = start(protocol_version).await;
+    assert_eq!(stream.state(), ProtocolState::Configuration);
+
+    // configuration has its own keep alive and ping ids
+    server
+        .send(configuration::ClientboundKeepAlive { id: 5 })
+        .await;
+    assert_eq!(
+        server
+            .read::<configuration::ServerboundKeepAlive>()
+            .await
+            .id,
+        5
+    );
+    server.send(configuration::Ping { id: 6 }).await;
+    assert_eq!(server.read::<configuration::Pong>().await.id, 6);
+
+    server
+        .send(configuration::ClientboundKnownPacks {
+            packs: vec![KnownPack {
+                namespace: "minecraft".to_string(),
+                id: "core".to_string(),
+                version: "1.20.5".to_string(),
+            }],
+        })
+        .await;
+    let known_packs: configuration::ServerboundKnownPacks = server.read().await;
+    assert!(known_packs.packs.is_empty());
+
+    // Registry Data is the caller's business
+    server.send_raw(0x07, b"registry").await;
+    let packet = next(&mut stream).await.unwrap().unwrap();
+    assert_eq!(packet.packet_id, 0x07);
+    assert_eq!(stream.state(), ProtocolState::Configuration);
+
+    server.send(configuration::FinishConfiguration {}).await;
+    let _: configuration::AcknowledgeFinishConfiguration = server.read().await;
+
+    // the same ids mean something else in play
+    server.send_raw(0x04, b"play packet").await;
+    let packet = next(&mut stream).await.unwrap().unwrap();
+    assert_eq!(packet.packet_id, 0x04);
+    assert_eq!(stream.state(), ProtocolState::Play);
+
+    server.send(play::ClientboundKeepAlive { id: 8 }).await;
+    assert_eq!(server.read::<play::ServerboundKeepAlive>().await.id, 8);
+
+    sender.send(0x06, b"from the caller").await.unwrap();
+    let packet = server.read_raw().await;
+    assert_eq!(
+        (packet.packet_id, packet.buffer.as_slice()),
+        (0x06, &b"from the caller"[..])
+    );
+}
+
+#[tokio::test]
+async fn reconfigures_when_sent_back_from_play() {
+    let protocol_version = PROTOCOL_1_20_3;
+    let (mut server, mut stream, _sender) = start(protocol_version).await;
+    server.send(configuration::FinishConfiguration {}).await;
+    let _: configuration::AcknowledgeFinishConfiguration = server.read().await;
+    server.send_raw(0x29, b"login").await;
+    assert_eq!(next(&mut stream).await.unwrap().unwrap().packet_id, 0x29);
+    assert_eq!(stream.state(), ProtocolState::Play);
+
+    // what Velocity does when switching servers
+    server.send(play::StartConfiguration {}).await;
+    let _: play::AcknowledgeConfiguration = server.read().await;
+
+    // back to configuration ids
+    server
+        .send(configuration::ClientboundKeepAlive { id: 3 })
+        .await;
+    assert_eq!(
+        server
+            .read::<configuration::ServerboundKeepAlive>()
+            .await
+            .id,
+        3
+    );
+    server.send_raw(0x05, b"registry").await;
+    assert_eq!(next(&mut stream).await.unwrap().unwrap().packet_id, 0x05);
+    assert_eq!(stream.state(), ProtocolState::Configuration);
+
+    server.send(configuration::FinishConfiguration {}).await;
+    let _: configuration::AcknowledgeFinishConfiguration = server.read().await;
+    server.send(play::ClientboundKeepAlive { id: 4 }).await;
+    assert_eq!(server.read::<play::ServerboundKeepAlive>().await.id, 4);
+    server.send_raw(0x29, b"login").await;
+    assert_eq!(next(&mut stream).await.unwrap().unwrap().packet_id, 0x29);
+    assert_eq!(stream.state(), ProtocolState::Play);
+}
+
+#[tokio::test]
+async fn disconnect_in_play_ends_the_stream() {
+    let (mut server, mut stream, _sender) = start(PROTOCOL_1_20).await;
+    server
+        .send(play::Disconnect {
+            reason_json: r#"{"text":"Server closed"}"#.to_string(),
+            reason_nbt: NetworkTag::default(),
+        })
+        .await;
+
+    match next(&mut stream).await {
+        Some(Err(SessionError::Disconnected(reason))) => {
+            assert_eq!(reason.to_plain(), "Server closed")
+        }
+        other => panic!("expected a disconnect, got {other:?}"),
+    }
+    assert!(next(&mut stream).await.is_none());
+}
+
+#[tokio::test]
+async fn disconnect_in_configuration() {
+    let (mut server, mut stream, _sender) = start(PROTOCOL_1_20_3).await;
+    server
+        .send(configuration::Disconnect {
+            reason_json: String::new(),
+            reason_nbt: NetworkTag(Some(Tag::String("Bad registries".to_string()))),
+        })
+        .await;
+
+    match next(&mut stream).await {
+        Some(Err(SessionError::Disconnected(reason))) => {
+            assert_eq!(reason.to_plain(), "Bad registries")
+        }
+        other => panic!("expected a disconnect, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn losing_the_connection_ends_the_stream() {
+    let (server, mut stream, sender) = start(PROTOCOL_1_20).await;
+    drop(server);
+
+    assert!(matches!(
+        next(&mut stream).await,
+        Some(Err(SessionError::ConnectionLost(_)))
+    ));
+    assert!(next(&mut stream).await.is_none());
+    assert!(matches!(
+        sender.send(0x00, &[]).await,
+        Err(SessionError::Closed)
+    ));
+}
@@ -0,0 +1,103 @@
+//! Parses status documents the way real servers and plugins send them,
+//! including broken bits that shouldn't fail the whole ping.
+
+use minecraft_utilities::StatusResponse;
+use uuid::Uuid;
+
+#[test]
+fn keeps_placeholder_sample_ids() {
+    let status = StatusResponse::from_json(
+        r#"{
+            "players": {
+                "max": 100,
+                "online": 3,
+                "sample": [
+                    { "name": "§6Welcome to the server!", "id": "00000000-0000-0000-0000-000000000000" },
+                    { "name": "Shrecknt", "id": "b64dfb9c-82ec-426d-918c-73f62afc4e01" },
+                    { "name": "line three", "id": "" },
+                    { "name": "no id at all" }
+                ]
+            }
+        }"#,
+    )
+    .unwrap();
+    let sample = status.players.unwrap().sample;
+    assert_eq!(sample.len(), 4);
+    assert_eq!(sample[0].uuid(), Some(Uuid::nil()));
+    assert_eq!(
+        sample[1].uuid(),
+        Some(Uuid::parse_str("b64dfb9c-82ec-426d-918c-73f62afc4e01").unwrap())
+    );
+    assert_eq!(sample[2].uuid(), None);
+    assert_eq!(sample[3].id, "");
+}
+
+#[test]
+fn skips_malformed_sample_entries() {
+    let status = StatusResponse::from_json(
+        r#"{
+            "version": { "name": "1.20.4", "protocol": 765 },
+            "players": {
+                "max": 20,
+                "online": 2,
+                "sample": [
+                    "just a string",
+                    { "name": 42, "id": "nope" },
+                    null,
+                    { "name": "Notch", "id": "069a79f4-44e9-4726-a5be-fca90e38aaf5" }
+                ]
+            }
+        }"#,
+    )
+    .unwrap();
+    let players = status.players.unwrap();
+    assert_eq!(players.online, 2);
+    assert_eq!(players.sample.len(), 1);
+    assert_eq!(players.sample[0].name, "Notch");
+
+    // not even a list
+    let status =
+        StatusResponse::from_json(r#"{ "players": { "max": 1, "online": 0, "sample": {} } }"#)
+            .unwrap();
+    assert!(status.players.unwrap().sample.is_empty());
+}
+
+#[test]
+fn skips_malformed_forge_entries() {
+    let status = StatusResponse::from_json(
+        r#"{
+            "forgeData": {
+                "channels": [
+                    { "res": "forge:handshake", "version": "1", "required": true },
+                    { "res": "mymod:main" },
+                    7
+                ],
+                "mods": [
+                    { "modId": "forge", "modmarker": "ANY" },
+                    { "modId": "jei" },
+                    { "modmarker": 3 },
+                    "minecraft"
+                ],
+                "fmlNetworkVersion": 3
+            },
+            "modinfo": {
+                "type": "FML",
+                "modList": [{ "modid": "mcp", "version": "9.42" }, { "modid": "noversion" }, false]
+            }
+        }"#,
+    )
+    .unwrap();
+    assert!(status.has_mod_loader());
+
+    let forge = status.forge_data.unwrap();
+    assert_eq!(forge.channels.len(), 2);
+    assert_eq!(forge.channels[1].version, "");
+    assert_eq!(forge.mods.len(), 2);
+    assert_eq!(forge.mods[1].mod_id, "jei");
+    assert_eq!(forge.mods[1].mod_marker, "");
+    assert_eq!(forge.fml_network_version, 3);
+
+    let mod_list = status.modinfo.unwrap().mod_list;
+    assert_eq!(mod_list.len(), 2);
+    assert_eq!(mod_list[1].mod_id, "noversion");
+}